@@ -7,7 +7,12 @@ use serde::{Deserialize, Serialize};
 pub enum Agent {
     Claude,
     Codex,
+    Gemini,
+    Opencode,
+    Aider,
     Generic,
+    /// A tool declared under `custom_agents` in the local config; see `ingest --name`.
+    Custom,
 }
 
 impl Agent {
@@ -15,7 +20,10 @@ impl Agent {
         match self {
             Agent::Claude => "Claude",
             Agent::Codex => "Codex",
-            Agent::Generic => "Agent",
+            Agent::Gemini => "Gemini",
+            Agent::Opencode => "OpenCode",
+            Agent::Aider => "Aider",
+            Agent::Generic | Agent::Custom => "Agent",
         }
     }
 }
@@ -25,5 +33,19 @@ impl Agent {
 pub enum SetupAgent {
     Claude,
     Codex,
+    Gemini,
     Opencode,
+    Aider,
+}
+
+impl From<SetupAgent> for Agent {
+    fn from(agent: SetupAgent) -> Self {
+        match agent {
+            SetupAgent::Claude => Agent::Claude,
+            SetupAgent::Codex => Agent::Codex,
+            SetupAgent::Gemini => Agent::Gemini,
+            SetupAgent::Opencode => Agent::Opencode,
+            SetupAgent::Aider => Agent::Aider,
+        }
+    }
 }
@@ -1,6 +1,12 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 use agitiser_notify::agent::{Agent, SetupAgent};
+use agitiser_notify::output::OutputFormat;
+use agitiser_notify::paths::SettingsScope;
+use agitiser_notify::rules::{RuleAction, Sink};
+use agitiser_notify::speech::{SpeechPriority, VoiceType};
+use agitiser_notify::stats::GroupBy;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -9,6 +15,12 @@ use agitiser_notify::agent::{Agent, SetupAgent};
     about = "Agent task completion speech notifier"
 )]
 pub struct Cli {
+    /// Use DIR/claude, DIR/codex and DIR/agitiser-notify instead of the default config locations.
+    #[arg(long, global = true, value_name = "DIR")]
+    pub config_dir: Option<PathBuf>,
+    /// Print results as text, or as one JSON document with an id and status per result.
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -27,6 +39,10 @@ pub enum Commands {
             default_values_t = [SetupAgent::Claude, SetupAgent::Codex]
         )]
         agents: Vec<SetupAgent>,
+        #[arg(long, value_enum, default_value_t = SettingsScope::User)]
+        scope: SettingsScope,
+        #[arg(long)]
+        dir: Option<PathBuf>,
     },
     Remove {
         #[arg(
@@ -36,10 +52,30 @@ pub enum Commands {
             default_values_t = [SetupAgent::Claude, SetupAgent::Codex]
         )]
         agents: Vec<SetupAgent>,
+        #[arg(long, value_enum, default_value_t = SettingsScope::User)]
+        scope: SettingsScope,
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// Point managed integration entries at this binary after it moved or was reinstalled
+    Relink {
+        /// Project directory for the project and local scopes
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Install the bare `agitiser-notify` name, looked up on PATH, from now on
+        #[arg(long, conflicts_with = "absolute")]
+        use_path: bool,
+        /// Install the absolute path of this binary from now on (the default; project scope
+        /// entries always use the bare name)
+        #[arg(long)]
+        absolute: bool,
     },
     Ingest {
         #[arg(long, value_enum)]
         agent: Agent,
+        /// Custom agent declared under `custom_agents` in the local config
+        #[arg(long, value_name = "NAME", required_if_eq("agent", "custom"))]
+        name: Option<String>,
         #[arg(long)]
         payload: Option<String>,
         #[arg(index = 1)]
@@ -49,7 +85,74 @@ pub enum Commands {
         #[arg(long, default_value_t = false)]
         verbose: bool,
     },
-    Doctor,
+    Run {
+        /// Spoken name instead of the program name
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long, default_value_t = false)]
+        verbose: bool,
+        /// Command and arguments, after `--`
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "COMMAND"
+        )]
+        command: Vec<String>,
+    },
+    /// Hold announcements back for `digest` until `unmute` (or for a while)
+    Mute {
+        /// How long to stay muted (30m, 2h, ...); without it, until `unmute`
+        #[arg(long = "for", value_name = "DURATION")]
+        duration: Option<String>,
+    },
+    Unmute,
+    /// Speak one summary of the announcements held back while muted, in quiet hours or
+    /// while the screen was locked, then clear them
+    Digest {
+        /// Print the summary without speaking it or clearing the queue
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    Last {
+        /// Number of announcements to replay, oldest first
+        #[arg(long, default_value_t = 1)]
+        n: usize,
+        /// Speak them again instead of only printing them
+        #[arg(long, default_value_t = false)]
+        speak: bool,
+    },
+    History {
+        #[arg(long, value_enum)]
+        agent: Option<Agent>,
+        /// Project name (case-insensitive)
+        #[arg(long)]
+        project: Option<String>,
+        /// Only events from this long ago or newer (30m, 2h, 1d, ...)
+        #[arg(long, value_name = "DURATION")]
+        since: Option<String>,
+        /// Newest entries to show; 0 shows all
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Counts, median turn durations and busiest projects from the event history
+    Stats {
+        /// Only events from this long ago or newer (7d, 12h, ...)
+        #[arg(long, value_name = "DURATION")]
+        since: Option<String>,
+        #[arg(long, value_enum, default_value_t = GroupBy::Project)]
+        by: GroupBy,
+    },
+    Doctor {
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Repair what can be repaired by re-running the relevant setup step
+        #[arg(long)]
+        fix: bool,
+        /// Also send a test utterance to speech-dispatcher
+        #[arg(long)]
+        speak: bool,
+    },
     Config {
         #[command(subcommand)]
         command: ConfigCommand,
@@ -80,23 +183,70 @@ pub enum ConfigCommand {
         #[command(subcommand)]
         command: SubagentCommand,
     },
+    ClaudeHooks {
+        #[command(subcommand)]
+        command: ClaudeHooksCommand,
+    },
+    CodexChain {
+        #[command(subcommand)]
+        command: CodexChainCommand,
+    },
+    Summary {
+        #[command(subcommand)]
+        command: SummaryCommand,
+    },
+    MinDuration {
+        #[command(subcommand)]
+        command: MinDurationCommand,
+    },
+    Generic {
+        #[command(subcommand)]
+        command: GenericCommand,
+    },
+    Speech {
+        #[command(subcommand)]
+        command: SpeechCommand,
+    },
+    Quiet {
+        #[command(subcommand)]
+        command: QuietCommand,
+    },
+    RateLimit {
+        #[command(subcommand)]
+        command: RateLimitCommand,
+    },
+    Dedup {
+        #[command(subcommand)]
+        command: DedupCommand,
+    },
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
 }
 
+/// `--digest` selects the template spoken by `digest` (`{{events}}`, `{{count}}`).
 #[derive(Debug, Subcommand)]
 pub enum TemplateCommand {
     Get {
         #[arg(long, value_enum)]
         agent: Option<Agent>,
+        #[arg(long, conflicts_with = "agent")]
+        digest: bool,
     },
     Set {
         #[arg(long, value_enum)]
         agent: Option<Agent>,
+        #[arg(long, conflicts_with = "agent")]
+        digest: bool,
         #[arg(long)]
         value: String,
     },
     Reset {
         #[arg(long, value_enum)]
         agent: Option<Agent>,
+        #[arg(long, conflicts_with = "agent")]
+        digest: bool,
     },
 }
 
@@ -132,3 +282,224 @@ pub enum SubagentCommand {
         enabled: bool,
     },
 }
+
+#[derive(Debug, Subcommand)]
+pub enum MinDurationCommand {
+    Get {
+        #[arg(long, value_enum)]
+        agent: Option<Agent>,
+    },
+    Set {
+        #[arg(long, value_enum)]
+        agent: Option<Agent>,
+        #[arg(long)]
+        value: String,
+    },
+    Reset {
+        #[arg(long, value_enum)]
+        agent: Option<Agent>,
+    },
+}
+
+/// Field paths are dotted (`status.phase`) or JSON pointers (`/status/phase`); pass an
+/// empty value to fall back to the built-in keys again.
+#[derive(Debug, Subcommand)]
+pub enum GenericCommand {
+    Get,
+    Set(GenericSetArgs),
+    Reset,
+}
+
+#[derive(Debug, Args)]
+pub struct GenericSetArgs {
+    #[arg(long)]
+    pub kind_field: Option<String>,
+    #[arg(long)]
+    pub cwd_field: Option<String>,
+    #[arg(long)]
+    pub project_field: Option<String>,
+    #[arg(long)]
+    pub agent_name_field: Option<String>,
+    #[arg(long)]
+    pub message_field: Option<String>,
+    /// Comma-separated kinds that are announced (replaces the current list)
+    #[arg(long, value_delimiter = ',')]
+    pub terminal_kinds: Option<Vec<String>>,
+    /// Regex matched against the kind
+    #[arg(long)]
+    pub terminal_pattern: Option<String>,
+}
+
+/// Per-kind spd-say priority, voice and earcon; `task-failed` and `error` default to
+/// `important` priority and the freedesktop `dialog-error` sound.
+#[derive(Debug, Subcommand)]
+pub enum SpeechCommand {
+    Get {
+        #[arg(long)]
+        kind: String,
+    },
+    Set {
+        #[arg(long)]
+        kind: String,
+        #[arg(long, value_enum)]
+        priority: Option<SpeechPriority>,
+        #[arg(long, value_enum)]
+        voice: Option<VoiceType>,
+        /// Sound file played before the announcement; pass an empty value to disable it
+        #[arg(long)]
+        earcon: Option<String>,
+    },
+    Reset {
+        #[arg(long)]
+        kind: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QuietCommand {
+    Get,
+    Set {
+        /// Local HH:MM-HH:MM window, for example 22:00-07:00; pass an empty value to clear it
+        #[arg(long)]
+        hours: Option<String>,
+        /// Also hold announcements back while the session is locked (asks loginctl)
+        #[arg(long, action = clap::ArgAction::Set)]
+        when_locked: Option<bool>,
+    },
+    Reset,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RateLimitCommand {
+    Get,
+    Set {
+        /// Announcements one agent may make in one project back to back; 0 disables the limit
+        #[arg(long)]
+        burst: Option<u32>,
+        /// Time for the bucket to win back one announcement (30s, 2m, ...; default 1m)
+        #[arg(long, value_name = "DURATION")]
+        refill: Option<String>,
+        /// Minimum gap between announcements of the same kind from one agent in one project; 0 disables it
+        #[arg(long, value_name = "DURATION")]
+        cooldown: Option<String>,
+    },
+    Reset,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DedupCommand {
+    Get,
+    Set {
+        /// How long a repeated event counts as a duplicate (10s, 1m, ...); 0 disables it
+        #[arg(long, value_name = "DURATION")]
+        window: String,
+    },
+    Reset,
+}
+
+#[derive(Debug, Args)]
+pub struct AddRuleArgs {
+    #[arg(long)]
+    pub name: String,
+    #[arg(long, value_enum)]
+    pub agent: Option<Agent>,
+    #[arg(long)]
+    pub kind: Option<String>,
+    /// Glob over the project name, for example `infra-*`
+    #[arg(long)]
+    pub project: Option<String>,
+    /// Glob over the working directory; `**` crosses directories
+    #[arg(long)]
+    pub cwd: Option<String>,
+    /// Payload value to match as PATH=VALUE (dotted path or JSON pointer); repeatable
+    #[arg(long = "field", value_name = "PATH=VALUE")]
+    pub fields: Vec<String>,
+    /// Local HH:MM-HH:MM window
+    #[arg(long)]
+    pub hours: Option<String>,
+    #[arg(long, value_name = "DURATION")]
+    pub min_duration: Option<String>,
+    #[arg(long, value_name = "DURATION")]
+    pub max_duration: Option<String>,
+    /// Minimum session length so far, from the Claude transcript
+    #[arg(long, value_name = "DURATION")]
+    pub min_session: Option<String>,
+    /// Maximum session length so far, from the Claude transcript
+    #[arg(long, value_name = "DURATION")]
+    pub max_session: Option<String>,
+    #[arg(long, value_enum, default_value_t = RuleAction::Speak)]
+    pub action: RuleAction,
+    /// Sinks for announced events (default: speech)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub route_to: Vec<Sink>,
+    #[arg(long)]
+    pub template: Option<String>,
+    #[arg(long, value_enum)]
+    pub priority: Option<SpeechPriority>,
+    /// Recorded in the history when the rule drops an event
+    #[arg(long)]
+    pub reason: Option<String>,
+    /// 1-based position among the configured rules; appended when omitted
+    #[arg(long)]
+    pub position: Option<usize>,
+}
+
+/// Rules are evaluated in order and the first match decides.
+#[derive(Debug, Subcommand)]
+pub enum RulesCommand {
+    List,
+    Add(Box<AddRuleArgs>),
+    Remove {
+        #[arg(long)]
+        name: String,
+    },
+    /// Show which rule a payload would match, without announcing it
+    Test {
+        #[arg(long, value_enum)]
+        agent: Agent,
+        /// Custom agent declared under `custom_agents` in the local config
+        #[arg(long, value_name = "NAME", required_if_eq("agent", "custom"))]
+        name: Option<String>,
+        #[arg(long)]
+        payload: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SummaryCommand {
+    Get,
+    Set {
+        #[arg(long)]
+        max_words: usize,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CodexChainCommand {
+    Get,
+    Set {
+        #[arg(long, action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ClaudeHooksCommand {
+    List,
+    Add {
+        #[arg(long)]
+        event: String,
+        /// Tool name regex, anchored like Claude's own matchers (`*` matches every tool)
+        #[arg(long, default_value = "*")]
+        matcher: String,
+        #[arg(long)]
+        event_kind: Option<String>,
+    },
+    Remove {
+        #[arg(long)]
+        event: String,
+        #[arg(long)]
+        matcher: Option<String>,
+    },
+    Reset,
+}
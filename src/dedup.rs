@@ -0,0 +1,144 @@
+//! Drops repeated deliveries of the same completion, such as Claude firing `Stop` twice
+//! for one session, or a `SubagentStop` followed by a `Stop` for the same turn. Events are
+//! keyed on agent, session id, event class and (when the agent reports one) turn id; the
+//! keys seen within the window live in `dedup.json`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::event::{NormalizedEvent, COMPLETION_EVENT_KINDS};
+use crate::lockfile;
+
+pub const DEFAULT_WINDOW_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SeenEvents {
+    /// Unix timestamp each key was first seen.
+    #[serde(default)]
+    pub seen: BTreeMap<String, u64>,
+}
+
+impl SeenEvents {
+    /// Records `key` at `now` and returns `None`, or returns when it was first seen if that
+    /// is less than `window_secs` ago.
+    pub fn check(&mut self, key: &str, window_secs: u64, now: u64) -> Option<u64> {
+        self.seen
+            .retain(|_, first_seen| now < first_seen.saturating_add(window_secs));
+        if let Some(first_seen) = self.seen.get(key) {
+            return Some(*first_seen);
+        }
+        self.seen.insert(key.to_string(), now);
+        None
+    }
+}
+
+/// Events without a session id cannot be told apart from a genuinely new completion, so
+/// they get no key and are never deduplicated.
+pub fn key(event: &NormalizedEvent) -> Option<String> {
+    let session = event.session_id.as_deref()?;
+    let mut key = format!(
+        "{}/{session}/{}",
+        event.display_name(),
+        class(&event.event_kind)
+    );
+    if let Some(turn) = event.turn_id.as_deref() {
+        key.push('/');
+        key.push_str(turn);
+    }
+    Some(key)
+}
+
+/// Plan and task completions of one turn announce the same thing, so they share a class;
+/// every other kind is its own class.
+fn class(event_kind: &str) -> &str {
+    if COMPLETION_EVENT_KINDS.contains(&event_kind) {
+        "completion"
+    } else {
+        event_kind
+    }
+}
+
+/// Applies [`SeenEvents::check`] to the keys stored at `path` under the file lock; returns
+/// when the duplicate `key` was first seen.
+pub fn admit(path: &Path, key: &str, window_secs: u64, now: u64) -> Result<Option<u64>> {
+    if window_secs == 0 {
+        return Ok(None);
+    }
+    lockfile::update(path, |seen: &mut SeenEvents| {
+        seen.check(key, window_secs, now)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use crate::event::normalize;
+    use serde_json::json;
+
+    #[test]
+    fn keys_on_session_kind_and_turn() {
+        let claude = normalize(
+            Agent::Claude,
+            json!({"hook_event_name": "Stop", "session_id": "abc", "cwd": "/srv/api"}),
+        )
+        .expect("claude event");
+        assert_eq!(key(&claude).as_deref(), Some("Claude/abc/completion"));
+
+        let codex = normalize(
+            Agent::Codex,
+            json!({"type": "agent-turn-complete", "thread-id": "t-1", "turn-id": "7"}),
+        )
+        .expect("codex event");
+        assert_eq!(key(&codex).as_deref(), Some("Codex/t-1/completion/7"));
+
+        let anonymous =
+            normalize(Agent::Codex, json!({"type": "agent-turn-complete"})).expect("codex event");
+        assert_eq!(key(&anonymous), None);
+    }
+
+    #[test]
+    fn subagent_stop_then_stop_share_a_key() {
+        let payload =
+            |hook: &str| json!({"hook_event_name": hook, "session_id": "abc", "cwd": "/srv/api"});
+        let subagent = normalize(Agent::Claude, payload("SubagentStop")).expect("subagent");
+        let stop = normalize(Agent::Claude, payload("Stop")).expect("stop");
+        assert_eq!(subagent.event_kind, "plan-end");
+        assert_eq!(stop.event_kind, "task-end");
+
+        let mut seen = SeenEvents::default();
+        assert_eq!(seen.check(&key(&subagent).expect("key"), 10, 100), None);
+        assert_eq!(seen.check(&key(&stop).expect("key"), 10, 101), Some(100));
+    }
+
+    #[test]
+    fn repeats_within_the_window_are_duplicates() {
+        let mut seen = SeenEvents::default();
+        assert_eq!(seen.check("Claude/abc/task-end", 10, 100), None);
+        assert_eq!(seen.check("Claude/abc/task-end", 10, 105), Some(100));
+        assert_eq!(seen.check("Claude/abc/plan-end", 10, 105), None);
+        assert_eq!(seen.check("Claude/abc/task-end", 10, 110), None);
+        assert_eq!(seen.seen.len(), 2);
+    }
+
+    #[test]
+    fn admit_persists_between_calls() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("dedup.json");
+
+        assert_eq!(
+            admit(&path, "Codex/t-1/task-end", 10, 0).expect("admit"),
+            None
+        );
+        assert_eq!(
+            admit(&path, "Codex/t-1/task-end", 10, 3).expect("admit"),
+            Some(0)
+        );
+        assert_eq!(
+            admit(&path, "Codex/t-1/task-end", 0, 3).expect("admit"),
+            None
+        );
+    }
+}
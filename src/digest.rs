@@ -0,0 +1,222 @@
+//! Announcements held back by mute, quiet hours or a locked screen wait in `pending.json`
+//! until `agitiser-notify digest` speaks them as one summary.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::agent::Agent;
+use crate::lockfile;
+use crate::template::render_digest;
+
+/// Oldest events are dropped beyond this, so a long absence cannot grow the file forever.
+pub const CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PendingQueue {
+    #[serde(default)]
+    pub events: Vec<PendingEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PendingEvent {
+    /// Unix timestamp in seconds.
+    pub timestamp: u64,
+    pub agent: Agent,
+    /// Spoken agent name at the time the event was held back.
+    pub agent_name: String,
+    pub event_kind: String,
+    /// Resolved `{{event_kind}}` label, used for kinds without a digest phrase.
+    pub event_kind_label: String,
+    pub project: String,
+    /// The announcement that would have been spoken.
+    pub message: String,
+    /// Why it was held back.
+    pub reason: String,
+}
+
+impl PendingQueue {
+    pub fn push(&mut self, event: PendingEvent) {
+        self.events.push(event);
+        if self.events.len() > CAPACITY {
+            let excess = self.events.len() - CAPACITY;
+            self.events.drain(..excess);
+        }
+    }
+}
+
+pub fn load(path: &Path) -> Result<PendingQueue> {
+    if !path.exists() {
+        return Ok(PendingQueue::default());
+    }
+
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    if raw.trim().is_empty() {
+        return Ok(PendingQueue::default());
+    }
+
+    serde_json::from_str(&raw).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Appends `event` to the queue at `path` under the file lock.
+pub fn enqueue(path: &Path, event: PendingEvent) -> Result<()> {
+    lockfile::update(path, |queue: &mut PendingQueue| queue.push(event))
+}
+
+/// Empties the queue at `path` under the file lock and returns what it held, so events
+/// enqueued while the digest is spoken wait for the next one.
+pub fn drain(path: &Path) -> Result<Vec<PendingEvent>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    lockfile::update(path, |queue: &mut PendingQueue| {
+        std::mem::take(&mut queue.events)
+    })
+}
+
+/// Puts drained `events` back in front of anything enqueued since, for when the digest
+/// could not be spoken.
+pub fn requeue(path: &Path, mut events: Vec<PendingEvent>) -> Result<()> {
+    lockfile::update(path, |queue: &mut PendingQueue| {
+        events.append(&mut queue.events);
+        queue.events = events;
+        if queue.events.len() > CAPACITY {
+            let excess = queue.events.len() - CAPACITY;
+            queue.events.drain(..excess);
+        }
+    })
+}
+
+/// Renders the digest for `events` with the configured `template` (or the built-in
+/// `While you were away: {{events}}.`).
+pub fn compose(events: &[PendingEvent], template: Option<&str>) -> String {
+    render_digest(template, &describe_events(events), events.len())
+}
+
+/// Groups events by agent, kind and project in first-seen order, for example
+/// `Claude finished 4 tasks in backend, Codex finished planning in infra`.
+pub fn describe_events(events: &[PendingEvent]) -> String {
+    let mut groups: Vec<(&PendingEvent, usize)> = Vec::new();
+    for event in events {
+        let existing = groups.iter_mut().find(|(first, _)| {
+            first.agent_name == event.agent_name
+                && first.event_kind == event.event_kind
+                && first.project == event.project
+        });
+        match existing {
+            Some((_, count)) => *count += 1,
+            None => groups.push((event, 1)),
+        }
+    }
+
+    groups
+        .iter()
+        .map(|(event, count)| describe_group(event, *count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn describe_group(event: &PendingEvent, count: usize) -> String {
+    let agent = &event.agent_name;
+    let project = &event.project;
+    let times = if count > 1 {
+        format!(" {count} times")
+    } else {
+        String::new()
+    };
+    match event.event_kind.as_str() {
+        "plan-end" => format!("{agent} finished planning in {project}{times}"),
+        "attention" => format!("{agent} needed your attention in {project}{times}"),
+        "task-failed" if count > 1 => format!("{agent} failed {count} times in {project}"),
+        "task-failed" => format!("{agent} failed in {project}"),
+        "error" if count > 1 => format!("{agent} hit {count} errors in {project}"),
+        "error" => format!("{agent} hit an error in {project}"),
+        _ if count > 1 => format!(
+            "{agent} finished {count} {} in {project}",
+            plural(&event.event_kind_label)
+        ),
+        _ => format!("{agent} finished a {} in {project}", event.event_kind_label),
+    }
+}
+
+/// Multi-word labels are left intact (`3 task completed events`).
+fn plural(label: &str) -> String {
+    if label.contains(' ') {
+        format!("{label} events")
+    } else {
+        format!("{label}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(agent_name: &str, event_kind: &str, label: &str, project: &str) -> PendingEvent {
+        PendingEvent {
+            timestamp: 0,
+            agent: Agent::Claude,
+            agent_name: agent_name.to_string(),
+            event_kind: event_kind.to_string(),
+            event_kind_label: label.to_string(),
+            project: project.to_string(),
+            message: String::new(),
+            reason: "muted".to_string(),
+        }
+    }
+
+    #[test]
+    fn groups_events_into_one_summary() {
+        let mut events = vec![pending("Claude", "task-end", "task", "backend"); 4];
+        events.insert(1, pending("Codex", "plan-end", "plan", "infra"));
+        events.push(pending("Codex", "task-failed", "failed task", "infra"));
+        events.push(pending("Nightly", "build-done", "build done", "ci"));
+        events.push(pending("Nightly", "build-done", "build done", "ci"));
+
+        assert_eq!(
+            compose(&events, None),
+            "While you were away: Claude finished 4 tasks in backend, Codex finished planning in infra, \
+             Codex failed in infra, Nightly finished 2 build done events in ci."
+        );
+    }
+
+    #[test]
+    fn uses_the_configured_template() {
+        let events = vec![pending("Claude", "error", "error", "api")];
+        assert_eq!(
+            compose(&events, Some("{{count}} missed: {{events}}")),
+            "1 missed: Claude hit an error in api"
+        );
+    }
+
+    #[test]
+    fn drain_keeps_events_enqueued_afterwards() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("pending.json");
+        enqueue(&path, pending("Claude", "task-end", "task", "api")).expect("enqueue");
+
+        let drained = drain(&path).expect("drain");
+        assert_eq!(drained.len(), 1);
+        enqueue(&path, pending("Codex", "plan-end", "plan", "infra")).expect("enqueue");
+        requeue(&path, drained).expect("requeue");
+
+        let queue = load(&path).expect("load");
+        assert_eq!(queue.events.len(), 2);
+        assert_eq!(queue.events[0].agent_name, "Claude");
+        assert_eq!(queue.events[1].agent_name, "Codex");
+    }
+
+    #[test]
+    fn drops_the_oldest_events_beyond_capacity() {
+        let mut queue = PendingQueue::default();
+        for timestamp in 0..(CAPACITY as u64 + 2) {
+            let mut event = pending("Claude", "task-end", "task", "api");
+            event.timestamp = timestamp;
+            queue.push(event);
+        }
+        assert_eq!(queue.events.len(), CAPACITY);
+        assert_eq!(queue.events[0].timestamp, 2);
+    }
+}
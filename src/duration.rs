@@ -0,0 +1,102 @@
+use anyhow::{bail, Context, Result};
+use std::time::Duration;
+
+/// Parses durations such as `45`, `30s`, `2m`, `1h30m` or `7d`. A bare number is seconds.
+pub fn parse(value: &str) -> Result<Duration> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        bail!("duration must not be empty");
+    }
+    if let Ok(seconds) = trimmed.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total = 0u64;
+    let mut digits = String::new();
+    for ch in trimmed.chars() {
+        if ch.is_ascii_digit() {
+            digits.push(ch);
+            continue;
+        }
+        let multiplier = match ch.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => bail!("invalid duration {trimmed:?}; use forms like 30s, 2m, 1h30m"),
+        };
+        if digits.is_empty() {
+            bail!("invalid duration {trimmed:?}; expected a number before '{ch}'");
+        }
+        let amount = digits
+            .parse::<u64>()
+            .with_context(|| format!("invalid duration {trimmed:?}"))?;
+        total = total.saturating_add(amount.saturating_mul(multiplier));
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        bail!("invalid duration {trimmed:?}; missing unit after {digits}");
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Formats a duration the way it should be spoken, for example `2 minutes 5 seconds`.
+/// Only the two most significant units are kept.
+pub fn humanize(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let units = [
+        (total / 86_400, "day"),
+        (total % 86_400 / 3_600, "hour"),
+        (total % 3_600 / 60, "minute"),
+        (total % 60, "second"),
+    ];
+
+    let parts = units
+        .iter()
+        .skip_while(|(value, _)| *value == 0)
+        .take(2)
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| {
+            if *value == 1 {
+                format!("1 {unit}")
+            } else {
+                format!("{value} {unit}s")
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if parts.is_empty() {
+        "0 seconds".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_compound_durations() {
+        assert_eq!(parse("45").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse("1h30m").unwrap(), Duration::from_secs(5_400));
+        assert_eq!(parse("7d").unwrap(), Duration::from_secs(604_800));
+        assert!(parse("").is_err());
+        assert!(parse("m").is_err());
+        assert!(parse("5x").is_err());
+        assert!(parse("1h30").is_err());
+    }
+
+    #[test]
+    fn humanizes_two_most_significant_units() {
+        assert_eq!(humanize(Duration::from_secs(0)), "0 seconds");
+        assert_eq!(humanize(Duration::from_secs(1)), "1 second");
+        assert_eq!(humanize(Duration::from_secs(125)), "2 minutes 5 seconds");
+        assert_eq!(humanize(Duration::from_secs(3_600)), "1 hour");
+        assert_eq!(humanize(Duration::from_secs(3_725)), "1 hour 2 minutes");
+    }
+}
@@ -1,7 +1,12 @@
+use regex::Regex;
 use serde_json::Value;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use crate::agent::Agent;
+use crate::state::{ClaudeHookConfig, CustomAgentConfig, GenericConfig, LocalState};
+use crate::summary;
+use crate::transcript::TranscriptStats;
 
 #[derive(Debug, Clone)]
 pub struct NormalizedEvent {
@@ -9,21 +14,104 @@ pub struct NormalizedEvent {
     pub event_kind: String,
     pub cwd: Option<PathBuf>,
     pub project_name: String,
+    pub session_id: Option<String>,
+    pub turn_id: Option<String>,
+    pub last_message: Option<String>,
+    pub user_prompt: Option<String>,
+    /// Speakable summary of `last_message`, filled in by [`NormalizedEvent::summarize`].
+    pub summary: Option<String>,
+    /// Claude session metadata read from `transcript_path` during ingest.
+    pub transcript: Option<TranscriptStats>,
+    /// Elapsed time of the turn that just ended, when known.
+    pub duration: Option<Duration>,
+    /// Spoken name overriding `agent.display_name()`, set for custom agents.
+    pub agent_name: Option<String>,
+    /// Command line of a wrapped or reported run (`run`, generic `command`).
+    pub command: Option<String>,
+    /// Exit status of that run, when known.
+    pub exit_code: Option<i64>,
     pub raw_payload: Value,
 }
 
+impl NormalizedEvent {
+    pub fn display_name(&self) -> &str {
+        self.agent_name
+            .as_deref()
+            .unwrap_or_else(|| self.agent.display_name())
+    }
+
+    pub fn summarize(&mut self, max_words: usize) {
+        self.summary = self
+            .last_message
+            .as_deref()
+            .and_then(|message| summary::summarize(message, max_words));
+    }
+}
+
+/// Kinds for runs that ended badly: `task-failed` when the agent gave up on the task,
+/// `error` when the tool itself broke (API errors, crashes). Always announced, however short
+/// the turn.
+pub const FAILURE_EVENT_KINDS: &[&str] = &["task-failed", "error"];
+
+/// Kinds for work that finished normally, the only ones `min_duration` can skip.
+pub const COMPLETION_EVENT_KINDS: &[&str] = &["task-end", "plan-end"];
+
+/// Claude hook used only to record turn start times; it is never announced.
+const USER_PROMPT_SUBMIT_EVENT: &str = "UserPromptSubmit";
+
 pub fn normalize(agent: Agent, payload: Value) -> Option<NormalizedEvent> {
+    normalize_with_state(agent, payload, &LocalState::default())
+}
+
+pub fn normalize_with_state(
+    agent: Agent,
+    payload: Value,
+    state: &LocalState,
+) -> Option<NormalizedEvent> {
     match agent {
-        Agent::Claude => normalize_claude(payload),
+        Agent::Claude => normalize_claude(payload, &state.claude.hooks),
         Agent::Codex => normalize_codex(payload),
-        Agent::Generic => normalize_generic(payload),
+        Agent::Gemini => normalize_gemini(payload),
+        Agent::Opencode => normalize_opencode(payload),
+        Agent::Aider => normalize_aider(payload),
+        Agent::Generic => normalize_generic(payload, &state.generic),
+        // Declared custom agents go through `normalize_custom`; anything else falls back to
+        // the generic heuristics under the default name.
+        Agent::Custom => {
+            normalize_generic(payload, &GenericConfig::default()).map(|event| NormalizedEvent {
+                agent: Agent::Custom,
+                ..event
+            })
+        }
+    }
+}
+
+/// Normalizes a payload for the custom agent `name` declared in `state.custom_agents`.
+pub fn normalize_custom(name: &str, payload: Value, state: &LocalState) -> Option<NormalizedEvent> {
+    let definition = state.custom_agents.get(name)?;
+    normalize_with_definition(name, definition, payload)
+}
+
+/// Looks up a dotted path (`event.type`, `items.0.text`) or a JSON pointer (`/event/type`).
+pub fn select_field<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.trim();
+    if path.starts_with('/') {
+        return payload.pointer(path);
     }
+
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(payload, |value, segment| match value {
+            Value::Object(object) => object.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
 }
 
 pub fn announcement_message(event: &NormalizedEvent) -> String {
     format!(
         "{} finished a {} in {}",
-        event.agent.display_name(),
+        event.display_name(),
         event.event_kind,
         event.project_name
     )
@@ -46,17 +134,31 @@ pub fn project_name_from_cwd(cwd: Option<&str>) -> String {
         .unwrap_or_else(|| candidate.to_string())
 }
 
-fn normalize_claude(payload: Value) -> Option<NormalizedEvent> {
+fn normalize_claude(payload: Value, hooks: &[ClaudeHookConfig]) -> Option<NormalizedEvent> {
     let object = payload.as_object()?;
-    let event_kind = claude_event_kind(object)?;
+    let event_kind = claude_event_kind(object)
+        .map(ToOwned::to_owned)
+        .or_else(|| configured_claude_event_kind(object, hooks))?;
 
     let cwd_str = object.get("cwd").and_then(Value::as_str);
     let cwd = cwd_str.map(PathBuf::from);
     Some(NormalizedEvent {
         agent: Agent::Claude,
-        event_kind: event_kind.to_string(),
+        event_kind,
         project_name: project_name_from_cwd(cwd_str),
         cwd,
+        session_id: string_field(object, "session_id"),
+        turn_id: None,
+        last_message: string_field(object, "last_assistant_message")
+            .or_else(|| string_field(object, "error_details"))
+            .or_else(|| string_field(object, "error")),
+        user_prompt: string_field(object, "prompt"),
+        summary: None,
+        transcript: None,
+        duration: None,
+        agent_name: None,
+        command: None,
+        exit_code: None,
         raw_payload: payload,
     })
 }
@@ -65,12 +167,64 @@ fn claude_event_kind(object: &serde_json::Map<String, Value>) -> Option<&'static
     let hook_event = object.get("hook_event_name").and_then(Value::as_str)?;
     match hook_event {
         "Stop" => Some("task-end"),
+        "StopFailure" => Some("error"),
         "SubagentStop" => Some("plan-end"),
         "PermissionRequest" if is_exit_plan_mode_request(object) => Some("plan-end"),
         _ => None,
     }
 }
 
+fn configured_claude_event_kind(
+    object: &serde_json::Map<String, Value>,
+    hooks: &[ClaudeHookConfig],
+) -> Option<String> {
+    let hook_event = object
+        .get("hook_event_name")
+        .and_then(Value::as_str)
+        .filter(|hook_event| *hook_event != USER_PROMPT_SUBMIT_EVENT)?;
+    let tool_name = object.get("tool_name").and_then(Value::as_str);
+    let hook = hooks
+        .iter()
+        .find(|hook| hook.event == hook_event && matcher_matches(&hook.matcher, tool_name))?;
+
+    Some(
+        hook.event_kind
+            .as_deref()
+            .map(str::trim)
+            .filter(|kind| !kind.is_empty())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_else(|| kebab_case(hook_event)),
+    )
+}
+
+/// Compiles a Claude hook matcher, a regex that must match the whole tool name.
+pub fn matcher_regex(matcher: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", matcher.trim()))
+}
+
+fn matcher_matches(matcher: &str, tool_name: Option<&str>) -> bool {
+    let matcher = matcher.trim();
+    if matcher.is_empty() || matcher == "*" {
+        return true;
+    }
+
+    match tool_name {
+        Some(tool_name) => matcher_regex(matcher).is_ok_and(|regex| regex.is_match(tool_name)),
+        None => true,
+    }
+}
+
+fn kebab_case(value: &str) -> String {
+    let mut kebab = String::new();
+    for (index, ch) in value.chars().enumerate() {
+        if ch.is_ascii_uppercase() && index > 0 {
+            kebab.push('-');
+        }
+        kebab.push(ch.to_ascii_lowercase());
+    }
+    kebab
+}
+
 fn is_exit_plan_mode_request(object: &serde_json::Map<String, Value>) -> bool {
     object.get("tool_name").and_then(Value::as_str) == Some("ExitPlanMode")
         || object.get("tool").and_then(Value::as_str) == Some("ExitPlanMode")
@@ -89,6 +243,29 @@ fn normalize_codex(payload: Value) -> Option<NormalizedEvent> {
         event_kind: event_kind.to_string(),
         project_name: project_name_from_cwd(cwd_str),
         cwd,
+        session_id: string_field(object, "thread-id"),
+        turn_id: string_field(object, "turn-id"),
+        last_message: string_field(object, "last-assistant-message")
+            .or_else(|| string_field(object, "message"))
+            .or_else(|| string_field(object, "error")),
+        user_prompt: object
+            .get("input-messages")
+            .and_then(Value::as_array)
+            .and_then(|messages| {
+                messages
+                    .iter()
+                    .rev()
+                    .filter_map(Value::as_str)
+                    .map(str::trim)
+                    .find(|message| !message.is_empty())
+            })
+            .map(ToOwned::to_owned),
+        summary: None,
+        transcript: None,
+        duration: None,
+        agent_name: None,
+        command: None,
+        exit_code: None,
         raw_payload: payload,
     })
 }
@@ -97,56 +274,317 @@ fn codex_event_kind(kind: &str) -> Option<&'static str> {
     match kind {
         "agent-turn-complete" => Some("task-end"),
         "agent-plan-complete" => Some("plan-end"),
+        "agent-turn-failed" => Some("task-failed"),
+        "error" => Some("error"),
         _ => None,
     }
 }
 
-fn normalize_generic(payload: Value) -> Option<NormalizedEvent> {
+fn normalize_gemini(payload: Value) -> Option<NormalizedEvent> {
     let object = payload.as_object()?;
+    let hook_event = object.get("hook_event_name").and_then(Value::as_str)?;
+    let event_kind = gemini_event_kind(hook_event)?;
 
-    let event_kind = object
-        .get("event_kind")
-        .or_else(|| object.get("event-kind"))
-        .or_else(|| object.get("type"))
-        .or_else(|| object.get("kind"))
-        .or_else(|| object.get("event"))
-        .and_then(Value::as_str)?
-        .to_string();
+    let cwd_str = object.get("cwd").and_then(Value::as_str);
+    let cwd = cwd_str.map(PathBuf::from);
+    Some(NormalizedEvent {
+        agent: Agent::Gemini,
+        event_kind: event_kind.to_string(),
+        project_name: project_name_from_cwd(cwd_str),
+        cwd,
+        session_id: string_field(object, "session_id"),
+        turn_id: None,
+        last_message: string_field(object, "prompt_response")
+            .or_else(|| string_field(object, "message")),
+        user_prompt: string_field(object, "prompt"),
+        summary: None,
+        transcript: None,
+        duration: None,
+        agent_name: None,
+        command: None,
+        exit_code: None,
+        raw_payload: payload,
+    })
+}
 
-    if !is_terminal_event(&event_kind) {
+/// `BeforeAgent` is installed only to record turn start times and maps to nothing.
+fn gemini_event_kind(hook_event: &str) -> Option<&'static str> {
+    match hook_event {
+        "AfterAgent" => Some("task-end"),
+        "Notification" => Some("attention"),
+        _ => None,
+    }
+}
+
+fn normalize_opencode(payload: Value) -> Option<NormalizedEvent> {
+    let object = payload.as_object()?;
+    let kind = object.get("type").and_then(Value::as_str)?;
+    let event_kind = opencode_event_kind(kind)?;
+
+    let cwd_str = object
+        .get("cwd")
+        .or_else(|| object.get("directory"))
+        .and_then(Value::as_str);
+    let cwd = cwd_str.map(PathBuf::from);
+    Some(NormalizedEvent {
+        agent: Agent::Opencode,
+        event_kind: event_kind.to_string(),
+        project_name: project_name_from_cwd(cwd_str),
+        cwd,
+        session_id: string_field(object, "session_id"),
+        turn_id: None,
+        last_message: string_field(object, "message"),
+        user_prompt: string_field(object, "prompt"),
+        summary: None,
+        transcript: None,
+        duration: None,
+        agent_name: None,
+        command: None,
+        exit_code: None,
+        raw_payload: payload,
+    })
+}
+
+fn opencode_event_kind(kind: &str) -> Option<&'static str> {
+    match kind {
+        "session.idle" => Some("task-end"),
+        _ => None,
+    }
+}
+
+/// Aider's notifications command carries no event data: every invocation means Aider
+/// finished a reply, and the project is the directory Aider runs the command from.
+fn normalize_aider(payload: Value) -> Option<NormalizedEvent> {
+    let object = payload.as_object()?;
+
+    let cwd = object
+        .get("cwd")
+        .and_then(Value::as_str)
+        .map(PathBuf::from)
+        .or_else(|| std::env::current_dir().ok());
+    Some(NormalizedEvent {
+        agent: Agent::Aider,
+        event_kind: "task-end".to_string(),
+        project_name: project_name_from_cwd(cwd.as_deref().and_then(Path::to_str)),
+        cwd,
+        session_id: None,
+        turn_id: None,
+        last_message: None,
+        user_prompt: None,
+        summary: None,
+        transcript: None,
+        duration: None,
+        agent_name: None,
+        command: None,
+        exit_code: None,
+        raw_payload: payload,
+    })
+}
+
+fn normalize_with_definition(
+    name: &str,
+    definition: &CustomAgentConfig,
+    payload: Value,
+) -> Option<NormalizedEvent> {
+    let selected = |path: &str| {
+        select_field(&payload, path)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToOwned::to_owned)
+    };
+
+    let raw_kind = selected(&definition.event_field)?;
+    let event_kind = definition.kinds.get(&raw_kind)?.trim().to_ascii_lowercase();
+    if event_kind.is_empty() {
         return None;
     }
 
-    let cwd = object.get("cwd").and_then(Value::as_str).map(PathBuf::from);
-    let project_name = match cwd.as_ref().and_then(|p| p.to_str()) {
-        Some(path) => project_name_from_cwd(Some(path)),
-        None => object
-            .get("project")
+    let cwd_str = selected(&definition.cwd_field);
+    let session_id = selected(&definition.session_field);
+    let last_message = selected(&definition.message_field);
+    let agent_name = definition
+        .display_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|display_name| !display_name.is_empty())
+        .unwrap_or(name)
+        .to_string();
+    Some(NormalizedEvent {
+        agent: Agent::Custom,
+        event_kind,
+        project_name: project_name_from_cwd(cwd_str.as_deref()),
+        cwd: cwd_str.map(PathBuf::from),
+        session_id,
+        turn_id: None,
+        last_message,
+        user_prompt: None,
+        summary: None,
+        transcript: None,
+        duration: None,
+        agent_name: Some(agent_name),
+        command: None,
+        exit_code: None,
+        raw_payload: payload,
+    })
+}
+
+fn normalize_generic(payload: Value, config: &GenericConfig) -> Option<NormalizedEvent> {
+    let object = payload.as_object()?;
+    let selected = |path: Option<&String>, fallback_keys: &[&str]| -> Option<String> {
+        let value = match path {
+            Some(path) => select_field(&payload, path),
+            None => fallback_keys.iter().find_map(|key| object.get(*key)),
+        };
+        value
             .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
             .map(ToOwned::to_owned)
-            .unwrap_or_else(|| "unknown project".to_string()),
     };
 
+    let kind = selected(
+        config.kind_field.as_ref(),
+        &["event_kind", "event-kind", "type", "kind", "event"],
+    );
+    if kind
+        .as_deref()
+        .is_some_and(|kind| !is_terminal_kind(kind, config))
+    {
+        return None;
+    }
+    let event_kind = match generic_outcome_kind(object) {
+        Some(outcome) => outcome.to_string(),
+        None => kind?,
+    };
+
+    let cwd = selected(config.cwd_field.as_ref(), &["cwd"]).map(PathBuf::from);
+    let project_from_cwd = cwd
+        .as_ref()
+        .and_then(|path| path.to_str())
+        .map(|path| project_name_from_cwd(Some(path)));
+    let project_name = match config.project_field {
+        Some(ref path) => selected(Some(path), &[]).or(project_from_cwd),
+        None => project_from_cwd.or_else(|| selected(None, &["project"])),
+    }
+    .unwrap_or_else(|| "unknown project".to_string());
+    let agent_name = config
+        .agent_name_field
+        .as_ref()
+        .and_then(|path| selected(Some(path), &[]));
+    let last_message = selected(config.message_field.as_ref(), &["message"]);
+
     Some(NormalizedEvent {
         agent: Agent::Generic,
         event_kind,
         cwd,
         project_name,
+        session_id: string_field(object, "session_id"),
+        turn_id: string_field(object, "turn_id"),
+        last_message,
+        user_prompt: string_field(object, "prompt"),
+        summary: None,
+        transcript: None,
+        duration: None,
+        agent_name,
+        command: string_field(object, "command"),
+        exit_code: exit_code_field(object),
         raw_payload: payload,
     })
 }
 
+/// A non-zero `exit_code` or a failing `status` turns a terminal or kindless generic payload
+/// into a failure event. Successful outcomes leave the kind alone.
+fn generic_outcome_kind(object: &serde_json::Map<String, Value>) -> Option<&'static str> {
+    if exit_code_field(object).is_some_and(|code| code != 0) {
+        return Some("task-failed");
+    }
+
+    let status = object.get("status").and_then(Value::as_str)?;
+    match status.trim().to_ascii_lowercase().as_str() {
+        "failed" | "failure" | "fail" => Some("task-failed"),
+        "error" | "errored" => Some("error"),
+        _ => None,
+    }
+}
+
+fn exit_code_field(object: &serde_json::Map<String, Value>) -> Option<i64> {
+    object.get("exit_code").and_then(|code| match code {
+        Value::Number(number) => number.as_i64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+fn string_field(object: &serde_json::Map<String, Value>, key: &str) -> Option<String> {
+    object
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+/// Applies the configured allow-list and pattern; either one matching is enough. Without
+/// either, the failure kinds and [`is_terminal_event`] decide. An invalid pattern never
+/// matches.
+fn is_terminal_kind(event_kind: &str, config: &GenericConfig) -> bool {
+    let pattern = config
+        .terminal_pattern
+        .as_deref()
+        .filter(|pattern| !pattern.trim().is_empty());
+    if config.terminal_kinds.is_empty() && pattern.is_none() {
+        return FAILURE_EVENT_KINDS
+            .iter()
+            .any(|kind| kind.eq_ignore_ascii_case(event_kind))
+            || is_terminal_event(event_kind);
+    }
+
+    config
+        .terminal_kinds
+        .iter()
+        .any(|kind| kind.trim().eq_ignore_ascii_case(event_kind))
+        || pattern
+            .and_then(|pattern| Regex::new(pattern).ok())
+            .is_some_and(|regex| regex.is_match(event_kind))
+}
+
+const TERMINAL_WORDS: &[&str] = &[
+    "complete",
+    "completed",
+    "finish",
+    "finished",
+    "done",
+    "stop",
+    "stopped",
+];
+
+/// Matches whole words only, so `task-done` and `TaskCompleted` are terminal but
+/// `undone` and `nonstop` are not.
 fn is_terminal_event(event_kind: &str) -> bool {
-    let lowered = event_kind.to_ascii_lowercase();
-    lowered.contains("complete")
-        || lowered.contains("finish")
-        || lowered.contains("done")
-        || lowered.contains("stop")
+    let mut words = vec![String::new()];
+    let mut previous_lowercase = false;
+    for ch in event_kind.chars() {
+        if !ch.is_ascii_alphanumeric() || (previous_lowercase && ch.is_ascii_uppercase()) {
+            words.push(String::new());
+        }
+        if ch.is_ascii_alphanumeric() {
+            words
+                .last_mut()
+                .expect("words is never empty")
+                .push(ch.to_ascii_lowercase());
+        }
+        previous_lowercase = ch.is_ascii_lowercase();
+    }
+    words
+        .iter()
+        .any(|word| TERMINAL_WORDS.contains(&word.as_str()))
 }
 
 #[cfg(test)]
 mod tests {
     use serde_json::json;
+    use std::collections::BTreeMap;
 
     use super::*;
 
@@ -203,6 +641,65 @@ mod tests {
         assert!(normalize(Agent::Claude, payload).is_none());
     }
 
+    #[test]
+    fn parses_configured_claude_hook_event() {
+        let state = LocalState {
+            claude: crate::state::ClaudeState {
+                hooks: vec![ClaudeHookConfig {
+                    event: "PostToolUse".to_string(),
+                    matcher: "Bash|Edit".to_string(),
+                    event_kind: None,
+                }],
+                ..crate::state::ClaudeState::default()
+            },
+            ..LocalState::default()
+        };
+
+        let payload = json!({
+            "hook_event_name": "PostToolUse",
+            "tool_name": "Bash",
+            "cwd": "/home/notes/Projects/agitiser"
+        });
+        let normalized = normalize_with_state(Agent::Claude, payload, &state)
+            .expect("expected configured hook event");
+        assert_eq!(normalized.event_kind, "post-tool-use");
+
+        let payload = json!({
+            "hook_event_name": "PostToolUse",
+            "tool_name": "Read",
+            "cwd": "/home/notes/Projects/agitiser"
+        });
+        assert!(normalize_with_state(Agent::Claude, payload, &state).is_none());
+    }
+
+    #[test]
+    fn claude_hook_matchers_are_anchored_regexes() {
+        assert!(matcher_matches("Edit.*", Some("Edit")));
+        assert!(matcher_matches("Edit.*", Some("EditNotebook")));
+        assert!(!matcher_matches("Edit.*", Some("MultiEdit")));
+        assert!(matcher_matches(
+            "mcp__.*",
+            Some("mcp__github__create_issue")
+        ));
+        assert!(matcher_matches("Bash|Edit", Some("Edit")));
+        assert!(!matcher_matches("Bash", Some("BashOutput")));
+        assert!(matcher_matches("*", Some("Read")));
+        assert!(!matcher_matches("Edit(", Some("Edit(")));
+    }
+
+    #[test]
+    fn parses_claude_stop_failure_as_error() {
+        let payload = json!({
+            "hook_event_name": "StopFailure",
+            "error": "rate_limit",
+            "cwd": "/home/notes/Projects/agitiser"
+        });
+
+        let normalized = normalize(Agent::Claude, payload).expect("expected claude failure");
+        assert_eq!(normalized.event_kind, "error");
+        assert_eq!(normalized.last_message.as_deref(), Some("rate_limit"));
+    }
+
     #[test]
     fn ignores_non_terminal_claude_events() {
         let payload = json!({
@@ -224,6 +721,27 @@ mod tests {
         assert_eq!(normalized.project_name, "notiser");
     }
 
+    #[test]
+    fn parses_codex_turn_metadata() {
+        let payload = json!({
+            "type": "agent-turn-complete",
+            "thread-id": "thread-1",
+            "turn-id": "turn-7",
+            "cwd": "/home/notes/Projects/notiser",
+            "input-messages": ["first", "run the tests"],
+            "last-assistant-message": "All 42 tests pass."
+        });
+
+        let normalized = normalize(Agent::Codex, payload).expect("expected codex completion");
+        assert_eq!(normalized.session_id.as_deref(), Some("thread-1"));
+        assert_eq!(normalized.turn_id.as_deref(), Some("turn-7"));
+        assert_eq!(normalized.user_prompt.as_deref(), Some("run the tests"));
+        assert_eq!(
+            normalized.last_message.as_deref(),
+            Some("All 42 tests pass.")
+        );
+    }
+
     #[test]
     fn parses_codex_plan_complete_event() {
         let payload = json!({
@@ -237,6 +755,24 @@ mod tests {
         assert_eq!(normalized.project_name, "notiser");
     }
 
+    #[test]
+    fn parses_codex_failure_notifications() {
+        for (kind, expected) in [("agent-turn-failed", "task-failed"), ("error", "error")] {
+            let payload = json!({
+                "type": kind,
+                "cwd": "/home/notes/Projects/backend",
+                "message": "stream disconnected"
+            });
+
+            let normalized = normalize(Agent::Codex, payload).expect("expected codex failure");
+            assert_eq!(normalized.event_kind, expected);
+            assert_eq!(
+                normalized.last_message.as_deref(),
+                Some("stream disconnected")
+            );
+        }
+    }
+
     #[test]
     fn ignores_unknown_codex_completion_events() {
         let payload = json!({
@@ -247,6 +783,221 @@ mod tests {
         assert!(normalize(Agent::Codex, payload).is_none());
     }
 
+    #[test]
+    fn parses_gemini_after_agent_event() {
+        let payload = json!({
+            "hook_event_name": "AfterAgent",
+            "session_id": "g-1",
+            "cwd": "/home/notes/Projects/notiser",
+            "prompt": "fix the build",
+            "prompt_response": "The build passes again."
+        });
+
+        let normalized = normalize(Agent::Gemini, payload).expect("expected gemini completion");
+        assert_eq!(normalized.event_kind, "task-end");
+        assert_eq!(normalized.project_name, "notiser");
+        assert_eq!(normalized.session_id.as_deref(), Some("g-1"));
+        assert_eq!(normalized.user_prompt.as_deref(), Some("fix the build"));
+        assert_eq!(
+            normalized.last_message.as_deref(),
+            Some("The build passes again.")
+        );
+    }
+
+    #[test]
+    fn parses_gemini_notification_as_attention() {
+        let payload = json!({
+            "hook_event_name": "Notification",
+            "notification_type": "ToolPermission",
+            "cwd": "/tmp/demo"
+        });
+
+        let normalized = normalize(Agent::Gemini, payload).expect("expected gemini notification");
+        assert_eq!(normalized.event_kind, "attention");
+    }
+
+    #[test]
+    fn ignores_gemini_before_agent_event() {
+        let payload = json!({
+            "hook_event_name": "BeforeAgent",
+            "cwd": "/tmp/demo"
+        });
+
+        assert!(normalize(Agent::Gemini, payload).is_none());
+    }
+
+    #[test]
+    fn parses_opencode_session_idle_event() {
+        let payload = json!({
+            "type": "session.idle",
+            "session_id": "ses_123",
+            "cwd": "/home/notes/Projects/notiser"
+        });
+
+        let normalized = normalize(Agent::Opencode, payload).expect("expected opencode idle");
+        assert_eq!(normalized.event_kind, "task-end");
+        assert_eq!(normalized.project_name, "notiser");
+        assert_eq!(normalized.session_id.as_deref(), Some("ses_123"));
+    }
+
+    #[test]
+    fn ignores_other_opencode_events() {
+        let payload = json!({
+            "type": "session.updated",
+            "cwd": "/tmp/demo"
+        });
+
+        assert!(normalize(Agent::Opencode, payload).is_none());
+    }
+
+    #[test]
+    fn parses_aider_notification_with_cwd_fallback() {
+        let normalized = normalize(Agent::Aider, json!({})).expect("expected aider completion");
+        assert_eq!(normalized.event_kind, "task-end");
+        assert_eq!(normalized.cwd, std::env::current_dir().ok());
+
+        let normalized = normalize(Agent::Aider, json!({"cwd": "/home/notes/Projects/notiser"}))
+            .expect("expected aider completion");
+        assert_eq!(normalized.project_name, "notiser");
+    }
+
+    fn custom_state() -> LocalState {
+        let mut state = LocalState::default();
+        state.custom_agents.insert(
+            "builder".to_string(),
+            CustomAgentConfig {
+                display_name: Some("Build Bot".to_string()),
+                event_field: "event.type".to_string(),
+                cwd_field: "/workspace/path".to_string(),
+                session_field: "runs.0.id".to_string(),
+                kinds: BTreeMap::from([
+                    ("run.finished".to_string(), "task-end".to_string()),
+                    ("plan.ready".to_string(), "plan-end".to_string()),
+                ]),
+                ..CustomAgentConfig::default()
+            },
+        );
+        state
+    }
+
+    #[test]
+    fn selects_dotted_and_pointer_paths() {
+        let payload = json!({"event": {"type": "done"}, "items": [{"id": "a"}, {"id": "b"}]});
+
+        assert_eq!(select_field(&payload, "event.type"), Some(&json!("done")));
+        assert_eq!(select_field(&payload, "items.1.id"), Some(&json!("b")));
+        assert_eq!(select_field(&payload, "/items/0/id"), Some(&json!("a")));
+        assert_eq!(select_field(&payload, "event.missing"), None);
+        assert_eq!(select_field(&payload, "items.x"), None);
+    }
+
+    #[test]
+    fn parses_custom_agent_with_declared_mapping() {
+        let payload = json!({
+            "event": {"type": "run.finished"},
+            "workspace": {"path": "/home/notes/Projects/notiser"},
+            "runs": [{"id": "r-9"}],
+            "message": "Deployed."
+        });
+
+        let normalized =
+            normalize_custom("builder", payload, &custom_state()).expect("expected custom event");
+        assert_eq!(normalized.agent, Agent::Custom);
+        assert_eq!(normalized.event_kind, "task-end");
+        assert_eq!(normalized.project_name, "notiser");
+        assert_eq!(normalized.session_id.as_deref(), Some("r-9"));
+        assert_eq!(normalized.last_message.as_deref(), Some("Deployed."));
+        assert_eq!(normalized.display_name(), "Build Bot");
+    }
+
+    #[test]
+    fn ignores_unmapped_custom_agent_events() {
+        let payload = json!({"event": {"type": "run.stopped"}});
+        assert!(normalize_custom("builder", payload.clone(), &custom_state()).is_none());
+        assert!(normalize_custom("unknown", payload.clone(), &custom_state()).is_none());
+        assert!(normalize_with_state(Agent::Custom, payload, &custom_state()).is_none());
+    }
+
+    #[test]
+    fn terminal_heuristic_matches_whole_words() {
+        for kind in [
+            "done",
+            "task-done",
+            "TaskCompleted",
+            "RUN_FINISHED",
+            "stopped",
+        ] {
+            assert!(is_terminal_event(kind), "{kind} should be terminal");
+        }
+        for kind in ["undone", "nonstop", "completion-pending", "progress"] {
+            assert!(!is_terminal_event(kind), "{kind} should not be terminal");
+        }
+    }
+
+    #[test]
+    fn parses_generic_payload_with_configured_selectors() {
+        let state = LocalState {
+            generic: GenericConfig {
+                kind_field: Some("status.phase".to_string()),
+                cwd_field: Some("/context/dir".to_string()),
+                project_field: Some("context.repo".to_string()),
+                agent_name_field: Some("tool".to_string()),
+                message_field: Some("result.text".to_string()),
+                terminal_kinds: vec!["Succeeded".to_string()],
+                terminal_pattern: Some("^fail".to_string()),
+            },
+            ..LocalState::default()
+        };
+        let payload = |phase: &str| {
+            json!({
+                "tool": "Nightly",
+                "status": {"phase": phase},
+                "context": {"dir": "/srv/checkout", "repo": "backend"},
+                "result": {"text": "All green."}
+            })
+        };
+
+        let normalized = normalize_with_state(Agent::Generic, payload("succeeded"), &state)
+            .expect("expected allow-listed kind");
+        assert_eq!(normalized.event_kind, "succeeded");
+        assert_eq!(normalized.project_name, "backend");
+        assert_eq!(normalized.cwd, Some(PathBuf::from("/srv/checkout")));
+        assert_eq!(normalized.display_name(), "Nightly");
+        assert_eq!(normalized.last_message.as_deref(), Some("All green."));
+
+        assert!(normalize_with_state(Agent::Generic, payload("failed"), &state).is_some());
+        assert!(normalize_with_state(Agent::Generic, payload("done"), &state).is_none());
+        assert!(normalize_with_state(Agent::Generic, payload("error"), &state).is_none());
+    }
+
+    #[test]
+    fn generic_status_and_exit_code_mark_failures() {
+        let failed = normalize(
+            Agent::Generic,
+            json!({"type": "build-done", "exit_code": 2, "cwd": "/srv/backend"}),
+        )
+        .expect("expected non-zero exit code to be announced");
+        assert_eq!(failed.event_kind, "task-failed");
+        let failed = normalize(Agent::Generic, json!({"exit_code": "1"}))
+            .expect("expected kindless failure to be announced");
+        assert_eq!(failed.event_kind, "task-failed");
+        assert!(normalize(Agent::Generic, json!({"type": "progress", "exit_code": 1})).is_none());
+        assert!(normalize(Agent::Generic, json!({"type": "build", "status": "failed"})).is_none());
+
+        let errored = normalize(Agent::Generic, json!({"status": "Error", "project": "api"}))
+            .expect("expected error status without a kind");
+        assert_eq!(errored.event_kind, "error");
+        assert_eq!(errored.project_name, "api");
+
+        let succeeded = normalize(
+            Agent::Generic,
+            json!({"type": "task-done", "exit_code": 0, "status": "ok"}),
+        )
+        .expect("expected successful run");
+        assert_eq!(succeeded.event_kind, "task-done");
+        assert!(normalize(Agent::Generic, json!({"type": "progress", "exit_code": 0})).is_none());
+    }
+
     #[test]
     fn extracts_project_name_from_cwd() {
         assert_eq!(
@@ -0,0 +1,328 @@
+//! Append-only JSONL log of every ingested event, kept next to the local state file.
+//! The active file rotates to `history.jsonl.1` (then `.2`, `.3`) once it grows past
+//! [`MAX_FILE_BYTES`], so the log never holds more than a few megabytes.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::agent::Agent;
+use crate::event::NormalizedEvent;
+use crate::lockfile;
+
+pub const MAX_FILE_BYTES: u64 = 1024 * 1024;
+pub const ROTATED_FILES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Delivery {
+    Announced,
+    Filtered,
+    /// Held back by mute, quiet hours or a locked screen and queued for `digest`.
+    Queued,
+    Failed,
+}
+
+impl Delivery {
+    pub fn label(self) -> &'static str {
+        match self {
+            Delivery::Announced => "announced",
+            Delivery::Filtered => "filtered",
+            Delivery::Queued => "queued",
+            Delivery::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix timestamp in seconds.
+    pub timestamp: u64,
+    pub agent: Agent,
+    /// Spoken name when it differs from the agent's built-in one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Turn length in seconds, when the payload or transcript carried one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+    /// Rendered announcement text, set once the event got as far as speaking.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub delivery: Delivery,
+    /// Why the event was filtered or queued, or the speech error for failed deliveries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// `--name` given to `ingest --agent custom` without a `[custom_agents]` definition.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unknown_custom_agent: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Starts an entry for a payload that has not been normalized yet.
+    pub fn new(agent: Agent, source: Option<&str>, timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            agent,
+            agent_name: None,
+            event_kind: None,
+            project: None,
+            cwd: None,
+            source: source.map(ToOwned::to_owned),
+            duration_secs: None,
+            message: None,
+            delivery: Delivery::Filtered,
+            reason: None,
+            unknown_custom_agent: None,
+        }
+    }
+
+    pub fn describe(&mut self, event: &NormalizedEvent) {
+        self.agent_name = event.agent_name.clone();
+        self.event_kind = Some(event.event_kind.clone());
+        self.project = Some(event.project_name.clone());
+        self.cwd = event
+            .cwd
+            .as_ref()
+            .map(|path| path.to_string_lossy().into_owned());
+    }
+
+    pub fn filter(&mut self, reason: impl Into<String>) {
+        self.delivery = Delivery::Filtered;
+        self.reason = Some(reason.into());
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.agent_name
+            .as_deref()
+            .unwrap_or_else(|| self.agent.display_name())
+    }
+
+    pub fn local_time(&self) -> String {
+        format_local_time(self.timestamp)
+    }
+}
+
+/// Formats a Unix timestamp as local `YYYY-MM-DD HH:MM:SS`.
+pub fn format_local_time(timestamp: u64) -> String {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+        .map(|time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryFilter {
+    pub agent: Option<Agent>,
+    /// Case-insensitive project name.
+    pub project: Option<String>,
+    /// Only entries at or after this Unix timestamp.
+    pub since: Option<u64>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        self.agent.is_none_or(|agent| entry.agent == agent)
+            && self.project.as_deref().is_none_or(|project| {
+                entry
+                    .project
+                    .as_deref()
+                    .is_some_and(|entry_project| entry_project.eq_ignore_ascii_case(project))
+            })
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+    }
+}
+
+/// Appends `entry`, rotating first when the active file is full. Both happen under the
+/// lock on `history.jsonl.lock`, so concurrent ingests cannot rotate twice.
+pub fn append(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    let mut line = serde_json::to_string(entry).context("failed to serialize history entry")?;
+    line.push('\n');
+
+    lockfile::exclusive(&sibling_path(path, "lock"), || {
+        let current_len = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+        if current_len > 0 && current_len + line.len() as u64 > MAX_FILE_BYTES {
+            rotate(path)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("failed to write {}", path.display()))
+    })
+}
+
+/// Reads the rotated files and the active one, oldest first. Lines that fail to parse
+/// (for example a partial write) are skipped.
+pub fn load(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let mut files = (1..=ROTATED_FILES)
+        .rev()
+        .map(|index| rotated_path(path, index))
+        .collect::<Vec<_>>();
+    files.push(path.to_path_buf());
+
+    let mut entries = Vec::new();
+    for file in files {
+        if !file.exists() {
+            continue;
+        }
+        let raw = fs::read_to_string(&file)
+            .with_context(|| format!("failed to read {}", file.display()))?;
+        entries.extend(
+            raw.lines()
+                .filter_map(|line| serde_json::from_str::<HistoryEntry>(line).ok()),
+        );
+    }
+    Ok(entries)
+}
+
+/// The newest `limit` entries matching `filter`, oldest first; a `limit` of 0 keeps all.
+pub fn query(
+    entries: Vec<HistoryEntry>,
+    filter: &HistoryFilter,
+    limit: usize,
+) -> Vec<HistoryEntry> {
+    let mut matching = entries
+        .into_iter()
+        .filter(|entry| filter.matches(entry))
+        .collect::<Vec<_>>();
+    if limit > 0 && matching.len() > limit {
+        matching.drain(..matching.len() - limit);
+    }
+    matching
+}
+
+fn rotate(path: &Path) -> Result<()> {
+    for index in (1..ROTATED_FILES).rev() {
+        let from = rotated_path(path, index);
+        if from.exists() {
+            let to = rotated_path(path, index + 1);
+            fs::rename(&from, &to)
+                .with_context(|| format!("failed to rotate {}", from.display()))?;
+        }
+    }
+    let first = rotated_path(path, 1);
+    fs::rename(path, &first).with_context(|| format!("failed to rotate {}", path.display()))
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    sibling_path(path, &index.to_string())
+}
+
+/// `path` with `.{suffix}` appended, such as `history.jsonl.1`.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{suffix}"));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: u64, agent: Agent, project: &str) -> HistoryEntry {
+        HistoryEntry {
+            project: Some(project.to_string()),
+            ..HistoryEntry::new(agent, None, timestamp)
+        }
+    }
+
+    #[test]
+    fn appends_and_loads_entries_in_order() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("history.jsonl");
+        let mut announced = entry(10, Agent::Codex, "backend");
+        announced.delivery = Delivery::Announced;
+        announced.message = Some("Codex failed in backend.".to_string());
+
+        append(&path, &entry(5, Agent::Claude, "api")).expect("append");
+        append(&path, &announced).expect("append");
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "{\"truncated\n").unwrap();
+
+        let entries = load(&path).expect("load");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1], announced);
+    }
+
+    #[test]
+    fn rotates_when_the_active_file_is_full() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("history.jsonl");
+        fs::write(&path, "x".repeat(MAX_FILE_BYTES as usize)).unwrap();
+        fs::write(rotated_path(&path, ROTATED_FILES), "oldest").unwrap();
+
+        append(&path, &entry(1, Agent::Claude, "api")).expect("append");
+
+        assert_eq!(load(&path).expect("load").len(), 1);
+        assert_eq!(
+            fs::metadata(rotated_path(&path, 1)).unwrap().len(),
+            MAX_FILE_BYTES
+        );
+        assert!(!rotated_path(&path, ROTATED_FILES + 1).exists());
+    }
+
+    #[test]
+    fn concurrent_appends_rotate_once() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("history.jsonl");
+        fs::write(&path, "x".repeat(MAX_FILE_BYTES as usize)).unwrap();
+
+        std::thread::scope(|scope| {
+            let appends = (0..8)
+                .map(|timestamp| {
+                    let path = &path;
+                    scope.spawn(move || append(path, &entry(timestamp, Agent::Claude, "api")))
+                })
+                .collect::<Vec<_>>();
+            for append in appends {
+                append.join().expect("thread").expect("append");
+            }
+        });
+
+        assert_eq!(load(&path).expect("load").len(), 8);
+        assert_eq!(
+            fs::metadata(rotated_path(&path, 1)).unwrap().len(),
+            MAX_FILE_BYTES
+        );
+        assert!(!rotated_path(&path, 2).exists());
+    }
+
+    #[test]
+    fn query_filters_and_keeps_the_newest_entries() {
+        let entries = vec![
+            entry(100, Agent::Codex, "backend"),
+            entry(200, Agent::Claude, "backend"),
+            entry(300, Agent::Codex, "Backend"),
+            entry(400, Agent::Codex, "frontend"),
+        ];
+        let filter = HistoryFilter {
+            agent: Some(Agent::Codex),
+            project: Some("backend".to_string()),
+            since: Some(150),
+        };
+
+        let matching = query(entries.clone(), &filter, 0);
+        assert_eq!(matching, vec![entries[2].clone()]);
+
+        let newest = query(entries.clone(), &HistoryFilter::default(), 2);
+        assert_eq!(newest, entries[2..].to_vec());
+    }
+}
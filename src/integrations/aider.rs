@@ -0,0 +1,391 @@
+//! Aider reads `notifications-command` from `.aider.conf.yml` and runs it through the
+//! shell whenever it finishes a reply and waits for input. The YAML file is edited line
+//! by line so comments and unrelated keys survive untouched.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+use super::hooks::{command_program, shell_quote};
+use crate::paths;
+use crate::state::LocalState;
+
+const COMMAND_KEY: &str = "notifications-command";
+const NOTIFICATIONS_KEY: &str = "notifications";
+const SOURCE_MARKER: &str = "--source aider-notify";
+
+pub fn managed_command(executable_path: &Path) -> String {
+    let quoted_exe = shell_quote(executable_path.to_string_lossy().as_ref());
+    let config_dir_arg = paths::config_dir_override()
+        .map(|dir| {
+            format!(
+                " --config-dir {}",
+                shell_quote(dir.to_string_lossy().as_ref())
+            )
+        })
+        .unwrap_or_default();
+    format!(
+        "{quoted_exe}{config_dir_arg} ingest --agent aider --source aider-notify --payload '{{}}'"
+    )
+}
+
+pub fn setup(config_path: &Path, state: &mut LocalState, executable_path: &Path) -> Result<bool> {
+    let mut lines = load_config(config_path)?;
+    let desired = managed_command(executable_path);
+    let changed = apply_setup(&mut lines, state, &desired)
+        .with_context(|| format!("cannot update {}", config_path.display()))?;
+    if changed {
+        write_config(config_path, &lines)?;
+    }
+    Ok(changed)
+}
+
+pub fn remove(config_path: &Path, state: &mut LocalState) -> Result<bool> {
+    if !config_path.exists() {
+        return Ok(false);
+    }
+
+    let mut lines = load_config(config_path)?;
+    let changed = apply_remove(&mut lines, state);
+    if changed {
+        write_config(config_path, &lines)?;
+    }
+    Ok(changed)
+}
+
+pub fn is_configured(config_path: &Path) -> Result<bool> {
+    if !config_path.exists() {
+        return Ok(false);
+    }
+
+    let lines = load_config(config_path)?;
+    Ok(
+        get_value(&lines, COMMAND_KEY).is_some_and(|command| is_managed_command(&command))
+            && get_value(&lines, NOTIFICATIONS_KEY).is_some_and(|value| is_enabled(&value)),
+    )
+}
+
+/// The program the managed notifications command runs.
+pub fn program(config_path: &Path) -> Result<Option<String>> {
+    if !config_path.exists() {
+        return Ok(None);
+    }
+
+    let lines = load_config(config_path)?;
+    Ok(get_value(&lines, COMMAND_KEY)
+        .filter(|command| is_managed_command(command))
+        .and_then(|command| command_program(&command)))
+}
+
+pub fn apply_setup(lines: &mut Vec<String>, state: &mut LocalState, desired: &str) -> Result<bool> {
+    ensure_single_line(lines, COMMAND_KEY)?;
+    ensure_single_line(lines, NOTIFICATIONS_KEY)?;
+    let mut changed = false;
+
+    match get_value(lines, COMMAND_KEY) {
+        Some(ref command) if command == desired => {}
+        Some(ref command) if is_managed_command(command) => {
+            set_value(lines, COMMAND_KEY, &yaml_quote(desired));
+            changed = true;
+        }
+        Some(command) => {
+            if state.aider.previous_notifications_command.is_none() {
+                state.aider.previous_notifications_command = Some(command);
+            }
+            set_value(lines, COMMAND_KEY, &yaml_quote(desired));
+            changed = true;
+        }
+        None => {
+            set_value(lines, COMMAND_KEY, &yaml_quote(desired));
+            changed = true;
+        }
+    }
+
+    if !get_value(lines, NOTIFICATIONS_KEY).is_some_and(|value| is_enabled(&value)) {
+        if !state.aider.enabled_notifications {
+            state.aider.enabled_notifications = true;
+            state.aider.previous_notifications = get_raw_value(lines, NOTIFICATIONS_KEY);
+        }
+        set_value(lines, NOTIFICATIONS_KEY, "true");
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+pub fn apply_remove(lines: &mut Vec<String>, state: &mut LocalState) -> bool {
+    let Some(existing) = get_value(lines, COMMAND_KEY) else {
+        return false;
+    };
+
+    if !is_managed_command(&existing) {
+        return false;
+    }
+
+    match state.aider.previous_notifications_command.take() {
+        Some(previous) => set_value(lines, COMMAND_KEY, &yaml_quote(&previous)),
+        None => remove_key(lines, COMMAND_KEY),
+    }
+    if std::mem::take(&mut state.aider.enabled_notifications) {
+        match state.aider.previous_notifications.take() {
+            Some(previous) => set_value(lines, NOTIFICATIONS_KEY, &previous),
+            None => remove_key(lines, NOTIFICATIONS_KEY),
+        }
+    }
+    true
+}
+
+/// YAML 1.1 booleans, which Aider's config parser accepts for `notifications`.
+fn is_enabled(value: &str) -> bool {
+    matches!(
+        value.to_ascii_lowercase().as_str(),
+        "true" | "yes" | "on" | "y"
+    )
+}
+
+fn is_managed_command(command: &str) -> bool {
+    command.contains("ingest --agent aider") && command.contains(SOURCE_MARKER)
+}
+
+fn load_config(config_path: &Path) -> Result<Vec<String>> {
+    if !config_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let raw = fs::read_to_string(config_path)
+        .with_context(|| format!("failed to read {}", config_path.display()))?;
+    Ok(raw.lines().map(ToOwned::to_owned).collect())
+}
+
+fn write_config(config_path: &Path, lines: &[String]) -> Result<()> {
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+
+    let mut raw = lines.join("\n");
+    raw.push('\n');
+    fs::write(config_path, raw)
+        .with_context(|| format!("failed to write {}", config_path.display()))
+}
+
+/// Index of the top-level `key:` line; indented lines belong to nested mappings.
+fn find_key(lines: &[String], key: &str) -> Option<usize> {
+    lines.iter().position(|line| {
+        line.strip_prefix(key)
+            .map(|rest| rest.trim_start().starts_with(':'))
+            .unwrap_or(false)
+    })
+}
+
+fn get_value(lines: &[String], key: &str) -> Option<String> {
+    let index = find_key(lines, key)?;
+    if spans_lines(lines, index) {
+        return None;
+    }
+    let (_, raw_value) = lines[index].split_once(':')?;
+    parse_scalar(raw_value.trim())
+}
+
+/// The value of `key` as written, trailing comment included; `None` when it is empty.
+fn get_raw_value(lines: &[String], key: &str) -> Option<String> {
+    let (_, raw_value) = lines[find_key(lines, key)?].split_once(':')?;
+    let raw_value = raw_value.trim();
+    (!raw_value.is_empty()).then(|| raw_value.to_string())
+}
+
+/// Block scalars (`|`, `>`), flow collections and values continued on indented lines
+/// cannot be replaced by rewriting the `key:` line.
+fn spans_lines(lines: &[String], index: usize) -> bool {
+    let raw_value = lines[index]
+        .split_once(':')
+        .map(|(_, raw_value)| raw_value.trim())
+        .unwrap_or_default();
+    raw_value.starts_with(['|', '>', '[', '{'])
+        || lines
+            .get(index + 1)
+            .is_some_and(|next| next.starts_with([' ', '\t']) && !next.trim().is_empty())
+}
+
+fn ensure_single_line(lines: &[String], key: &str) -> Result<()> {
+    if find_key(lines, key).is_some_and(|index| spans_lines(lines, index)) {
+        bail!("{key} is a block or flow value; rewrite it as a single-line string first");
+    }
+    Ok(())
+}
+
+fn set_value(lines: &mut Vec<String>, key: &str, yaml_value: &str) {
+    let line = format!("{key}: {yaml_value}");
+    match find_key(lines, key) {
+        Some(index) => lines[index] = line,
+        None => lines.push(line),
+    }
+}
+
+fn remove_key(lines: &mut Vec<String>, key: &str) {
+    if let Some(index) = find_key(lines, key) {
+        lines.remove(index);
+    }
+}
+
+/// Parses a single-line YAML scalar: double-quoted, single-quoted or plain with an
+/// optional trailing comment.
+fn parse_scalar(raw: &str) -> Option<String> {
+    if let Some(inner) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = inner.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' => return Some(value),
+                '\\' => match chars.next()? {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    other => value.push(other),
+                },
+                other => value.push(other),
+            }
+        }
+        return None;
+    }
+
+    if let Some(inner) = raw.strip_prefix('\'') {
+        let mut value = String::new();
+        let mut chars = inner.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if ch == '\'' {
+                if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    value.push('\'');
+                } else {
+                    return Some(value);
+                }
+            } else {
+                value.push(ch);
+            }
+        }
+        return None;
+    }
+
+    let value = match raw.find(" #") {
+        Some(index) => &raw[..index],
+        None => raw,
+    }
+    .trim_end();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn yaml_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANAGED: &str =
+        "'/tmp/agitiser-notify' ingest --agent aider --source aider-notify --payload '{}'";
+
+    fn lines(raw: &str) -> Vec<String> {
+        raw.lines().map(ToOwned::to_owned).collect()
+    }
+
+    #[test]
+    fn setup_preserves_comments_and_saves_previous_command() {
+        let mut config = lines(
+            "# my aider config\nmodel: sonnet\nnotifications-command: 'notify-send ''Aider done''' # bell\n",
+        );
+        let mut state = LocalState::default();
+
+        assert!(apply_setup(&mut config, &mut state, MANAGED).expect("setup"));
+        assert_eq!(
+            state.aider.previous_notifications_command.as_deref(),
+            Some("notify-send 'Aider done'")
+        );
+        assert!(state.aider.enabled_notifications);
+        assert_eq!(config[0], "# my aider config");
+        assert_eq!(config[1], "model: sonnet");
+        assert_eq!(get_value(&config, COMMAND_KEY).as_deref(), Some(MANAGED));
+        assert_eq!(
+            get_value(&config, NOTIFICATIONS_KEY).as_deref(),
+            Some("true")
+        );
+
+        assert!(!apply_setup(&mut config, &mut state, MANAGED).expect("setup"));
+    }
+
+    #[test]
+    fn remove_restores_previous_command_and_notifications() {
+        let mut config = lines("model: sonnet\nnotifications: false # quiet\n");
+        let mut state = LocalState::default();
+        apply_setup(&mut config, &mut state, MANAGED).expect("setup");
+
+        assert!(apply_remove(&mut config, &mut state));
+        assert_eq!(
+            config,
+            lines("model: sonnet\nnotifications: false # quiet\n")
+        );
+        assert!(!state.aider.enabled_notifications);
+        assert_eq!(state.aider.previous_notifications, None);
+
+        let mut config = lines("model: sonnet\n");
+        apply_setup(&mut config, &mut state, MANAGED).expect("setup");
+        assert!(apply_remove(&mut config, &mut state));
+        assert_eq!(config, lines("model: sonnet\n"));
+
+        let mut config = lines(&format!(
+            "notifications: true\nnotifications-command: {}\n",
+            yaml_quote(MANAGED)
+        ));
+        state.aider.previous_notifications_command = Some("say done".to_string());
+        assert!(apply_remove(&mut config, &mut state));
+        assert_eq!(get_value(&config, COMMAND_KEY).as_deref(), Some("say done"));
+        assert_eq!(
+            get_value(&config, NOTIFICATIONS_KEY).as_deref(),
+            Some("true")
+        );
+    }
+
+    #[test]
+    fn user_enabled_notifications_are_left_alone() {
+        for enabled in ["True", "yes", "ON"] {
+            let mut config = lines(&format!("notifications: {enabled}\n"));
+            let mut state = LocalState::default();
+            apply_setup(&mut config, &mut state, MANAGED).expect("setup");
+            assert!(!state.aider.enabled_notifications);
+
+            assert!(apply_remove(&mut config, &mut state));
+            assert_eq!(config, lines(&format!("notifications: {enabled}\n")));
+        }
+    }
+
+    #[test]
+    fn setup_refuses_multi_line_values() {
+        let mut state = LocalState::default();
+        for raw in [
+            "notifications-command: |\n  say done\n",
+            "notifications-command: >-\n  say\n  done\n",
+            "notifications-command: [say, done]\n",
+            "notifications-command:\n  say done\n",
+        ] {
+            let mut config = lines(raw);
+            assert!(
+                apply_setup(&mut config, &mut state, MANAGED).is_err(),
+                "{raw}"
+            );
+            assert_eq!(config, lines(raw));
+        }
+        assert_eq!(
+            get_value(
+                &lines("notifications-command: |\n  say done\n"),
+                COMMAND_KEY
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn nested_keys_are_not_matched() {
+        let config = lines("extra:\n  notifications-command: nested\n");
+        assert_eq!(get_value(&config, COMMAND_KEY), None);
+    }
+}
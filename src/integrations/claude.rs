@@ -1,27 +1,83 @@
-use anyhow::{Context, Result};
-use serde_json::{json, Map, Value};
-use std::fs;
+use anyhow::Result;
+use serde_json::Value;
 use std::path::Path;
 
+use super::hooks::{self, shell_quote, HookTarget};
+use crate::paths;
+use crate::state::{ClaudeHookConfig, ClaudeHookKey, ClaudeState};
+
 const STOP_EVENT: &str = "Stop";
+const STOP_FAILURE_EVENT: &str = "StopFailure";
 const SUBAGENT_STOP_EVENT: &str = "SubagentStop";
 const PERMISSION_REQUEST_EVENT: &str = "PermissionRequest";
 const PERMISSION_REQUEST_MATCHER: &str = "ExitPlanMode";
+const USER_PROMPT_SUBMIT_EVENT: &str = "UserPromptSubmit";
 const SOURCE_MARKER: &str = "--source claude-hook";
 
 pub fn managed_command(executable_path: &Path) -> String {
     let quoted_exe = shell_quote(executable_path.to_string_lossy().as_ref());
+    let config_dir_arg = paths::config_dir_override()
+        .map(|dir| {
+            format!(
+                " --config-dir {}",
+                shell_quote(dir.to_string_lossy().as_ref())
+            )
+        })
+        .unwrap_or_default();
     format!(
-        "AGITISER_NOTIFY=1 {quoted_exe} ingest --agent claude --source claude-hook >/dev/null 2>&1"
+        "AGITISER_NOTIFY=1 {quoted_exe}{config_dir_arg} ingest --agent claude --source claude-hook >/dev/null 2>&1"
     )
 }
 
-pub fn setup(settings_path: &Path, executable_path: &Path) -> Result<bool> {
-    let mut settings = load_settings(settings_path)?;
+pub fn default_managed_hooks() -> Vec<ClaudeHookConfig> {
+    [
+        (STOP_EVENT, "*"),
+        (STOP_FAILURE_EVENT, "*"),
+        (SUBAGENT_STOP_EVENT, "*"),
+        (PERMISSION_REQUEST_EVENT, PERMISSION_REQUEST_MATCHER),
+        (USER_PROMPT_SUBMIT_EVENT, "*"),
+    ]
+    .into_iter()
+    .map(|(event, matcher)| ClaudeHookConfig {
+        event: event.to_string(),
+        matcher: matcher.to_string(),
+        event_kind: None,
+    })
+    .collect()
+}
+
+/// The defaults that are not disabled, with the configured hooks overriding a default of the
+/// same event and matcher and appended otherwise.
+pub fn managed_hooks(state: &ClaudeState) -> Vec<ClaudeHookConfig> {
+    let mut hooks: Vec<ClaudeHookConfig> = default_managed_hooks()
+        .into_iter()
+        .filter(|hook| !state.disabled_hooks.contains(&hook.key()))
+        .collect();
+    for configured in &state.hooks {
+        match hooks.iter_mut().find(|hook| hook.key() == configured.key()) {
+            Some(hook) => *hook = configured.clone(),
+            None => hooks.push(configured.clone()),
+        }
+    }
+    hooks
+}
+
+pub fn is_default_hook(key: &ClaudeHookKey) -> bool {
+    default_managed_hooks()
+        .iter()
+        .any(|hook| hook.key() == *key)
+}
+
+pub fn setup(
+    settings_path: &Path,
+    executable_path: &Path,
+    hooks: &[ClaudeHookConfig],
+) -> Result<bool> {
+    let mut settings = hooks::load_settings(settings_path)?;
     let command = managed_command(executable_path);
-    let changed = apply_setup(&mut settings, &command);
+    let changed = apply_setup(&mut settings, &command, hooks);
     if changed {
-        write_settings(settings_path, &settings)?;
+        hooks::write_settings(settings_path, &settings)?;
     }
     Ok(changed)
 }
@@ -31,286 +87,97 @@ pub fn remove(settings_path: &Path) -> Result<bool> {
         return Ok(false);
     }
 
-    let mut settings = load_settings(settings_path)?;
-    let had_managed_hooks = has_managed_hook(&settings);
+    let mut settings = hooks::load_settings(settings_path)?;
+    let had_managed_hooks = hooks::has_managed_hook(&settings, is_managed_command);
     let changed = apply_remove(&mut settings);
     if changed {
-        write_settings(settings_path, &settings)?;
+        hooks::write_settings(settings_path, &settings)?;
     }
-    Ok(had_managed_hooks && !has_managed_hook(&settings))
+    Ok(had_managed_hooks && !hooks::has_managed_hook(&settings, is_managed_command))
 }
 
-pub fn is_configured(settings_path: &Path) -> Result<bool> {
+pub fn is_configured(settings_path: &Path, hooks: &[ClaudeHookConfig]) -> Result<bool> {
     if !settings_path.exists() {
         return Ok(false);
     }
 
-    let settings = load_settings(settings_path)?;
-    Ok(has_managed_hook(&settings))
-}
-
-pub fn apply_setup(settings: &mut Value, command: &str) -> bool {
-    let mut changed = false;
-
-    let root_obj = ensure_root_object(settings);
-    let hooks_obj = ensure_object_entry(root_obj, "hooks");
-    let stop_hooks = ensure_array_entry(hooks_obj, STOP_EVENT);
-    if ensure_managed_hook(stop_hooks, command, "*") {
-        changed = true;
-    }
-
-    let subagent_stop_hooks = ensure_array_entry(hooks_obj, SUBAGENT_STOP_EVENT);
-    if ensure_managed_hook(subagent_stop_hooks, command, "*") {
-        changed = true;
-    }
-
-    let permission_request_hooks = ensure_array_entry(hooks_obj, PERMISSION_REQUEST_EVENT);
-    if ensure_managed_hook(
-        permission_request_hooks,
-        command,
-        PERMISSION_REQUEST_MATCHER,
-    ) {
-        changed = true;
-    }
-
-    changed
-}
-
-pub fn apply_remove(settings: &mut Value) -> bool {
-    let mut changed = false;
-
-    let root_obj = match settings.as_object_mut() {
-        Some(root_obj) => root_obj,
-        None => return false,
-    };
-
-    let hooks_obj = match root_obj.get_mut("hooks").and_then(Value::as_object_mut) {
-        Some(hooks_obj) => hooks_obj,
-        None => return false,
-    };
-
-    for event in [STOP_EVENT, SUBAGENT_STOP_EVENT, PERMISSION_REQUEST_EVENT] {
-        let mut remove_event = false;
-        if let Some(event_hooks) = hooks_obj.get_mut(event).and_then(Value::as_array_mut) {
-            if remove_managed_hooks(event_hooks) {
-                changed = true;
-            }
-            remove_event = event_hooks.is_empty();
-        }
-        if remove_event {
-            hooks_obj.remove(event);
-            changed = true;
-        }
-    }
-
-    if hooks_obj.is_empty() {
-        root_obj.remove("hooks");
-        changed = true;
-    }
-
-    changed
-}
-
-fn has_managed_hook(settings: &Value) -> bool {
-    let hooks_obj = match settings.get("hooks").and_then(Value::as_object) {
-        Some(hooks_obj) => hooks_obj,
-        None => return false,
-    };
-
-    [STOP_EVENT, SUBAGENT_STOP_EVENT, PERMISSION_REQUEST_EVENT]
-        .iter()
-        .any(|event| {
-            hooks_obj
-                .get(*event)
-                .and_then(Value::as_array)
-                .map(|entries| event_has_managed_hook(entries))
-                .unwrap_or(false)
-        })
-}
-
-fn is_managed_command(command: &str) -> bool {
-    command.contains("ingest --agent claude") && command.contains(SOURCE_MARKER)
+    let settings = hooks::load_settings(settings_path)?;
+    Ok(has_managed_hooks_for(&settings, hooks))
 }
 
-fn event_has_managed_hook(entries: &[Value]) -> bool {
-    entries.iter().any(|entry| {
-        entry
-            .get("hooks")
-            .and_then(Value::as_array)
-            .map(|hooks| {
-                hooks.iter().any(|hook| {
-                    hook.get("command")
-                        .and_then(Value::as_str)
-                        .map(is_managed_command)
-                        .unwrap_or(false)
-                })
-            })
-            .unwrap_or(false)
-    })
+/// How the managed hooks in one settings file compare with the wanted set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookInspection {
+    /// Wanted hooks without a managed command under their matcher.
+    pub missing: Vec<ClaudeHookConfig>,
+    /// The programs the managed commands run, once each; empty when none is installed.
+    pub programs: Vec<String>,
 }
 
-fn ensure_managed_hook(event_hooks: &mut Vec<Value>, command: &str, matcher: &str) -> bool {
-    let mut changed = false;
-    let mut desired_exists = false;
-
-    for entry in event_hooks.iter_mut() {
-        let entry_matcher_matches = entry
-            .get("matcher")
-            .and_then(Value::as_str)
-            .unwrap_or_default()
-            == matcher;
-        if let Some(hooks_array) = entry
-            .as_object_mut()
-            .and_then(|obj| obj.get_mut("hooks"))
-            .and_then(Value::as_array_mut)
-        {
-            let original_len = hooks_array.len();
-            hooks_array.retain(|hook| {
-                let hook_command = hook
-                    .get("command")
-                    .and_then(Value::as_str)
-                    .unwrap_or_default()
-                    .to_string();
-
-                if !is_managed_command(&hook_command) {
-                    return true;
-                }
-
-                if hook_command == command && entry_matcher_matches && !desired_exists {
-                    desired_exists = true;
-                    true
-                } else {
-                    false
-                }
-            });
-
-            if hooks_array.len() != original_len {
-                changed = true;
-            }
-        }
-    }
-
-    let original_len = event_hooks.len();
-    event_hooks.retain(|entry| {
-        entry
-            .get("hooks")
-            .and_then(Value::as_array)
-            .map(|hooks| !hooks.is_empty())
-            .unwrap_or(true)
-    });
-    if event_hooks.len() != original_len {
-        changed = true;
-    }
-
-    if !desired_exists {
-        event_hooks.push(json!({
-            "matcher": matcher,
-            "hooks": [
-                {
-                    "type": "command",
-                    "command": command,
-                }
-            ]
-        }));
-        changed = true;
+pub fn inspect(settings_path: &Path, hooks: &[ClaudeHookConfig]) -> Result<HookInspection> {
+    if !settings_path.exists() {
+        return Ok(HookInspection {
+            missing: hooks.to_vec(),
+            programs: Vec::new(),
+        });
     }
 
-    changed
+    let settings = hooks::load_settings(settings_path)?;
+    Ok(inspect_settings(&settings, hooks))
 }
 
-fn remove_managed_hooks(event_hooks: &mut Vec<Value>) -> bool {
-    let mut changed = false;
-
-    for entry in event_hooks.iter_mut() {
-        if let Some(hooks_array) = entry
-            .as_object_mut()
-            .and_then(|obj| obj.get_mut("hooks"))
-            .and_then(Value::as_array_mut)
-        {
-            let original_len = hooks_array.len();
-            hooks_array.retain(|hook| {
-                let hook_command = hook
-                    .get("command")
-                    .and_then(Value::as_str)
-                    .unwrap_or_default();
-                !is_managed_command(hook_command)
-            });
-            if hooks_array.len() != original_len {
-                changed = true;
+pub fn inspect_settings(settings: &Value, hooks: &[ClaudeHookConfig]) -> HookInspection {
+    let missing = hooks::missing_targets(settings, &targets(hooks), is_managed_command);
+    let mut programs = Vec::new();
+    for command in hooks::managed_commands(settings, is_managed_command) {
+        if let Some(program) = hooks::command_program(&command) {
+            if !programs.contains(&program) {
+                programs.push(program);
             }
         }
     }
-
-    let original_len = event_hooks.len();
-    event_hooks.retain(|entry| {
-        entry
-            .get("hooks")
-            .and_then(Value::as_array)
-            .map(|hooks| !hooks.is_empty())
-            .unwrap_or(true)
-    });
-    if event_hooks.len() != original_len {
-        changed = true;
-    }
-
-    changed
-}
-
-fn load_settings(settings_path: &Path) -> Result<Value> {
-    if !settings_path.exists() {
-        return Ok(json!({}));
-    }
-
-    let raw = fs::read_to_string(settings_path)
-        .with_context(|| format!("failed to read {}", settings_path.display()))?;
-    if raw.trim().is_empty() {
-        return Ok(json!({}));
+    HookInspection {
+        missing: hooks
+            .iter()
+            .filter(|hook| {
+                missing
+                    .iter()
+                    .any(|(event, matcher)| hook.event == *event && hook.matcher == *matcher)
+            })
+            .cloned()
+            .collect(),
+        programs,
     }
-
-    serde_json::from_str(&raw)
-        .with_context(|| format!("failed to parse {}", settings_path.display()))
 }
 
-fn write_settings(settings_path: &Path, settings: &Value) -> Result<()> {
-    if let Some(parent) = settings_path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("failed to create {}", parent.display()))?;
-    }
-
-    let raw =
-        serde_json::to_string_pretty(settings).context("failed to serialize settings.json")?;
-    fs::write(settings_path, format!("{raw}\n"))
-        .with_context(|| format!("failed to write {}", settings_path.display()))
+pub fn apply_setup(settings: &mut Value, command: &str, hooks: &[ClaudeHookConfig]) -> bool {
+    hooks::apply_setup(settings, command, &targets(hooks), is_managed_command)
 }
 
-fn ensure_root_object(value: &mut Value) -> &mut Map<String, Value> {
-    if !value.is_object() {
-        *value = json!({});
-    }
-    value.as_object_mut().expect("root should be an object")
+pub fn apply_remove(settings: &mut Value) -> bool {
+    let default_events = [
+        STOP_EVENT,
+        STOP_FAILURE_EVENT,
+        SUBAGENT_STOP_EVENT,
+        PERMISSION_REQUEST_EVENT,
+        USER_PROMPT_SUBMIT_EVENT,
+    ];
+    hooks::apply_remove(settings, &default_events, is_managed_command)
 }
 
-fn ensure_object_entry<'a>(
-    obj: &'a mut Map<String, Value>,
-    key: &str,
-) -> &'a mut Map<String, Value> {
-    let value = obj.entry(key.to_string()).or_insert_with(|| json!({}));
-    if !value.is_object() {
-        *value = json!({});
-    }
-    value.as_object_mut().expect("entry should be an object")
+fn targets(hooks: &[ClaudeHookConfig]) -> Vec<HookTarget<'_>> {
+    hooks
+        .iter()
+        .map(|hook| (hook.event.as_str(), hook.matcher.as_str()))
+        .collect()
 }
 
-fn ensure_array_entry<'a>(obj: &'a mut Map<String, Value>, key: &str) -> &'a mut Vec<Value> {
-    let value = obj.entry(key.to_string()).or_insert_with(|| json!([]));
-    if !value.is_array() {
-        *value = json!([]);
-    }
-    value.as_array_mut().expect("entry should be an array")
+fn has_managed_hooks_for(settings: &Value, hooks: &[ClaudeHookConfig]) -> bool {
+    hooks::has_managed_hooks_for(settings, &targets(hooks), is_managed_command)
 }
 
-fn shell_quote(value: &str) -> String {
-    format!("'{}'", value.replace('\'', "'\"'\"'"))
+fn is_managed_command(command: &str) -> bool {
+    command.contains("ingest --agent claude") && command.contains(SOURCE_MARKER)
 }
 
 #[cfg(test)]
@@ -338,18 +205,20 @@ mod tests {
         let mut settings = json!({});
 
         assert!(
-            apply_setup(&mut settings, command),
+            apply_setup(&mut settings, command, &default_managed_hooks()),
             "first setup should change"
         );
         assert_eq!(managed_hook_count(&settings, STOP_EVENT), 1);
+        assert_eq!(managed_hook_count(&settings, STOP_FAILURE_EVENT), 1);
         assert_eq!(managed_hook_count(&settings, SUBAGENT_STOP_EVENT), 1);
         assert_eq!(managed_hook_count(&settings, PERMISSION_REQUEST_EVENT), 1);
+        assert_eq!(managed_hook_count(&settings, USER_PROMPT_SUBMIT_EVENT), 1);
         assert_eq!(
             settings["hooks"][PERMISSION_REQUEST_EVENT][0]["matcher"],
             PERMISSION_REQUEST_MATCHER
         );
         assert!(
-            !apply_setup(&mut settings, command),
+            !apply_setup(&mut settings, command, &default_managed_hooks()),
             "second setup should be idempotent"
         );
         assert_eq!(managed_hook_count(&settings, STOP_EVENT), 1);
@@ -361,6 +230,113 @@ mod tests {
         );
     }
 
+    #[test]
+    fn setup_installs_configured_hooks_and_prunes_dropped_events() {
+        let command =
+            "AGITISER_NOTIFY=1 '/tmp/agitiser-notify' ingest --agent claude --source claude-hook";
+        let mut settings = json!({});
+        let mut hooks = default_managed_hooks();
+        hooks.push(ClaudeHookConfig {
+            event: "PostToolUse".to_string(),
+            matcher: "Bash".to_string(),
+            event_kind: None,
+        });
+        hooks.push(ClaudeHookConfig {
+            event: PERMISSION_REQUEST_EVENT.to_string(),
+            matcher: "Bash".to_string(),
+            event_kind: None,
+        });
+
+        assert!(apply_setup(&mut settings, command, &hooks));
+        assert_eq!(managed_hook_count(&settings, "PostToolUse"), 1);
+        assert_eq!(managed_hook_count(&settings, PERMISSION_REQUEST_EVENT), 2);
+        assert!(has_managed_hooks_for(&settings, &hooks));
+        assert!(!apply_setup(&mut settings, command, &hooks));
+
+        let defaults = default_managed_hooks();
+        assert!(apply_setup(&mut settings, command, &defaults));
+        assert!(settings["hooks"].get("PostToolUse").is_none());
+        assert_eq!(managed_hook_count(&settings, PERMISSION_REQUEST_EVENT), 1);
+        assert!(!has_managed_hooks_for(&settings, &hooks));
+        assert!(has_managed_hooks_for(&settings, &defaults));
+    }
+
+    #[test]
+    fn managed_hooks_merge_configured_hooks_over_current_defaults() {
+        let state = ClaudeState {
+            hooks: vec![
+                ClaudeHookConfig {
+                    event: STOP_EVENT.to_string(),
+                    matcher: "*".to_string(),
+                    event_kind: Some("turn-done".to_string()),
+                },
+                ClaudeHookConfig {
+                    event: "PostToolUse".to_string(),
+                    matcher: "Bash".to_string(),
+                    event_kind: None,
+                },
+            ],
+            disabled_hooks: vec![ClaudeHookKey {
+                event: SUBAGENT_STOP_EVENT.to_string(),
+                matcher: "*".to_string(),
+            }],
+        };
+
+        let hooks = managed_hooks(&state);
+        let events: Vec<&str> = hooks.iter().map(|hook| hook.event.as_str()).collect();
+        assert_eq!(
+            events,
+            [
+                STOP_EVENT,
+                STOP_FAILURE_EVENT,
+                PERMISSION_REQUEST_EVENT,
+                USER_PROMPT_SUBMIT_EVENT,
+                "PostToolUse"
+            ]
+        );
+        assert_eq!(hooks[0].event_kind.as_deref(), Some("turn-done"));
+        assert_eq!(
+            managed_hooks(&ClaudeState::default()),
+            default_managed_hooks()
+        );
+    }
+
+    #[test]
+    fn inspect_reports_missing_hooks_and_programs() {
+        let command =
+            "AGITISER_NOTIFY=1 '/tmp/old prefix/agitiser-notify' ingest --agent claude --source claude-hook";
+        let mut settings = json!({});
+        let hooks = default_managed_hooks();
+        apply_setup(&mut settings, command, &hooks);
+        settings["hooks"][PERMISSION_REQUEST_EVENT][0]["matcher"] = json!("Bash");
+
+        let inspection = inspect_settings(&settings, &hooks);
+        assert_eq!(inspection.programs, vec!["/tmp/old prefix/agitiser-notify"]);
+        assert_eq!(inspection.missing.len(), 1);
+        assert_eq!(inspection.missing[0].event, PERMISSION_REQUEST_EVENT);
+        assert_eq!(inspection.missing[0].matcher, PERMISSION_REQUEST_MATCHER);
+
+        let empty = inspect_settings(&json!({}), &hooks);
+        assert!(empty.programs.is_empty());
+        assert_eq!(empty.missing, hooks);
+    }
+
+    #[test]
+    fn remove_cleans_up_configured_events() {
+        let command =
+            "AGITISER_NOTIFY=1 '/tmp/agitiser-notify' ingest --agent claude --source claude-hook";
+        let mut settings = json!({});
+        let hooks = vec![ClaudeHookConfig {
+            event: "PostToolUse".to_string(),
+            matcher: "Bash".to_string(),
+            event_kind: None,
+        }];
+
+        assert!(apply_setup(&mut settings, command, &hooks));
+        assert!(apply_remove(&mut settings));
+        assert!(settings.get("hooks").is_none());
+    }
+
     #[test]
     fn remove_keeps_unmanaged_hooks_for_stop_and_subagent_stop() {
         let mut settings = json!({
@@ -1,27 +1,35 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::fs;
 use std::path::Path;
+use std::process::Command;
 use toml_edit::{Array, DocumentMut, Item, Value};
 
-use crate::state::LocalState;
+use crate::paths;
+use crate::state::{CodexState, LocalState};
 
 const SOURCE_VALUE: &str = "codex-notify";
 
 pub fn managed_notify_command(executable_path: &Path) -> Vec<String> {
-    vec![
-        executable_path.to_string_lossy().to_string(),
+    let mut command = vec![executable_path.to_string_lossy().to_string()];
+    if let Some(dir) = paths::config_dir_override() {
+        command.push("--config-dir".to_string());
+        command.push(dir.to_string_lossy().to_string());
+    }
+    command.extend([
         "ingest".to_string(),
         "--agent".to_string(),
         "codex".to_string(),
         "--source".to_string(),
         SOURCE_VALUE.to_string(),
-    ]
+    ]);
+    command
 }
 
 pub fn setup(config_path: &Path, state: &mut LocalState, executable_path: &Path) -> Result<bool> {
     let mut doc = load_config(config_path)?;
     let desired = managed_notify_command(executable_path);
-    let changed = apply_setup(&mut doc, state, &desired);
+    let changed = apply_setup(&mut doc, state, &desired)
+        .with_context(|| format!("cannot update {}", config_path.display()))?;
     if changed {
         write_config(config_path, &doc)?;
     }
@@ -52,26 +60,65 @@ pub fn is_configured(config_path: &Path) -> Result<bool> {
         .unwrap_or(false))
 }
 
-pub fn apply_setup(doc: &mut DocumentMut, state: &mut LocalState, desired: &[String]) -> bool {
+/// What the `notify` key of the Codex config holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifyStatus {
+    Missing,
+    /// Not an array of strings, which Codex cannot run.
+    Malformed,
+    Unmanaged(Vec<String>),
+    Managed(Vec<String>),
+}
+
+pub fn inspect(config_path: &Path) -> Result<NotifyStatus> {
+    if !config_path.exists() {
+        return Ok(NotifyStatus::Missing);
+    }
+
+    let doc = load_config(config_path)?;
+    if doc.get("notify").is_none() {
+        return Ok(NotifyStatus::Missing);
+    }
+    Ok(match extract_notify(&doc) {
+        Some(notify) if notify.is_empty() => NotifyStatus::Malformed,
+        Some(notify) if is_managed_notify(&notify) => NotifyStatus::Managed(notify),
+        Some(notify) => NotifyStatus::Unmanaged(notify),
+        None => NotifyStatus::Malformed,
+    })
+}
+
+/// Replaces `notify` with `desired`, saving a user's own value in `state` for `remove`. A
+/// `notify` that is not an array of strings is saved as TOML source; one that is not a
+/// value at all is refused.
+pub fn apply_setup(
+    doc: &mut DocumentMut,
+    state: &mut LocalState,
+    desired: &[String],
+) -> Result<bool> {
     let existing = extract_notify(doc);
     match existing {
-        Some(ref notify) if notify == desired => false,
-        Some(ref notify) if is_managed_notify(notify) => {
-            set_notify(doc, desired);
-            true
-        }
+        Some(ref notify) if notify == desired => return Ok(false),
+        Some(ref notify) if is_managed_notify(notify) => {}
         Some(notify) => {
             if state.codex.previous_notify.is_none() {
                 state.codex.previous_notify = Some(notify);
             }
-            set_notify(doc, desired);
-            true
         }
         None => {
-            set_notify(doc, desired);
-            true
+            if let Some(item) = doc.get("notify") {
+                let Some(value) = item.as_value() else {
+                    bail!("notify is a table, not a command; move it aside and run setup again");
+                };
+                if state.codex.previous_notify.is_none()
+                    && state.codex.previous_notify_raw.is_none()
+                {
+                    state.codex.previous_notify_raw = Some(value.to_string().trim().to_string());
+                }
+            }
         }
     }
+    set_notify(doc, desired);
+    Ok(true)
 }
 
 pub fn apply_remove(doc: &mut DocumentMut, state: &mut LocalState) -> bool {
@@ -85,12 +132,47 @@ pub fn apply_remove(doc: &mut DocumentMut, state: &mut LocalState) -> bool {
 
     if let Some(previous) = state.codex.previous_notify.take() {
         set_notify(doc, &previous);
+    } else if let Some(previous) = state
+        .codex
+        .previous_notify_raw
+        .take()
+        .and_then(|raw| raw.parse::<Value>().ok())
+    {
+        doc["notify"] = Item::Value(previous);
     } else {
         remove_notify(doc);
     }
     true
 }
 
+/// Runs the notify command that setup replaced, passing `payload` as its final argument
+/// the same way Codex would. Returns `false` when chaining is disabled or nothing was saved.
+pub fn run_previous_notify(state: &CodexState, payload: &str) -> Result<bool> {
+    if !state.chain_previous_notify {
+        return Ok(false);
+    }
+    let Some((program, args)) = state
+        .previous_notify
+        .as_deref()
+        .and_then(|previous| previous.split_first())
+    else {
+        return Ok(false);
+    };
+    if is_managed_notify(state.previous_notify.as_deref().unwrap_or_default()) {
+        return Ok(false);
+    }
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(payload)
+        .status()
+        .with_context(|| format!("failed to execute previous notify command {program}"))?;
+    if !status.success() {
+        bail!("previous notify command {program} exited with {status}");
+    }
+    Ok(true)
+}
+
 fn is_managed_notify(notify: &[String]) -> bool {
     let has = |needle: &str| notify.iter().any(|s| s == needle);
     has("ingest") && has("--agent") && has("codex") && has("--source") && has(SOURCE_VALUE)
@@ -147,15 +229,41 @@ mod tests {
     use super::*;
     use crate::state::{CodexState, LocalState};
 
+    #[test]
+    fn run_previous_notify_requires_chaining_and_saved_command() {
+        let mut state = CodexState {
+            previous_notify: Some(vec!["true".to_string()]),
+            chain_previous_notify: false,
+            ..CodexState::default()
+        };
+        assert!(!run_previous_notify(&state, "{}").expect("disabled chain"));
+
+        state.chain_previous_notify = true;
+        assert!(run_previous_notify(&state, "{}").expect("chained notify"));
+
+        state.previous_notify = None;
+        assert!(!run_previous_notify(&state, "{}").expect("nothing saved"));
+    }
+
     #[test]
     fn setup_saves_previous_notify_and_sets_managed_command() {
         let mut doc =
             r#"notify = ["notify-send", "Codex"]"#.parse::<DocumentMut>().expect("valid toml");
         let mut state = LocalState {
+            claude: crate::state::ClaudeState::default(),
             codex: CodexState::default(),
+            aider: crate::state::AiderState::default(),
             templates: crate::state::TemplateConfig::default(),
             event_kind_labels: crate::state::EventKindLabelsConfig::default(),
             notifications: crate::state::NotificationConfig::default(),
+            summary: crate::state::SummaryConfig::default(),
+            speech: crate::state::SpeechConfig::default(),
+            quiet: crate::state::QuietConfig::default(),
+            rate_limit: crate::state::RateLimitConfig::default(),
+            dedup: crate::state::DedupConfig::default(),
+            command: crate::state::CommandConfig::default(),
+            generic: Default::default(),
+            custom_agents: Default::default(),
         };
         let managed = vec![
             "/tmp/agitiser-notify".to_string(),
@@ -166,7 +274,7 @@ mod tests {
             "codex-notify".to_string(),
         ];
 
-        assert!(apply_setup(&mut doc, &mut state, &managed));
+        assert!(apply_setup(&mut doc, &mut state, &managed).expect("setup"));
         assert_eq!(
             state.codex.previous_notify,
             Some(vec!["notify-send".to_string(), "Codex".to_string()])
@@ -180,12 +288,24 @@ mod tests {
             .parse::<DocumentMut>()
             .expect("valid toml");
         let mut state = LocalState {
+            claude: crate::state::ClaudeState::default(),
             codex: CodexState {
                 previous_notify: Some(vec!["notify-send".to_string(), "Codex".to_string()]),
+                chain_previous_notify: false,
+                ..CodexState::default()
             },
+            aider: crate::state::AiderState::default(),
             templates: crate::state::TemplateConfig::default(),
             event_kind_labels: crate::state::EventKindLabelsConfig::default(),
             notifications: crate::state::NotificationConfig::default(),
+            summary: crate::state::SummaryConfig::default(),
+            speech: crate::state::SpeechConfig::default(),
+            quiet: crate::state::QuietConfig::default(),
+            rate_limit: crate::state::RateLimitConfig::default(),
+            dedup: crate::state::DedupConfig::default(),
+            command: crate::state::CommandConfig::default(),
+            generic: Default::default(),
+            custom_agents: Default::default(),
         };
 
         assert!(apply_remove(&mut doc, &mut state));
@@ -195,4 +315,59 @@ mod tests {
         );
         assert!(state.codex.previous_notify.is_none());
     }
+
+    #[test]
+    fn malformed_notify_is_restored_on_remove() {
+        let original = "model = \"o3\"\nnotify = \"notify-send Codex\"\n";
+        let mut doc = original.parse::<DocumentMut>().expect("valid toml");
+        let mut state = LocalState::default();
+        let managed = managed_notify_command(Path::new("/tmp/agitiser-notify"));
+
+        assert!(apply_setup(&mut doc, &mut state, &managed).expect("setup"));
+        assert_eq!(
+            state.codex.previous_notify_raw.as_deref(),
+            Some("\"notify-send Codex\"")
+        );
+        assert_eq!(extract_notify(&doc).as_deref(), Some(managed.as_slice()));
+
+        assert!(apply_remove(&mut doc, &mut state));
+        assert_eq!(doc.to_string(), original);
+        assert!(state.codex.previous_notify_raw.is_none());
+
+        let mut table = "[notify]\ncommand = \"notify-send\"\n"
+            .parse::<DocumentMut>()
+            .expect("valid toml");
+        assert!(apply_setup(&mut table, &mut state, &managed).is_err());
+        assert!(state.codex.previous_notify_raw.is_none());
+    }
+
+    #[test]
+    fn inspect_tells_managed_unmanaged_and_malformed_apart() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("config.toml");
+        assert_eq!(inspect(&path).expect("inspect"), NotifyStatus::Missing);
+
+        let cases = [
+            (r#"model = "o3""#, NotifyStatus::Missing),
+            (r#"notify = "notify-send""#, NotifyStatus::Malformed),
+            ("notify = []", NotifyStatus::Malformed),
+            (
+                r#"notify = ["notify-send", "Codex"]"#,
+                NotifyStatus::Unmanaged(vec!["notify-send".to_string(), "Codex".to_string()]),
+            ),
+        ];
+        for (raw, expected) in cases {
+            fs::write(&path, raw).expect("write config");
+            assert_eq!(inspect(&path).expect("inspect"), expected, "{raw}");
+        }
+
+        let managed = managed_notify_command(Path::new("/tmp/agitiser-notify"));
+        let mut doc = DocumentMut::new();
+        set_notify(&mut doc, &managed);
+        fs::write(&path, doc.to_string()).expect("write config");
+        assert_eq!(
+            inspect(&path).expect("inspect"),
+            NotifyStatus::Managed(managed)
+        );
+    }
 }
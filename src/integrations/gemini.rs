@@ -0,0 +1,164 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::path::Path;
+
+use super::hooks::{self, shell_quote, HookTarget};
+use crate::paths;
+
+const BEFORE_AGENT_EVENT: &str = "BeforeAgent";
+const AFTER_AGENT_EVENT: &str = "AfterAgent";
+const NOTIFICATION_EVENT: &str = "Notification";
+const SOURCE_MARKER: &str = "--source gemini-hook";
+
+/// `BeforeAgent` records the turn start, `AfterAgent` ends the turn and `Notification`
+/// fires when Gemini CLI waits on the user (for example a tool permission prompt).
+const MANAGED_HOOKS: [HookTarget<'static>; 3] = [
+    (BEFORE_AGENT_EVENT, "*"),
+    (AFTER_AGENT_EVENT, "*"),
+    (NOTIFICATION_EVENT, "*"),
+];
+
+pub fn managed_command(executable_path: &Path) -> String {
+    let quoted_exe = shell_quote(executable_path.to_string_lossy().as_ref());
+    let config_dir_arg = paths::config_dir_override()
+        .map(|dir| {
+            format!(
+                " --config-dir {}",
+                shell_quote(dir.to_string_lossy().as_ref())
+            )
+        })
+        .unwrap_or_default();
+    format!(
+        "AGITISER_NOTIFY=1 {quoted_exe}{config_dir_arg} ingest --agent gemini --source gemini-hook >/dev/null 2>&1"
+    )
+}
+
+pub fn setup(settings_path: &Path, executable_path: &Path) -> Result<bool> {
+    let mut settings = hooks::load_settings(settings_path)?;
+    let command = managed_command(executable_path);
+    let changed = apply_setup(&mut settings, &command);
+    if changed {
+        hooks::write_settings(settings_path, &settings)?;
+    }
+    Ok(changed)
+}
+
+pub fn remove(settings_path: &Path) -> Result<bool> {
+    if !settings_path.exists() {
+        return Ok(false);
+    }
+
+    let mut settings = hooks::load_settings(settings_path)?;
+    let had_managed_hooks = hooks::has_managed_hook(&settings, is_managed_command);
+    let changed = apply_remove(&mut settings);
+    if changed {
+        hooks::write_settings(settings_path, &settings)?;
+    }
+    Ok(had_managed_hooks && !hooks::has_managed_hook(&settings, is_managed_command))
+}
+
+pub fn is_configured(settings_path: &Path) -> Result<bool> {
+    if !settings_path.exists() {
+        return Ok(false);
+    }
+
+    let settings = hooks::load_settings(settings_path)?;
+    Ok(hooks::has_managed_hooks_for(
+        &settings,
+        &MANAGED_HOOKS,
+        is_managed_command,
+    ))
+}
+
+/// The programs the managed hooks run, once each.
+pub fn programs(settings_path: &Path) -> Result<Vec<String>> {
+    if !settings_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let settings = hooks::load_settings(settings_path)?;
+    let mut programs = Vec::new();
+    for command in hooks::managed_commands(&settings, is_managed_command) {
+        if let Some(program) = hooks::command_program(&command) {
+            if !programs.contains(&program) {
+                programs.push(program);
+            }
+        }
+    }
+    Ok(programs)
+}
+
+pub fn apply_setup(settings: &mut Value, command: &str) -> bool {
+    hooks::apply_setup(settings, command, &MANAGED_HOOKS, is_managed_command)
+}
+
+pub fn apply_remove(settings: &mut Value) -> bool {
+    let events = MANAGED_HOOKS.map(|(event, _)| event);
+    hooks::apply_remove(settings, &events, is_managed_command)
+}
+
+fn is_managed_command(command: &str) -> bool {
+    command.contains("ingest --agent gemini") && command.contains(SOURCE_MARKER)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const COMMAND: &str =
+        "AGITISER_NOTIFY=1 '/tmp/agitiser-notify' ingest --agent gemini --source gemini-hook >/dev/null 2>&1";
+
+    #[test]
+    fn setup_installs_managed_hooks_once() {
+        let mut settings = json!({ "theme": "Default" });
+
+        assert!(apply_setup(&mut settings, COMMAND));
+        assert!(!apply_setup(&mut settings, COMMAND));
+        assert_eq!(settings["theme"], "Default");
+        for event in [BEFORE_AGENT_EVENT, AFTER_AGENT_EVENT, NOTIFICATION_EVENT] {
+            assert_eq!(
+                settings["hooks"][event][0]["hooks"][0]["command"], COMMAND,
+                "missing managed hook for {event}"
+            );
+        }
+        assert!(hooks::has_managed_hooks_for(
+            &settings,
+            &MANAGED_HOOKS,
+            is_managed_command
+        ));
+    }
+
+    #[test]
+    fn remove_keeps_unmanaged_hooks_and_claude_commands() {
+        let mut settings = json!({
+            "hooks": {
+                "AfterAgent": [
+                    { "matcher": "*", "hooks": [{ "type": "command", "command": "notify-send done" }] }
+                ],
+                "Notification": [
+                    { "matcher": "*", "hooks": [{
+                        "type": "command",
+                        "command": "agitiser-notify ingest --agent claude --source claude-hook"
+                    }] }
+                ]
+            }
+        });
+        apply_setup(&mut settings, COMMAND);
+
+        assert!(apply_remove(&mut settings));
+        assert!(!hooks::has_managed_hook(&settings, is_managed_command));
+        assert_eq!(
+            settings["hooks"]["AfterAgent"][0]["hooks"][0]["command"],
+            "notify-send done"
+        );
+        assert_eq!(
+            settings["hooks"]["Notification"][0]["hooks"]
+                .as_array()
+                .map(Vec::len),
+            Some(1)
+        );
+        assert!(settings["hooks"].get(BEFORE_AGENT_EVENT).is_none());
+    }
+}
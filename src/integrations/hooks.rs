@@ -0,0 +1,490 @@
+//! Managed command hooks inside a JSON settings file shaped like
+//! `{"hooks": {"<Event>": [{"matcher": "...", "hooks": [{"type": "command", "command": "..."}]}]}}`.
+//! Claude Code and Gemini CLI share this layout; each integration supplies its own
+//! predicate for recognising the commands it owns.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Recognises a hook command installed by one integration.
+pub(crate) type IsManaged = fn(&str) -> bool;
+
+/// An `(event, matcher)` pair the integration wants a managed hook for.
+pub(crate) type HookTarget<'a> = (&'a str, &'a str);
+
+pub(crate) fn apply_setup(
+    settings: &mut Value,
+    command: &str,
+    targets: &[HookTarget<'_>],
+    is_managed: IsManaged,
+) -> bool {
+    let mut changed = false;
+
+    let root_obj = ensure_root_object(settings);
+    let hooks_obj = ensure_object_entry(root_obj, "hooks");
+
+    let desired = matchers_by_event(targets);
+    for (event, matchers) in &desired {
+        let event_hooks = ensure_array_entry(hooks_obj, event);
+        if ensure_managed_hooks(event_hooks, command, matchers, is_managed) {
+            changed = true;
+        }
+    }
+
+    let stale_events = hooks_obj
+        .iter()
+        .filter(|(event, entries)| {
+            !desired
+                .iter()
+                .any(|(desired_event, _)| desired_event == *event)
+                && entries
+                    .as_array()
+                    .map(|entries| event_has_managed_hook(entries, is_managed))
+                    .unwrap_or(false)
+        })
+        .map(|(event, _)| event.clone())
+        .collect::<Vec<_>>();
+    if remove_managed_hooks_from_events(hooks_obj, &stale_events, is_managed) {
+        changed = true;
+    }
+
+    changed
+}
+
+/// Removes managed hooks from `default_events` and from any other event that still
+/// carries one, dropping events and the `hooks` object once they are empty.
+pub(crate) fn apply_remove(
+    settings: &mut Value,
+    default_events: &[&str],
+    is_managed: IsManaged,
+) -> bool {
+    let mut changed = false;
+
+    let root_obj = match settings.as_object_mut() {
+        Some(root_obj) => root_obj,
+        None => return false,
+    };
+
+    let hooks_obj = match root_obj.get_mut("hooks").and_then(Value::as_object_mut) {
+        Some(hooks_obj) => hooks_obj,
+        None => return false,
+    };
+
+    let mut events = default_events
+        .iter()
+        .map(|event| event.to_string())
+        .collect::<Vec<_>>();
+    for (event, entries) in hooks_obj.iter() {
+        let has_managed = entries
+            .as_array()
+            .map(|entries| event_has_managed_hook(entries, is_managed))
+            .unwrap_or(false);
+        if has_managed && !events.contains(event) {
+            events.push(event.clone());
+        }
+    }
+    if remove_managed_hooks_from_events(hooks_obj, &events, is_managed) {
+        changed = true;
+    }
+
+    if hooks_obj.is_empty() {
+        root_obj.remove("hooks");
+        changed = true;
+    }
+
+    changed
+}
+
+pub(crate) fn has_managed_hook(settings: &Value, is_managed: IsManaged) -> bool {
+    settings
+        .get("hooks")
+        .and_then(Value::as_object)
+        .map(|hooks_obj| {
+            hooks_obj.values().any(|entries| {
+                entries
+                    .as_array()
+                    .map(|entries| event_has_managed_hook(entries, is_managed))
+                    .unwrap_or(false)
+            })
+        })
+        .unwrap_or(false)
+}
+
+pub(crate) fn has_managed_hooks_for(
+    settings: &Value,
+    targets: &[HookTarget<'_>],
+    is_managed: IsManaged,
+) -> bool {
+    !targets.is_empty() && missing_targets(settings, targets, is_managed).is_empty()
+}
+
+/// The targets without a managed hook under their matcher.
+pub(crate) fn missing_targets<'a>(
+    settings: &Value,
+    targets: &[HookTarget<'a>],
+    is_managed: IsManaged,
+) -> Vec<HookTarget<'a>> {
+    let hooks_obj = settings.get("hooks").and_then(Value::as_object);
+    targets
+        .iter()
+        .copied()
+        .filter(|(event, matcher)| {
+            !hooks_obj
+                .and_then(|hooks_obj| hooks_obj.get(*event))
+                .and_then(Value::as_array)
+                .is_some_and(|entries| {
+                    entries.iter().any(|entry| {
+                        entry_matcher(entry) == *matcher
+                            && entry_has_managed_hook(entry, is_managed)
+                    })
+                })
+        })
+        .collect()
+}
+
+/// Every managed command in the settings, once each.
+pub(crate) fn managed_commands(settings: &Value, is_managed: IsManaged) -> Vec<String> {
+    let mut commands = Vec::new();
+    let entries = settings
+        .get("hooks")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|hooks_obj| hooks_obj.values())
+        .filter_map(Value::as_array)
+        .flatten();
+    for entry in entries {
+        let hooks = entry.get("hooks").and_then(Value::as_array);
+        for hook in hooks.into_iter().flatten() {
+            let Some(command) = hook.get("command").and_then(Value::as_str) else {
+                continue;
+            };
+            if is_managed(command) && !commands.iter().any(|known| known == command) {
+                commands.push(command.to_string());
+            }
+        }
+    }
+    commands
+}
+
+/// The program a hook command runs: its first word after any leading `NAME=value`
+/// assignments, with the quoting from [`shell_quote`] undone.
+pub(crate) fn command_program(command: &str) -> Option<String> {
+    shell_words(command).into_iter().find(|word| {
+        !word.split_once('=').is_some_and(|(name, _)| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        })
+    })
+}
+
+/// Splits a command on unquoted whitespace, honouring single quotes, double quotes and
+/// backslash escapes; good enough for the commands setup writes.
+fn shell_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                word.extend(chars.by_ref().take_while(|ch| *ch != '\''));
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '"' => break,
+                        '\\' => word.extend(chars.next()),
+                        ch => word.push(ch),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            ch if ch.is_whitespace() => words.extend(word.take()),
+            ch => word.get_or_insert_with(String::new).push(ch),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+pub(crate) fn load_settings(settings_path: &Path) -> Result<Value> {
+    if !settings_path.exists() {
+        return Ok(json!({}));
+    }
+
+    let raw = fs::read_to_string(settings_path)
+        .with_context(|| format!("failed to read {}", settings_path.display()))?;
+    if raw.trim().is_empty() {
+        return Ok(json!({}));
+    }
+
+    serde_json::from_str(&raw)
+        .with_context(|| format!("failed to parse {}", settings_path.display()))
+}
+
+pub(crate) fn write_settings(settings_path: &Path, settings: &Value) -> Result<()> {
+    if let Some(parent) = settings_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+
+    let raw =
+        serde_json::to_string_pretty(settings).context("failed to serialize settings.json")?;
+    fs::write(settings_path, format!("{raw}\n"))
+        .with_context(|| format!("failed to write {}", settings_path.display()))
+}
+
+pub(crate) fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\"'\"'"))
+}
+
+fn matchers_by_event(targets: &[HookTarget<'_>]) -> Vec<(String, Vec<String>)> {
+    let mut grouped: Vec<(String, Vec<String>)> = Vec::new();
+    for (target_event, matcher) in targets {
+        let matcher = matcher.to_string();
+        match grouped.iter_mut().find(|(event, _)| event == target_event) {
+            Some((_, matchers)) => {
+                if !matchers.contains(&matcher) {
+                    matchers.push(matcher);
+                }
+            }
+            None => grouped.push((target_event.to_string(), vec![matcher])),
+        }
+    }
+    grouped
+}
+
+fn remove_managed_hooks_from_events(
+    hooks_obj: &mut Map<String, Value>,
+    events: &[String],
+    is_managed: IsManaged,
+) -> bool {
+    let mut changed = false;
+
+    for event in events {
+        let mut remove_event = false;
+        if let Some(event_hooks) = hooks_obj.get_mut(event).and_then(Value::as_array_mut) {
+            if remove_managed_hooks(event_hooks, is_managed) {
+                changed = true;
+            }
+            remove_event = event_hooks.is_empty();
+        }
+        if remove_event {
+            hooks_obj.remove(event);
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+fn event_has_managed_hook(entries: &[Value], is_managed: IsManaged) -> bool {
+    entries
+        .iter()
+        .any(|entry| entry_has_managed_hook(entry, is_managed))
+}
+
+fn entry_has_managed_hook(entry: &Value, is_managed: IsManaged) -> bool {
+    entry
+        .get("hooks")
+        .and_then(Value::as_array)
+        .map(|hooks| {
+            hooks.iter().any(|hook| {
+                hook.get("command")
+                    .and_then(Value::as_str)
+                    .map(is_managed)
+                    .unwrap_or(false)
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn entry_matcher(entry: &Value) -> &str {
+    entry
+        .get("matcher")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+fn ensure_managed_hooks(
+    event_hooks: &mut Vec<Value>,
+    command: &str,
+    matchers: &[String],
+    is_managed: IsManaged,
+) -> bool {
+    let mut changed = false;
+    let mut present: Vec<String> = Vec::new();
+
+    for entry in event_hooks.iter_mut() {
+        let entry_matcher = entry_matcher(entry).to_string();
+        let entry_matcher_wanted = matchers.contains(&entry_matcher);
+        if let Some(hooks_array) = entry
+            .as_object_mut()
+            .and_then(|obj| obj.get_mut("hooks"))
+            .and_then(Value::as_array_mut)
+        {
+            let original_len = hooks_array.len();
+            hooks_array.retain(|hook| {
+                let hook_command = hook
+                    .get("command")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+
+                if !is_managed(&hook_command) {
+                    return true;
+                }
+
+                if hook_command == command
+                    && entry_matcher_wanted
+                    && !present.contains(&entry_matcher)
+                {
+                    present.push(entry_matcher.clone());
+                    true
+                } else {
+                    false
+                }
+            });
+
+            if hooks_array.len() != original_len {
+                changed = true;
+            }
+        }
+    }
+
+    if drop_empty_entries(event_hooks) {
+        changed = true;
+    }
+
+    for matcher in matchers {
+        if present.contains(matcher) {
+            continue;
+        }
+        event_hooks.push(json!({
+            "matcher": matcher,
+            "hooks": [
+                {
+                    "type": "command",
+                    "command": command,
+                }
+            ]
+        }));
+        changed = true;
+    }
+
+    changed
+}
+
+fn remove_managed_hooks(event_hooks: &mut Vec<Value>, is_managed: IsManaged) -> bool {
+    let mut changed = false;
+
+    for entry in event_hooks.iter_mut() {
+        if let Some(hooks_array) = entry
+            .as_object_mut()
+            .and_then(|obj| obj.get_mut("hooks"))
+            .and_then(Value::as_array_mut)
+        {
+            let original_len = hooks_array.len();
+            hooks_array.retain(|hook| {
+                let hook_command = hook
+                    .get("command")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                !is_managed(hook_command)
+            });
+            if hooks_array.len() != original_len {
+                changed = true;
+            }
+        }
+    }
+
+    if drop_empty_entries(event_hooks) {
+        changed = true;
+    }
+
+    changed
+}
+
+fn drop_empty_entries(event_hooks: &mut Vec<Value>) -> bool {
+    let original_len = event_hooks.len();
+    event_hooks.retain(|entry| {
+        entry
+            .get("hooks")
+            .and_then(Value::as_array)
+            .map(|hooks| !hooks.is_empty())
+            .unwrap_or(true)
+    });
+    event_hooks.len() != original_len
+}
+
+fn ensure_root_object(value: &mut Value) -> &mut Map<String, Value> {
+    if !value.is_object() {
+        *value = json!({});
+    }
+    value.as_object_mut().expect("root should be an object")
+}
+
+fn ensure_object_entry<'a>(
+    obj: &'a mut Map<String, Value>,
+    key: &str,
+) -> &'a mut Map<String, Value> {
+    let value = obj.entry(key.to_string()).or_insert_with(|| json!({}));
+    if !value.is_object() {
+        *value = json!({});
+    }
+    value.as_object_mut().expect("entry should be an object")
+}
+
+fn ensure_array_entry<'a>(obj: &'a mut Map<String, Value>, key: &str) -> &'a mut Vec<Value> {
+    let value = obj.entry(key.to_string()).or_insert_with(|| json!([]));
+    if !value.is_array() {
+        *value = json!([]);
+    }
+    value.as_array_mut().expect("entry should be an array")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_program_skips_assignments_and_unquotes() {
+        let exe = "/opt/it's here/agitiser-notify";
+        let command = format!(
+            "AGITISER_NOTIFY=1 {} ingest --agent claude",
+            shell_quote(exe)
+        );
+        assert_eq!(command_program(&command).as_deref(), Some(exe));
+        assert_eq!(
+            command_program("agitiser-notify ingest --agent gemini").as_deref(),
+            Some("agitiser-notify")
+        );
+        assert_eq!(
+            command_program(r#"A=1 B=2 "/usr/local/bin/agitiser notify" ingest"#).as_deref(),
+            Some("/usr/local/bin/agitiser notify")
+        );
+        assert_eq!(command_program("   "), None);
+    }
+
+    #[test]
+    fn missing_targets_checks_the_matcher() {
+        let is_managed: IsManaged = |command| command.contains("managed");
+        let settings = json!({
+            "hooks": {
+                "Stop": [{"matcher": "*", "hooks": [{"type": "command", "command": "managed"}]}],
+                "PermissionRequest": [{"matcher": "Bash", "hooks": [{"type": "command", "command": "managed"}]}]
+            }
+        });
+        let targets = [("Stop", "*"), ("PermissionRequest", "ExitPlanMode")];
+        assert_eq!(
+            missing_targets(&settings, &targets, is_managed),
+            vec![("PermissionRequest", "ExitPlanMode")]
+        );
+        assert_eq!(managed_commands(&settings, is_managed), vec!["managed"]);
+    }
+}
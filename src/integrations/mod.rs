@@ -1,2 +1,18 @@
+pub mod aider;
 pub mod claude;
 pub mod codex;
+pub mod gemini;
+mod hooks;
+pub mod opencode;
+
+use std::path::Path;
+
+/// Whether a program baked into a managed command can still run: an existing file when it
+/// is a path, or a name found on `PATH`.
+pub fn program_exists(program: &str) -> bool {
+    if program.contains('/') {
+        Path::new(program).is_file()
+    } else {
+        which::which(program).is_ok()
+    }
+}
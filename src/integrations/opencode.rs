@@ -0,0 +1,162 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+pub const PLUGIN_FILE_NAME: &str = "agitiser-notify.js";
+const SOURCE_VALUE: &str = "opencode-plugin";
+const MANAGED_MARKER: &str = "// Managed by agitiser-notify";
+const FORWARDED_EVENTS: &[&str] = &["session.idle"];
+
+pub fn managed_command(executable_path: &Path) -> Vec<String> {
+    let mut command = vec![executable_path.to_string_lossy().to_string()];
+    if let Some(dir) = paths::config_dir_override() {
+        command.push("--config-dir".to_string());
+        command.push(dir.to_string_lossy().to_string());
+    }
+    command.extend([
+        "ingest".to_string(),
+        "--agent".to_string(),
+        "opencode".to_string(),
+        "--source".to_string(),
+        SOURCE_VALUE.to_string(),
+    ]);
+    command
+}
+
+/// Renders the plugin module. OpenCode loads every file in its plugin directory and
+/// calls the exported factory once per project; the returned `event` hook forwards
+/// idle sessions to `ingest` as a small JSON payload.
+pub fn plugin_source(executable_path: &Path) -> String {
+    let command = serde_json::to_string(&managed_command(executable_path))
+        .expect("string arrays always serialize");
+    let events = serde_json::to_string(FORWARDED_EVENTS).expect("string arrays always serialize");
+    format!(
+        r#"{MANAGED_MARKER}; rewritten by `agitiser-notify setup --agents opencode`.
+const COMMAND = {command};
+const EVENTS = new Set({events});
+
+export const AgitiserNotify = async ({{ directory }}) => ({{
+  event: async ({{ event }}) => {{
+    if (!EVENTS.has(event.type)) return;
+    const payload = JSON.stringify({{
+      type: event.type,
+      session_id: event.properties?.sessionID,
+      cwd: directory,
+    }});
+    Bun.spawn([...COMMAND, "--payload", payload], {{
+      env: {{ ...process.env, AGITISER_NOTIFY: "1" }},
+      stdout: "ignore",
+      stderr: "ignore",
+    }});
+  }},
+}});
+"#
+    )
+}
+
+pub fn plugin_path(plugin_dir: &Path) -> PathBuf {
+    plugin_dir.join(PLUGIN_FILE_NAME)
+}
+
+pub fn setup(plugin_dir: &Path, executable_path: &Path) -> Result<bool> {
+    let path = plugin_path(plugin_dir);
+    let desired = plugin_source(executable_path);
+    match read_plugin(&path)? {
+        Some(existing) if existing == desired => return Ok(false),
+        Some(existing) if !is_managed_plugin(&existing) => {
+            bail!(
+                "{} exists and is not managed by agitiser-notify; refusing to overwrite it",
+                path.display()
+            );
+        }
+        _ => {}
+    }
+
+    fs::create_dir_all(plugin_dir)
+        .with_context(|| format!("failed to create {}", plugin_dir.display()))?;
+    fs::write(&path, desired).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+pub fn remove(plugin_dir: &Path) -> Result<bool> {
+    let path = plugin_path(plugin_dir);
+    match read_plugin(&path)? {
+        Some(existing) if is_managed_plugin(&existing) => {
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+pub fn is_configured(plugin_dir: &Path) -> Result<bool> {
+    Ok(read_plugin(&plugin_path(plugin_dir))?
+        .map(|existing| is_managed_plugin(&existing))
+        .unwrap_or(false))
+}
+
+/// The program the managed plugin spawns, read back from its `COMMAND` constant.
+pub fn program(plugin_dir: &Path) -> Result<Option<String>> {
+    let Some(source) = read_plugin(&plugin_path(plugin_dir))? else {
+        return Ok(None);
+    };
+    if !is_managed_plugin(&source) {
+        return Ok(None);
+    }
+    Ok(source
+        .lines()
+        .find_map(|line| line.strip_prefix("const COMMAND = "))
+        .and_then(|command| serde_json::from_str::<Vec<String>>(command.trim_end_matches(';')).ok())
+        .and_then(|command| command.into_iter().next()))
+}
+
+fn is_managed_plugin(source: &str) -> bool {
+    source.starts_with(MANAGED_MARKER)
+}
+
+fn read_plugin(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    fs::read_to_string(path)
+        .map(Some)
+        .with_context(|| format!("failed to read {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_source_embeds_quoted_command() {
+        let source = plugin_source(Path::new("/opt/agitiser \"notify\"/bin"));
+
+        assert!(is_managed_plugin(&source));
+        assert!(source.contains(r#"const COMMAND = ["/opt/agitiser \"notify\"/bin","#));
+        assert!(source.contains(r#""ingest","--agent","opencode","--source","opencode-plugin"]"#));
+        assert!(source.contains(r#"new Set(["session.idle"])"#));
+    }
+
+    #[test]
+    fn program_reads_the_command_back() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        assert_eq!(program(dir.path()).expect("program"), None);
+
+        setup(dir.path(), Path::new("/opt/agitiser \"notify\"/bin")).expect("setup");
+        assert_eq!(
+            program(dir.path()).expect("program").as_deref(),
+            Some("/opt/agitiser \"notify\"/bin")
+        );
+    }
+
+    #[test]
+    fn unmanaged_plugins_are_not_recognized() {
+        assert!(!is_managed_plugin(
+            "export const Mine = async () => ({});\n"
+        ));
+    }
+}
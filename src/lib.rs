@@ -1,7 +1,23 @@
 pub mod agent;
+pub mod dedup;
+pub mod digest;
+pub mod duration;
 pub mod event;
+pub mod history;
 pub mod integrations;
+pub mod lockfile;
+pub mod output;
 pub mod paths;
+pub mod quiet;
+pub mod recent;
+pub mod relink;
+pub mod rules;
+pub mod run;
 pub mod speech;
 pub mod state;
+pub mod stats;
+pub mod summary;
 pub mod template;
+pub mod throttle;
+pub mod transcript;
+pub mod turns;
@@ -0,0 +1,91 @@
+//! Read-modify-write of small JSON state files shared by concurrent `ingest` processes.
+
+use anyhow::{Context, Result};
+use fs4::fs_std::FileExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Runs `apply` holding an exclusive lock on `lock_path`, for files that are replaced or
+/// renamed and so cannot carry the lock themselves.
+pub fn exclusive<R>(lock_path: &Path, apply: impl FnOnce() -> Result<R>) -> Result<R> {
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path)
+        .with_context(|| format!("failed to open {}", lock_path.display()))?;
+    file.lock_exclusive()
+        .with_context(|| format!("failed to lock {}", lock_path.display()))?;
+    apply()
+}
+
+/// Loads the JSON state at `path` under a shared lock; default when missing or empty.
+pub fn read<T>(path: &Path) -> Result<T>
+where
+    T: Default + DeserializeOwned,
+{
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(T::default()),
+        Err(error) => {
+            return Err(error).with_context(|| format!("failed to open {}", path.display()))
+        }
+    };
+    FileExt::lock_shared(&file).with_context(|| format!("failed to lock {}", path.display()))?;
+    let mut raw = String::new();
+    file.read_to_string(&mut raw)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse(path, &raw)
+}
+
+/// Loads the JSON state at `path` (default when missing or empty), passes it to `apply`
+/// and writes it back, holding an exclusive lock on the file the whole time.
+pub fn update<T, R>(path: &Path, apply: impl FnOnce(&mut T) -> R) -> Result<R>
+where
+    T: Default + Serialize + DeserializeOwned,
+{
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    file.lock_exclusive()
+        .with_context(|| format!("failed to lock {}", path.display()))?;
+
+    let mut raw = String::new();
+    file.read_to_string(&mut raw)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut state = parse(path, &raw)?;
+
+    let result = apply(&mut state);
+    let raw = serde_json::to_string(&state)
+        .with_context(|| format!("failed to serialize {}", path.display()))?;
+    file.set_len(0)
+        .and_then(|_| file.seek(SeekFrom::Start(0)))
+        .and_then(|_| file.write_all(format!("{raw}\n").as_bytes()))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(result)
+}
+
+fn parse<T>(path: &Path, raw: &str) -> Result<T>
+where
+    T: Default + DeserializeOwned,
+{
+    if raw.trim().is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_str(raw).with_context(|| format!("failed to parse {}", path.display()))
+}
@@ -1,32 +1,52 @@
 mod cli;
+mod pipeline;
 
 use agitiser_notify::agent::{Agent, SetupAgent};
-use agitiser_notify::event::normalize;
-use agitiser_notify::integrations::{claude, codex};
-use agitiser_notify::{paths, speech, state};
+use agitiser_notify::event::{normalize_custom, normalize_with_state, project_name_from_cwd};
+use agitiser_notify::history::{Delivery, HistoryEntry};
+use agitiser_notify::integrations::{self, aider, claude, codex, gemini, opencode};
+use agitiser_notify::output::{Output, Status};
+use agitiser_notify::paths::SettingsScope;
+use agitiser_notify::rules::RuleAction;
+use agitiser_notify::{digest, history, paths, recent, relink, rules, speech, state, stats, turns};
 use anyhow::{bail, Context, Result};
-use clap::{CommandFactory, Parser};
+use clap::{CommandFactory, Parser, ValueEnum};
 use clap_complete::{generate, Shell};
 use serde_json::Value;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::{self, IsTerminal, Read};
 use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::cli::{
-    Cli, Commands, ConfigCommand, EventKindCommand, ShellArg, SubagentCommand, TemplateCommand,
+    AddRuleArgs, ClaudeHooksCommand, Cli, CodexChainCommand, Commands, ConfigCommand, DedupCommand,
+    EventKindCommand, GenericCommand, GenericSetArgs, MinDurationCommand, QuietCommand,
+    RateLimitCommand, RulesCommand, ShellArg, SpeechCommand, SubagentCommand, SummaryCommand,
+    TemplateCommand,
 };
 
 fn main() {
-    if let Err(error) = run() {
+    let cli = Cli::parse();
+    let mut output = Output::new(cli.output);
+    let result = run(&mut output, cli);
+    output.finish(result.as_ref().err());
+    if let Err(error) = result {
         eprintln!("error: {error:#}");
         std::process::exit(1);
     }
 }
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
+fn run(output: &mut Output, cli: Cli) -> Result<()> {
+    if let Some(config_dir) = cli.config_dir {
+        let config_dir = std::path::absolute(&config_dir)
+            .with_context(|| format!("failed to resolve {}", config_dir.display()))?;
+        paths::set_config_dir_override(config_dir);
+    }
     match cli.command {
         Commands::Completions { shell } => {
+            if output.is_json() {
+                bail!("completions are a shell script; --output json is not supported");
+            }
             let resolved_shell = shell
                 .or_else(detect_shell_from_env)
                 .context(
@@ -34,17 +54,50 @@ fn run() -> Result<()> {
                 )?;
             print_completions(resolved_shell)
         }
-        Commands::Setup { agents } => setup_agents(agents),
-        Commands::Remove { agents } => remove_agents(agents),
+        Commands::Setup { agents, scope, dir } => {
+            setup_agents(output, agents, scope, dir.as_deref())
+        }
+        Commands::Remove { agents, scope, dir } => {
+            remove_agents(output, agents, scope, dir.as_deref())
+        }
+        Commands::Relink {
+            dir,
+            use_path,
+            absolute,
+        } => relink_integrations(output, dir.as_deref(), use_path, absolute),
         Commands::Ingest {
             agent,
+            name,
             payload,
             trailing_payload,
             source,
             verbose,
-        } => ingest_event(agent, payload, trailing_payload, source, verbose),
-        Commands::Doctor => doctor(),
-        Commands::Config { command } => handle_config(command),
+        } => ingest_event(agent, name, payload, trailing_payload, source, verbose),
+        Commands::Run {
+            name,
+            verbose,
+            command,
+        } => {
+            let exit_code = run_command(output, &command, name.as_deref(), verbose)?;
+            if exit_code != 0 {
+                output.finish(None);
+                std::process::exit(exit_code);
+            }
+            Ok(())
+        }
+        Commands::Mute { duration } => mute(output, duration.as_deref()),
+        Commands::Unmute => unmute(output),
+        Commands::Digest { dry_run } => speak_digest(output, dry_run),
+        Commands::Last { n, speak } => replay_last(output, n, speak),
+        Commands::History {
+            agent,
+            project,
+            since,
+            limit,
+        } => show_history(output, agent, project, since.as_deref(), limit),
+        Commands::Stats { since, by } => show_stats(output, since.as_deref(), by),
+        Commands::Doctor { dir, fix, speak } => doctor(output, dir.as_deref(), fix, speak),
+        Commands::Config { command } => handle_config(output, command),
     }
 }
 
@@ -86,11 +139,21 @@ fn print_completions(shell: ShellArg) -> Result<()> {
     Ok(())
 }
 
-fn handle_config(command: ConfigCommand) -> Result<()> {
+fn handle_config(output: &mut Output, command: ConfigCommand) -> Result<()> {
     match command {
-        ConfigCommand::Template { command } => handle_template_config(command),
-        ConfigCommand::EventKind { command } => handle_event_kind_config(command),
-        ConfigCommand::Subagent { command } => handle_subagent_config(command),
+        ConfigCommand::Template { command } => handle_template_config(output, command),
+        ConfigCommand::EventKind { command } => handle_event_kind_config(output, command),
+        ConfigCommand::Subagent { command } => handle_subagent_config(output, command),
+        ConfigCommand::ClaudeHooks { command } => handle_claude_hooks_config(output, command),
+        ConfigCommand::CodexChain { command } => handle_codex_chain_config(output, command),
+        ConfigCommand::Summary { command } => handle_summary_config(output, command),
+        ConfigCommand::MinDuration { command } => handle_min_duration_config(output, command),
+        ConfigCommand::Generic { command } => handle_generic_config(output, command),
+        ConfigCommand::Speech { command } => handle_speech_config(output, command),
+        ConfigCommand::Quiet { command } => handle_quiet_config(output, command),
+        ConfigCommand::RateLimit { command } => handle_rate_limit_config(output, command),
+        ConfigCommand::Dedup { command } => handle_dedup_config(output, command),
+        ConfigCommand::Rules { command } => handle_rules_config(output, command),
     }
 }
 
@@ -98,16 +161,40 @@ fn template_scope_label(agent: Option<Agent>) -> &'static str {
     match agent {
         Some(Agent::Claude) => "claude",
         Some(Agent::Codex) => "codex",
+        Some(Agent::Gemini) => "gemini",
+        Some(Agent::Opencode) => "opencode",
+        Some(Agent::Aider) => "aider",
         Some(Agent::Generic) => "generic",
+        Some(Agent::Custom) => "custom",
         None => "global",
     }
 }
 
-fn template_slot(templates: &state::TemplateConfig, agent: Option<Agent>) -> &Option<String> {
+/// `--digest` selects the digest template instead of a global or per-agent one.
+fn template_target_label(agent: Option<Agent>, digest: bool) -> &'static str {
+    if digest {
+        "digest"
+    } else {
+        template_scope_label(agent)
+    }
+}
+
+fn template_slot(
+    templates: &state::TemplateConfig,
+    agent: Option<Agent>,
+    digest: bool,
+) -> &Option<String> {
+    if digest {
+        return &templates.digest;
+    }
     match agent {
         Some(Agent::Claude) => &templates.agents.claude,
         Some(Agent::Codex) => &templates.agents.codex,
+        Some(Agent::Gemini) => &templates.agents.gemini,
+        Some(Agent::Opencode) => &templates.agents.opencode,
+        Some(Agent::Aider) => &templates.agents.aider,
         Some(Agent::Generic) => &templates.agents.generic,
+        Some(Agent::Custom) => &templates.agents.custom,
         None => &templates.global,
     }
 }
@@ -115,11 +202,19 @@ fn template_slot(templates: &state::TemplateConfig, agent: Option<Agent>) -> &Op
 fn template_slot_mut(
     templates: &mut state::TemplateConfig,
     agent: Option<Agent>,
+    digest: bool,
 ) -> &mut Option<String> {
+    if digest {
+        return &mut templates.digest;
+    }
     match agent {
         Some(Agent::Claude) => &mut templates.agents.claude,
         Some(Agent::Codex) => &mut templates.agents.codex,
+        Some(Agent::Gemini) => &mut templates.agents.gemini,
+        Some(Agent::Opencode) => &mut templates.agents.opencode,
+        Some(Agent::Aider) => &mut templates.agents.aider,
         Some(Agent::Generic) => &mut templates.agents.generic,
+        Some(Agent::Custom) => &mut templates.agents.custom,
         None => &mut templates.global,
     }
 }
@@ -131,7 +226,11 @@ fn event_kind_labels_slot(
     match agent {
         Some(Agent::Claude) => &labels.agents.claude,
         Some(Agent::Codex) => &labels.agents.codex,
+        Some(Agent::Gemini) => &labels.agents.gemini,
+        Some(Agent::Opencode) => &labels.agents.opencode,
+        Some(Agent::Aider) => &labels.agents.aider,
         Some(Agent::Generic) => &labels.agents.generic,
+        Some(Agent::Custom) => &labels.agents.custom,
         None => &labels.global,
     }
 }
@@ -143,7 +242,11 @@ fn event_kind_labels_slot_mut(
     match agent {
         Some(Agent::Claude) => &mut labels.agents.claude,
         Some(Agent::Codex) => &mut labels.agents.codex,
+        Some(Agent::Gemini) => &mut labels.agents.gemini,
+        Some(Agent::Opencode) => &mut labels.agents.opencode,
+        Some(Agent::Aider) => &mut labels.agents.aider,
         Some(Agent::Generic) => &mut labels.agents.generic,
+        Some(Agent::Custom) => &mut labels.agents.custom,
         None => &mut labels.global,
     }
 }
@@ -156,87 +259,296 @@ fn normalize_event_kind_key(key: &str) -> Result<String> {
     Ok(normalized)
 }
 
-fn handle_template_config(command: TemplateCommand) -> Result<()> {
+fn handle_template_config(output: &mut Output, command: TemplateCommand) -> Result<()> {
+    match command {
+        TemplateCommand::Get { agent, digest } => template_get(output, agent, digest),
+        TemplateCommand::Set {
+            agent,
+            digest,
+            value,
+        } => template_set(output, agent, digest, value),
+        TemplateCommand::Reset { agent, digest } => template_reset(output, agent, digest),
+    }
+}
+
+fn handle_event_kind_config(output: &mut Output, command: EventKindCommand) -> Result<()> {
+    match command {
+        EventKindCommand::Get { agent, key } => event_kind_get(output, agent, &key),
+        EventKindCommand::Set { agent, key, value } => event_kind_set(output, agent, &key, &value),
+        EventKindCommand::Reset { agent, key } => event_kind_reset(output, agent, &key),
+    }
+}
+
+fn handle_subagent_config(output: &mut Output, command: SubagentCommand) -> Result<()> {
+    match command {
+        SubagentCommand::Get => subagent_get(output),
+        SubagentCommand::Set { enabled } => subagent_set(output, enabled),
+    }
+}
+
+fn min_duration_slot(config: &state::MinDurationConfig, agent: Option<Agent>) -> Option<u64> {
+    match agent {
+        Some(Agent::Claude) => config.agents.claude,
+        Some(Agent::Codex) => config.agents.codex,
+        Some(Agent::Gemini) => config.agents.gemini,
+        Some(Agent::Opencode) => config.agents.opencode,
+        Some(Agent::Aider) => config.agents.aider,
+        Some(Agent::Generic) => config.agents.generic,
+        Some(Agent::Custom) => config.agents.custom,
+        None => config.global,
+    }
+}
+
+fn min_duration_slot_mut(
+    config: &mut state::MinDurationConfig,
+    agent: Option<Agent>,
+) -> &mut Option<u64> {
+    match agent {
+        Some(Agent::Claude) => &mut config.agents.claude,
+        Some(Agent::Codex) => &mut config.agents.codex,
+        Some(Agent::Gemini) => &mut config.agents.gemini,
+        Some(Agent::Opencode) => &mut config.agents.opencode,
+        Some(Agent::Aider) => &mut config.agents.aider,
+        Some(Agent::Generic) => &mut config.agents.generic,
+        Some(Agent::Custom) => &mut config.agents.custom,
+        None => &mut config.global,
+    }
+}
+
+fn handle_min_duration_config(output: &mut Output, command: MinDurationCommand) -> Result<()> {
+    match command {
+        MinDurationCommand::Get { agent } => min_duration_get(output, agent),
+        MinDurationCommand::Set { agent, value } => min_duration_set(output, agent, &value),
+        MinDurationCommand::Reset { agent } => min_duration_reset(output, agent),
+    }
+}
+
+fn handle_generic_config(output: &mut Output, command: GenericCommand) -> Result<()> {
+    match command {
+        GenericCommand::Get => generic_get(output),
+        GenericCommand::Set(args) => generic_set(output, args),
+        GenericCommand::Reset => generic_reset(output),
+    }
+}
+
+fn handle_speech_config(output: &mut Output, command: SpeechCommand) -> Result<()> {
+    match command {
+        SpeechCommand::Get { kind } => speech_get(output, &kind),
+        SpeechCommand::Set {
+            kind,
+            priority,
+            voice,
+            earcon,
+        } => speech_set(output, &kind, priority, voice, earcon),
+        SpeechCommand::Reset { kind } => speech_reset(output, &kind),
+    }
+}
+
+fn handle_quiet_config(output: &mut Output, command: QuietCommand) -> Result<()> {
+    match command {
+        QuietCommand::Get => quiet_get(output),
+        QuietCommand::Set { hours, when_locked } => quiet_set(output, hours, when_locked),
+        QuietCommand::Reset => quiet_reset(output),
+    }
+}
+
+fn handle_rate_limit_config(output: &mut Output, command: RateLimitCommand) -> Result<()> {
+    match command {
+        RateLimitCommand::Get => rate_limit_get(output),
+        RateLimitCommand::Set {
+            burst,
+            refill,
+            cooldown,
+        } => rate_limit_set(output, burst, refill.as_deref(), cooldown.as_deref()),
+        RateLimitCommand::Reset => rate_limit_reset(output),
+    }
+}
+
+fn handle_dedup_config(output: &mut Output, command: DedupCommand) -> Result<()> {
+    match command {
+        DedupCommand::Get => dedup_get(output),
+        DedupCommand::Set { window } => dedup_set(output, &window),
+        DedupCommand::Reset => dedup_reset(output),
+    }
+}
+
+fn handle_rules_config(output: &mut Output, command: RulesCommand) -> Result<()> {
     match command {
-        TemplateCommand::Get { agent } => template_get(agent),
-        TemplateCommand::Set { agent, value } => template_set(agent, value),
-        TemplateCommand::Reset { agent } => template_reset(agent),
+        RulesCommand::List => rules_list(output),
+        RulesCommand::Add(args) => {
+            let AddRuleArgs {
+                name,
+                agent,
+                kind,
+                project,
+                cwd,
+                fields,
+                hours,
+                min_duration,
+                max_duration,
+                min_session,
+                max_session,
+                action,
+                route_to,
+                template,
+                priority,
+                reason,
+                position,
+            } = *args;
+            if let Some(hours) = hours.as_deref() {
+                agitiser_notify::quiet::parse_quiet_hours(hours)?;
+            }
+            let rule = state::RuleConfig {
+                name: name.trim().to_string(),
+                agent,
+                event_kind: kind
+                    .map(|kind| normalize_event_kind_key(&kind))
+                    .transpose()?,
+                project,
+                cwd,
+                fields: parse_rule_fields(&fields)?,
+                hours,
+                min_duration_secs: parse_duration_secs(min_duration.as_deref())?,
+                max_duration_secs: parse_duration_secs(max_duration.as_deref())?,
+                min_session_secs: parse_duration_secs(min_session.as_deref())?,
+                max_session_secs: parse_duration_secs(max_session.as_deref())?,
+                action,
+                route_to: (!route_to.is_empty()).then_some(route_to),
+                template,
+                priority,
+                reason,
+            };
+            rules_add(output, rule, position)
+        }
+        RulesCommand::Remove { name } => rules_remove(output, &name),
+        RulesCommand::Test {
+            agent,
+            name,
+            payload,
+        } => rules_test(output, agent, name.as_deref(), &payload),
+    }
+}
+
+fn handle_summary_config(output: &mut Output, command: SummaryCommand) -> Result<()> {
+    match command {
+        SummaryCommand::Get => summary_get(output),
+        SummaryCommand::Set { max_words } => summary_set(output, max_words),
     }
 }
 
-fn handle_event_kind_config(command: EventKindCommand) -> Result<()> {
+fn handle_codex_chain_config(output: &mut Output, command: CodexChainCommand) -> Result<()> {
     match command {
-        EventKindCommand::Get { agent, key } => event_kind_get(agent, &key),
-        EventKindCommand::Set { agent, key, value } => event_kind_set(agent, &key, &value),
-        EventKindCommand::Reset { agent, key } => event_kind_reset(agent, &key),
+        CodexChainCommand::Get => codex_chain_get(output),
+        CodexChainCommand::Set { enabled } => codex_chain_set(output, enabled),
     }
 }
 
-fn handle_subagent_config(command: SubagentCommand) -> Result<()> {
+fn handle_claude_hooks_config(output: &mut Output, command: ClaudeHooksCommand) -> Result<()> {
     match command {
-        SubagentCommand::Get => subagent_get(),
-        SubagentCommand::Set { enabled } => subagent_set(enabled),
+        ClaudeHooksCommand::List => claude_hooks_list(output),
+        ClaudeHooksCommand::Add {
+            event,
+            matcher,
+            event_kind,
+        } => claude_hooks_add(output, &event, &matcher, event_kind.as_deref()),
+        ClaudeHooksCommand::Remove { event, matcher } => {
+            claude_hooks_remove(output, &event, matcher.as_deref())
+        }
+        ClaudeHooksCommand::Reset => claude_hooks_reset(output),
     }
 }
 
-fn template_get(agent: Option<Agent>) -> Result<()> {
+fn template_get(output: &mut Output, agent: Option<Agent>, digest: bool) -> Result<()> {
     let state_path = paths::local_state_path()?;
     let local_state = state::load(&state_path)?;
-    if let Some(value) = template_slot(&local_state.templates, agent) {
-        println!("{value}");
-    } else {
-        println!("<unset>");
-    }
+    let value = template_slot(&local_state.templates, agent, digest);
+    output.value(
+        &format!("config.template.{}", template_target_label(agent, digest)),
+        value.as_deref().unwrap_or("<unset>"),
+        value,
+    );
     Ok(())
 }
 
-fn template_set(agent: Option<Agent>, value: String) -> Result<()> {
+fn template_set(
+    output: &mut Output,
+    agent: Option<Agent>,
+    digest: bool,
+    value: String,
+) -> Result<()> {
     agitiser_notify::template::validate_template(&value)?;
 
     let state_path = paths::local_state_path()?;
     let mut local_state = state::load(&state_path)?;
-    let slot = template_slot_mut(&mut local_state.templates, agent);
+    let slot = template_slot_mut(&mut local_state.templates, agent, digest);
     if slot.as_deref() == Some(value.as_str()) {
-        println!("template for {} unchanged", template_scope_label(agent));
+        output.line(
+            &format!("config.template.{}", template_target_label(agent, digest)),
+            Status::Ok,
+            format!(
+                "template for {} unchanged",
+                template_target_label(agent, digest)
+            ),
+        );
         return Ok(());
     }
 
     *slot = Some(value);
     state::save(&state_path, &local_state)?;
-    println!("template for {} updated", template_scope_label(agent));
+    output.line(
+        &format!("config.template.{}", template_target_label(agent, digest)),
+        Status::Ok,
+        format!(
+            "template for {} updated",
+            template_target_label(agent, digest)
+        ),
+    );
     Ok(())
 }
 
-fn template_reset(agent: Option<Agent>) -> Result<()> {
+fn template_reset(output: &mut Output, agent: Option<Agent>, digest: bool) -> Result<()> {
     let state_path = paths::local_state_path()?;
     let mut local_state = state::load(&state_path)?;
-    let slot = template_slot_mut(&mut local_state.templates, agent);
+    let slot = template_slot_mut(&mut local_state.templates, agent, digest);
     if slot.take().is_none() {
-        println!("template for {} already unset", template_scope_label(agent));
+        output.line(
+            &format!("config.template.{}", template_target_label(agent, digest)),
+            Status::Ok,
+            format!(
+                "template for {} already unset",
+                template_target_label(agent, digest)
+            ),
+        );
         return Ok(());
     }
 
     state::save(&state_path, &local_state)?;
-    println!("template for {} reset", template_scope_label(agent));
+    output.line(
+        &format!("config.template.{}", template_target_label(agent, digest)),
+        Status::Ok,
+        format!(
+            "template for {} reset",
+            template_target_label(agent, digest)
+        ),
+    );
     Ok(())
 }
 
-fn event_kind_get(agent: Option<Agent>, key: &str) -> Result<()> {
+fn event_kind_get(output: &mut Output, agent: Option<Agent>, key: &str) -> Result<()> {
     let state_path = paths::local_state_path()?;
     let local_state = state::load(&state_path)?;
     let normalized_key = normalize_event_kind_key(key)?;
 
-    if let Some(value) =
-        event_kind_labels_slot(&local_state.event_kind_labels, agent).get(&normalized_key)
-    {
-        println!("{value}");
-    } else {
-        println!("<unset>");
-    }
+    let value = event_kind_labels_slot(&local_state.event_kind_labels, agent).get(&normalized_key);
+    output.value(
+        &format!("config.event-kind.{}", template_scope_label(agent)),
+        value.map(String::as_str).unwrap_or("<unset>"),
+        value,
+    );
     Ok(())
 }
 
-fn event_kind_set(agent: Option<Agent>, key: &str, value: &str) -> Result<()> {
+fn event_kind_set(output: &mut Output, agent: Option<Agent>, key: &str, value: &str) -> Result<()> {
     let normalized_key = normalize_event_kind_key(key)?;
     let trimmed_value = value.trim();
     if trimmed_value.is_empty() {
@@ -255,290 +567,2481 @@ fn event_kind_set(agent: Option<Agent>, key: &str, value: &str) -> Result<()> {
     };
 
     if !changed {
-        println!(
-            "event-kind label for {} unchanged",
-            template_scope_label(agent)
+        output.line(
+            &format!("config.event-kind.{}", template_scope_label(agent)),
+            Status::Ok,
+            format!(
+                "event-kind label for {} unchanged",
+                template_scope_label(agent)
+            ),
         );
         return Ok(());
     }
 
     state::save(&state_path, &local_state)?;
-    println!(
-        "event-kind label for {} updated",
-        template_scope_label(agent)
+    output.line(
+        &format!("config.event-kind.{}", template_scope_label(agent)),
+        Status::Ok,
+        format!(
+            "event-kind label for {} updated",
+            template_scope_label(agent)
+        ),
     );
     Ok(())
 }
 
-fn event_kind_reset(agent: Option<Agent>, key: &str) -> Result<()> {
+fn event_kind_reset(output: &mut Output, agent: Option<Agent>, key: &str) -> Result<()> {
     let normalized_key = normalize_event_kind_key(key)?;
     let state_path = paths::local_state_path()?;
     let mut local_state = state::load(&state_path)?;
     let slot = event_kind_labels_slot_mut(&mut local_state.event_kind_labels, agent);
     if slot.remove(&normalized_key).is_none() {
-        println!(
-            "event-kind label for {} already unset",
-            template_scope_label(agent)
+        output.line(
+            &format!("config.event-kind.{}", template_scope_label(agent)),
+            Status::Ok,
+            format!(
+                "event-kind label for {} already unset",
+                template_scope_label(agent)
+            ),
         );
         return Ok(());
     }
 
     state::save(&state_path, &local_state)?;
-    println!("event-kind label for {} reset", template_scope_label(agent));
+    output.line(
+        &format!("config.event-kind.{}", template_scope_label(agent)),
+        Status::Ok,
+        format!("event-kind label for {} reset", template_scope_label(agent)),
+    );
     Ok(())
 }
 
-fn subagent_get() -> Result<()> {
+fn subagent_get(output: &mut Output) -> Result<()> {
     let state_path = paths::local_state_path()?;
     let local_state = state::load(&state_path)?;
-    println!("{}", local_state.notifications.claude_subagent);
+    let enabled = local_state.notifications.claude_subagent;
+    output.value("config.subagent", enabled.to_string(), enabled);
     Ok(())
 }
 
-fn subagent_set(enabled: bool) -> Result<()> {
+fn subagent_set(output: &mut Output, enabled: bool) -> Result<()> {
     let state_path = paths::local_state_path()?;
     let mut local_state = state::load(&state_path)?;
     if local_state.notifications.claude_subagent == enabled {
-        println!("subagent notifications unchanged");
+        output.line(
+            "config.subagent",
+            Status::Ok,
+            "subagent notifications unchanged",
+        );
         return Ok(());
     }
 
     local_state.notifications.claude_subagent = enabled;
     state::save(&state_path, &local_state)?;
-    println!("subagent notifications updated");
+    output.line(
+        "config.subagent",
+        Status::Ok,
+        "subagent notifications updated",
+    );
     Ok(())
 }
 
-fn setup_agents(agents: Vec<SetupAgent>) -> Result<()> {
-    let executable_path =
-        std::env::current_exe().context("failed to resolve current executable path")?;
-    let claude_path = paths::claude_settings_path()?;
-    let codex_path = paths::codex_config_path()?;
+fn min_duration_get(output: &mut Output, agent: Option<Agent>) -> Result<()> {
     let state_path = paths::local_state_path()?;
+    let local_state = state::load(&state_path)?;
+    let seconds = min_duration_slot(&local_state.notifications.min_duration, agent);
+    output.value(
+        &format!("config.min-duration.{}", template_scope_label(agent)),
+        seconds
+            .map(|seconds| agitiser_notify::duration::humanize(Duration::from_secs(seconds)))
+            .unwrap_or_else(|| "<unset>".to_string()),
+        seconds,
+    );
+    Ok(())
+}
 
+fn min_duration_set(output: &mut Output, agent: Option<Agent>, value: &str) -> Result<()> {
+    let seconds = agitiser_notify::duration::parse(value)?.as_secs();
+    let state_path = paths::local_state_path()?;
     let mut local_state = state::load(&state_path)?;
-    let initial_state = local_state.clone();
-
-    for agent in dedup_agents(agents) {
-        match agent {
-            SetupAgent::Claude => {
-                let changed = claude::setup(&claude_path, &executable_path)?;
-                if changed {
-                    println!(
-                        "Claude setup: installed managed hooks in {}",
-                        claude_path.display()
-                    );
-                } else {
-                    println!("Claude setup: already configured");
-                }
-            }
-            SetupAgent::Codex => {
-                let changed = codex::setup(&codex_path, &mut local_state, &executable_path)?;
-                if changed {
-                    println!(
-                        "Codex setup: configured notify command in {}",
-                        codex_path.display()
-                    );
-                } else {
-                    println!("Codex setup: already configured");
-                }
-            }
-            SetupAgent::Opencode => {
-                println!(
-                    "OpenCode setup: manual only in this release; see README for manual integration."
-                );
-            }
-        }
-    }
-
-    if local_state != initial_state {
-        state::save(&state_path, &local_state)?;
+    let slot = min_duration_slot_mut(&mut local_state.notifications.min_duration, agent);
+    if *slot == Some(seconds) {
+        output.line(
+            &format!("config.min-duration.{}", template_scope_label(agent)),
+            Status::Ok,
+            format!(
+                "minimum duration for {} unchanged",
+                template_scope_label(agent)
+            ),
+        );
+        return Ok(());
     }
 
+    *slot = Some(seconds);
+    state::save(&state_path, &local_state)?;
+    output.line(
+        &format!("config.min-duration.{}", template_scope_label(agent)),
+        Status::Ok,
+        format!(
+            "minimum duration for {} updated",
+            template_scope_label(agent)
+        ),
+    );
     Ok(())
 }
 
-fn remove_agents(agents: Vec<SetupAgent>) -> Result<()> {
-    let claude_path = paths::claude_settings_path()?;
-    let codex_path = paths::codex_config_path()?;
+fn min_duration_reset(output: &mut Output, agent: Option<Agent>) -> Result<()> {
     let state_path = paths::local_state_path()?;
-
     let mut local_state = state::load(&state_path)?;
-    let initial_state = local_state.clone();
-
-    for agent in dedup_agents(agents) {
-        match agent {
-            SetupAgent::Claude => {
-                let changed = claude::remove(&claude_path)?;
-                if changed {
-                    println!("Claude remove: removed managed hooks");
-                } else {
-                    println!("Claude remove: no managed hook found");
-                }
-            }
-            SetupAgent::Codex => {
-                let changed = codex::remove(&codex_path, &mut local_state)?;
-                if changed {
-                    println!("Codex remove: removed managed notify command");
-                } else {
-                    println!("Codex remove: no managed notify command found");
-                }
-            }
-            SetupAgent::Opencode => {
-                println!("OpenCode remove: nothing to remove (manual integration only).");
-            }
-        }
-    }
-
-    if local_state != initial_state {
-        state::save(&state_path, &local_state)?;
+    let slot = min_duration_slot_mut(&mut local_state.notifications.min_duration, agent);
+    if slot.take().is_none() {
+        output.line(
+            &format!("config.min-duration.{}", template_scope_label(agent)),
+            Status::Ok,
+            format!(
+                "minimum duration for {} already unset",
+                template_scope_label(agent)
+            ),
+        );
+        return Ok(());
     }
 
+    state::save(&state_path, &local_state)?;
+    output.line(
+        &format!("config.min-duration.{}", template_scope_label(agent)),
+        Status::Ok,
+        format!("minimum duration for {} reset", template_scope_label(agent)),
+    );
     Ok(())
 }
 
-fn ingest_event(
-    agent: Agent,
-    payload: Option<String>,
-    trailing_payload: Option<String>,
-    source: Option<String>,
-    verbose: bool,
-) -> Result<()> {
-    let payload_text = match payload.or(trailing_payload) {
-        Some(payload_text) => payload_text,
-        None => {
-            if std::io::stdin().is_terminal() {
-                bail!("no payload provided and stdin is a terminal; pass --payload or pipe JSON via stdin");
-            }
-            let mut stdin_payload = String::new();
-            std::io::stdin()
-                .read_to_string(&mut stdin_payload)
-                .context("failed to read payload from stdin")?;
-            stdin_payload
-        }
-    };
-
-    if payload_text.trim().is_empty() {
-        if verbose {
-            eprintln!("ingest: empty payload, skipping");
-        }
-        return Ok(());
+/// Applies an optional CLI value to a selector; an empty value clears it.
+fn update_field_selector(slot: &mut Option<String>, value: Option<String>) {
+    if let Some(value) = value {
+        let value = value.trim();
+        *slot = (!value.is_empty()).then(|| value.to_string());
     }
+}
 
-    let parsed_payload = match serde_json::from_str::<Value>(&payload_text) {
-        Ok(value) => value,
-        Err(error) => {
-            if verbose {
-                eprintln!("ingest: invalid JSON payload ({error})");
-            }
-            return Ok(());
-        }
-    };
-
-    let Some(event) = normalize(agent, parsed_payload) else {
-        if verbose {
-            eprintln!("ingest: payload is not a terminal event for {agent:?}");
-        }
-        return Ok(());
-    };
-
+fn generic_get(output: &mut Output) -> Result<()> {
     let state_path = paths::local_state_path()?;
-    let local_state = match state::load(&state_path) {
-        Ok(state) => state,
-        Err(error) => {
-            if verbose {
-                eprintln!(
-                    "ingest: failed to load {} ({error:#}), using default template",
-                    state_path.display()
-                );
-            }
-            state::LocalState::default()
-        }
-    };
-
-    if is_claude_subagent_event(&event) && !local_state.notifications.claude_subagent {
-        if verbose {
-            eprintln!("ingest: claude subagent notification disabled, skipping");
-        }
-        return Ok(());
-    }
-
-    speech::speak(&event, &local_state)?;
-    if verbose {
-        let cwd = event
-            .cwd
-            .as_ref()
-            .map(|path| path.display().to_string())
-            .unwrap_or_else(|| "<unknown>".to_string());
-        let payload_type = event
-            .raw_payload
-            .get("type")
-            .and_then(Value::as_str)
-            .unwrap_or("<none>");
-        let source_label = source.as_deref().unwrap_or("<none>");
-        eprintln!(
-            "ingest: announced {} event for project {} (cwd: {}, type: {}, source: {})",
-            event.event_kind, event.project_name, cwd, payload_type, source_label
+    let local_state = state::load(&state_path)?;
+    let config = &local_state.generic;
+    let selectors = [
+        ("kind_field", &config.kind_field),
+        ("cwd_field", &config.cwd_field),
+        ("project_field", &config.project_field),
+        ("agent_name_field", &config.agent_name_field),
+        ("message_field", &config.message_field),
+    ];
+    for (field, value) in selectors {
+        output.value(
+            &format!("config.generic.{field}"),
+            format!("{field} = {}", value.as_deref().unwrap_or("<default>")),
+            value,
         );
     }
+    let terminal_kinds = (!config.terminal_kinds.is_empty()).then_some(&config.terminal_kinds);
+    output.value(
+        "config.generic.terminal_kinds",
+        format!(
+            "terminal_kinds = {}",
+            terminal_kinds
+                .map(|kinds| kinds.join(","))
+                .unwrap_or_else(|| "<unset>".to_string())
+        ),
+        terminal_kinds,
+    );
+    output.value(
+        "config.generic.terminal_pattern",
+        format!(
+            "terminal_pattern = {}",
+            config.terminal_pattern.as_deref().unwrap_or("<unset>")
+        ),
+        &config.terminal_pattern,
+    );
     Ok(())
 }
 
-fn doctor() -> Result<()> {
-    let claude_path = paths::claude_settings_path()?;
-    let codex_path = paths::codex_config_path()?;
-
-    let mut has_errors = false;
+fn generic_set(output: &mut Output, args: GenericSetArgs) -> Result<()> {
+    let GenericSetArgs {
+        kind_field,
+        cwd_field,
+        project_field,
+        agent_name_field,
+        message_field,
+        terminal_kinds,
+        terminal_pattern,
+    } = args;
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let initial = local_state.generic.clone();
+    let config = &mut local_state.generic;
 
-    match speech::spd_say_path() {
-        Some(path) => println!(
-            "[ok] speech-dispatcher: found spd-say at {}",
-            path.display()
-        ),
-        None => {
-            println!("[error] speech-dispatcher: spd-say not found in PATH");
-            has_errors = true;
-        }
+    update_field_selector(&mut config.kind_field, kind_field);
+    update_field_selector(&mut config.cwd_field, cwd_field);
+    update_field_selector(&mut config.project_field, project_field);
+    update_field_selector(&mut config.agent_name_field, agent_name_field);
+    update_field_selector(&mut config.message_field, message_field);
+    if let Some(kinds) = terminal_kinds {
+        config.terminal_kinds = kinds
+            .iter()
+            .map(|kind| kind.trim())
+            .filter(|kind| !kind.is_empty())
+            .map(ToOwned::to_owned)
+            .collect();
     }
-
-    match claude::is_configured(&claude_path)? {
-        true => println!("[ok] claude: managed hooks configured"),
-        false => {
-            println!("[info] claude: managed hooks not configured");
+    if let Some(pattern) = terminal_pattern {
+        let pattern = pattern.trim();
+        if !pattern.is_empty() {
+            regex::Regex::new(pattern).context("invalid terminal pattern")?;
         }
+        config.terminal_pattern = (!pattern.is_empty()).then(|| pattern.to_string());
     }
 
-    match codex::is_configured(&codex_path)? {
-        true => println!("[ok] codex: managed notify command configured"),
-        false => {
-            println!("[info] codex: managed notify command not configured");
-        }
+    if local_state.generic == initial {
+        output.line(
+            "config.generic",
+            Status::Ok,
+            "generic agent settings unchanged",
+        );
+        return Ok(());
     }
 
-    println!("[info] opencode: auto-setup is not implemented in this release");
+    state::save(&state_path, &local_state)?;
+    output.line(
+        "config.generic",
+        Status::Ok,
+        "generic agent settings updated",
+    );
+    Ok(())
+}
 
-    if has_errors {
-        bail!("doctor found critical issues");
+fn generic_reset(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    if local_state.generic == state::GenericConfig::default() {
+        output.line(
+            "config.generic",
+            Status::Ok,
+            "generic agent settings already default",
+        );
+        return Ok(());
     }
 
+    local_state.generic = state::GenericConfig::default();
+    state::save(&state_path, &local_state)?;
+    output.line("config.generic", Status::Ok, "generic agent settings reset");
     Ok(())
 }
 
-fn dedup_agents(agents: Vec<SetupAgent>) -> Vec<SetupAgent> {
-    let mut ordered = Vec::new();
-    for agent in agents {
-        if !ordered.contains(&agent) {
-            ordered.push(agent);
-        }
+fn speech_get(output: &mut Output, kind: &str) -> Result<()> {
+    let key = normalize_event_kind_key(kind)?;
+    let state_path = paths::local_state_path()?;
+    let local_state = state::load(&state_path)?;
+    let settings = speech::resolve_settings(&key, &local_state.speech);
+    let priority = settings.priority.map(|priority| priority.as_str());
+    output.value(
+        &format!("config.speech.{key}.priority"),
+        format!("priority = {}", priority.unwrap_or("<default>")),
+        priority,
+    );
+    let voice = settings.voice.map(|voice| voice.as_str());
+    output.value(
+        &format!("config.speech.{key}.voice"),
+        format!("voice = {}", voice.unwrap_or("<default>")),
+        voice,
+    );
+    output.value(
+        &format!("config.speech.{key}.earcon"),
+        format!(
+            "earcon = {}",
+            settings
+                .earcon
+                .as_ref()
+                .map(|earcon| earcon.display().to_string())
+                .unwrap_or_else(|| "<none>".to_string())
+        ),
+        &settings.earcon,
+    );
+    Ok(())
+}
+
+fn speech_set(
+    output: &mut Output,
+    kind: &str,
+    priority: Option<speech::SpeechPriority>,
+    voice: Option<speech::VoiceType>,
+    earcon: Option<String>,
+) -> Result<()> {
+    let key = normalize_event_kind_key(kind)?;
+    if priority.is_none() && voice.is_none() && earcon.is_none() {
+        bail!("pass at least one of --priority, --voice or --earcon");
+    }
+
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let initial = local_state.speech.clone();
+    let config = local_state.speech.kinds.entry(key.clone()).or_default();
+    if priority.is_some() {
+        config.priority = priority;
+    }
+    if voice.is_some() {
+        config.voice = voice;
+    }
+    if let Some(earcon) = earcon {
+        config.earcon = Some(earcon.trim().to_string());
+    }
+
+    if local_state.speech == initial {
+        output.line(
+            &format!("config.speech.{key}"),
+            Status::Ok,
+            format!("speech settings for {key} unchanged"),
+        );
+        return Ok(());
+    }
+
+    state::save(&state_path, &local_state)?;
+    output.line(
+        &format!("config.speech.{key}"),
+        Status::Ok,
+        format!("speech settings for {key} updated"),
+    );
+    Ok(())
+}
+
+fn speech_reset(output: &mut Output, kind: &str) -> Result<()> {
+    let key = normalize_event_kind_key(kind)?;
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    if local_state.speech.kinds.remove(&key).is_none() {
+        output.line(
+            &format!("config.speech.{key}"),
+            Status::Ok,
+            format!("speech settings for {key} already default"),
+        );
+        return Ok(());
+    }
+
+    state::save(&state_path, &local_state)?;
+    output.line(
+        &format!("config.speech.{key}"),
+        Status::Ok,
+        format!("speech settings for {key} reset"),
+    );
+    Ok(())
+}
+
+fn quiet_get(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let local_state = state::load(&state_path)?;
+    let quiet = &local_state.quiet;
+    output.value(
+        "config.quiet.hours",
+        format!("hours = {}", quiet.hours.as_deref().unwrap_or("<unset>")),
+        &quiet.hours,
+    );
+    output.value(
+        "config.quiet.when_locked",
+        format!("when_locked = {}", quiet.when_locked),
+        quiet.when_locked,
+    );
+    let muted = mute_status(quiet, unix_now());
+    output.value("config.quiet.muted", format!("muted = {muted}"), &muted);
+    Ok(())
+}
+
+fn quiet_set(output: &mut Output, hours: Option<String>, when_locked: Option<bool>) -> Result<()> {
+    if hours.is_none() && when_locked.is_none() {
+        bail!("pass at least one of --hours or --when-locked");
+    }
+
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let initial = local_state.quiet.clone();
+    if let Some(hours) = hours {
+        let hours = hours.trim();
+        local_state.quiet.hours = if hours.is_empty() {
+            None
+        } else {
+            agitiser_notify::quiet::parse_quiet_hours(hours)?;
+            Some(hours.to_string())
+        };
+    }
+    if let Some(when_locked) = when_locked {
+        local_state.quiet.when_locked = when_locked;
+    }
+
+    if local_state.quiet == initial {
+        output.line("config.quiet", Status::Ok, "quiet settings unchanged");
+        return Ok(());
+    }
+
+    state::save(&state_path, &local_state)?;
+    output.line("config.quiet", Status::Ok, "quiet settings updated");
+    Ok(())
+}
+
+/// Leaves an active mute alone; `unmute` is the way out of that.
+fn quiet_reset(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let quiet = &mut local_state.quiet;
+    if quiet.hours.is_none() && !quiet.when_locked {
+        output.line("config.quiet", Status::Ok, "quiet settings already default");
+        return Ok(());
+    }
+
+    quiet.hours = None;
+    quiet.when_locked = false;
+    state::save(&state_path, &local_state)?;
+    output.line("config.quiet", Status::Ok, "quiet settings reset");
+    Ok(())
+}
+
+fn rate_limit_get(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let local_state = state::load(&state_path)?;
+    let rate_limit = &local_state.rate_limit;
+    output.value(
+        "config.rate-limit.burst",
+        format!(
+            "burst = {}",
+            rate_limit
+                .burst
+                .map(|burst| burst.to_string())
+                .unwrap_or_else(|| "<unset>".to_string())
+        ),
+        rate_limit.burst,
+    );
+    output.value(
+        "config.rate-limit.refill_secs",
+        format!("refill = {}s", rate_limit.refill_secs),
+        rate_limit.refill_secs,
+    );
+    output.value(
+        "config.rate-limit.cooldown_secs",
+        format!(
+            "cooldown = {}",
+            rate_limit
+                .cooldown_secs
+                .map(|cooldown| format!("{cooldown}s"))
+                .unwrap_or_else(|| "<unset>".to_string())
+        ),
+        rate_limit.cooldown_secs,
+    );
+    Ok(())
+}
+
+fn rate_limit_set(
+    output: &mut Output,
+    burst: Option<u32>,
+    refill: Option<&str>,
+    cooldown: Option<&str>,
+) -> Result<()> {
+    if burst.is_none() && refill.is_none() && cooldown.is_none() {
+        bail!("pass at least one of --burst, --refill or --cooldown");
+    }
+    let refill = refill
+        .map(agitiser_notify::duration::parse)
+        .transpose()?
+        .map(|refill| refill.as_secs());
+    if refill == Some(0) {
+        bail!("refill interval must be at least 1 second");
+    }
+    let cooldown = cooldown
+        .map(agitiser_notify::duration::parse)
+        .transpose()?
+        .map(|cooldown| cooldown.as_secs());
+
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let initial = local_state.rate_limit.clone();
+    let rate_limit = &mut local_state.rate_limit;
+    if let Some(burst) = burst {
+        rate_limit.burst = Some(burst).filter(|burst| *burst > 0);
+    }
+    if let Some(refill) = refill {
+        rate_limit.refill_secs = refill;
+    }
+    if let Some(cooldown) = cooldown {
+        rate_limit.cooldown_secs = Some(cooldown).filter(|cooldown| *cooldown > 0);
+    }
+
+    if local_state.rate_limit == initial {
+        output.line("config.rate-limit", Status::Ok, "rate limit unchanged");
+        return Ok(());
+    }
+
+    state::save(&state_path, &local_state)?;
+    output.line("config.rate-limit", Status::Ok, "rate limit updated");
+    Ok(())
+}
+
+fn rate_limit_reset(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    if local_state.rate_limit == state::RateLimitConfig::default() {
+        output.line(
+            "config.rate-limit",
+            Status::Ok,
+            "rate limit already default",
+        );
+        return Ok(());
+    }
+
+    local_state.rate_limit = state::RateLimitConfig::default();
+    state::save(&state_path, &local_state)?;
+    output.line("config.rate-limit", Status::Ok, "rate limit reset");
+    Ok(())
+}
+
+fn dedup_get(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let local_state = state::load(&state_path)?;
+    let window_secs = local_state.dedup.window_secs;
+    output.value(
+        "config.dedup.window_secs",
+        format!("window = {window_secs}s"),
+        window_secs,
+    );
+    Ok(())
+}
+
+fn dedup_set(output: &mut Output, window: &str) -> Result<()> {
+    let window_secs = agitiser_notify::duration::parse(window)?.as_secs();
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    if local_state.dedup.window_secs == window_secs {
+        output.line("config.dedup", Status::Ok, "dedup window unchanged");
+        return Ok(());
+    }
+
+    local_state.dedup.window_secs = window_secs;
+    state::save(&state_path, &local_state)?;
+    output.line("config.dedup", Status::Ok, "dedup window updated");
+    Ok(())
+}
+
+fn dedup_reset(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    if local_state.dedup == state::DedupConfig::default() {
+        output.line("config.dedup", Status::Ok, "dedup window already default");
+        return Ok(());
+    }
+
+    local_state.dedup = state::DedupConfig::default();
+    state::save(&state_path, &local_state)?;
+    output.line("config.dedup", Status::Ok, "dedup window reset");
+    Ok(())
+}
+
+fn parse_rule_fields(fields: &[String]) -> Result<BTreeMap<String, String>> {
+    fields
+        .iter()
+        .map(|field| match field.split_once('=') {
+            Some((path, value)) if !path.trim().is_empty() => {
+                Ok((path.trim().to_string(), value.to_string()))
+            }
+            _ => bail!("invalid --field {field:?}; use PATH=VALUE"),
+        })
+        .collect()
+}
+
+fn parse_duration_secs(value: Option<&str>) -> Result<Option<u64>> {
+    Ok(value
+        .map(agitiser_notify::duration::parse)
+        .transpose()?
+        .map(|duration| duration.as_secs()))
+}
+
+fn rules_list(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let local_state = state::load(&state_path)?;
+    let rules = rules::effective_rules(&local_state.notifications);
+    if rules.is_empty() {
+        output.line("config.rules", Status::Ok, "no rules configured");
+        return Ok(());
+    }
+
+    let builtin = rules.len() - local_state.notifications.rules.len();
+    for (index, rule) in rules.iter().enumerate() {
+        let line = if index < builtin {
+            format!("-  {} (built-in): {}", rule.name, rules::describe(rule))
+        } else {
+            format!(
+                "{}. {}: {}",
+                index - builtin + 1,
+                rule.name,
+                rules::describe(rule)
+            )
+        };
+        output.value(&format!("config.rules.{}", rule.name), line, rule);
+    }
+    Ok(())
+}
+
+fn rules_add(output: &mut Output, rule: state::RuleConfig, position: Option<usize>) -> Result<()> {
+    if rule.name.is_empty() {
+        bail!("rule name must not be empty");
+    }
+    if rule.name == rules::SUBAGENT_RULE_NAME {
+        bail!(
+            "rule name {} is reserved for `config subagent set --enabled false`",
+            rules::SUBAGENT_RULE_NAME
+        );
+    }
+    if let Some(template) = rule.template.as_deref() {
+        agitiser_notify::template::validate_template(template)?;
+    }
+
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let configured = &mut local_state.notifications.rules;
+    if configured.iter().any(|existing| existing.name == rule.name) {
+        bail!("rule {} already exists; remove it first", rule.name);
+    }
+    let index = match position {
+        Some(0) => bail!("rule positions start at 1"),
+        Some(position) => (position - 1).min(configured.len()),
+        None => configured.len(),
+    };
+    let name = rule.name.clone();
+    configured.insert(index, rule);
+    state::save(&state_path, &local_state)?;
+    output.line(
+        "config.rules",
+        Status::Ok,
+        format!("rule {name} added at position {}", index + 1),
+    );
+    Ok(())
+}
+
+fn rules_remove(output: &mut Output, name: &str) -> Result<()> {
+    if name == rules::SUBAGENT_RULE_NAME {
+        bail!(
+            "rule {} is built in; use `config subagent set --enabled true` to turn it off",
+            rules::SUBAGENT_RULE_NAME
+        );
+    }
+
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let configured = &mut local_state.notifications.rules;
+    let before = configured.len();
+    configured.retain(|rule| rule.name != name);
+    if configured.len() == before {
+        output.line(
+            "config.rules",
+            Status::Info,
+            format!("rule {name} not found"),
+        );
+        return Ok(());
+    }
+
+    state::save(&state_path, &local_state)?;
+    output.line("config.rules", Status::Ok, format!("rule {name} removed"));
+    Ok(())
+}
+
+fn rules_test(output: &mut Output, agent: Agent, name: Option<&str>, payload: &str) -> Result<()> {
+    let payload = serde_json::from_str::<Value>(payload).context("invalid JSON payload")?;
+    let local_state = state::load(&paths::local_state_path()?)?;
+    let event = match (agent, name) {
+        (Agent::Custom, Some(name)) => normalize_custom(name, payload, &local_state),
+        _ => normalize_with_state(agent, payload, &local_state),
+    };
+    let Some(event) = event else {
+        output.line(
+            "config.rules.test",
+            Status::Info,
+            "payload is not a terminal event; no rule applies",
+        );
+        return Ok(());
+    };
+
+    let minute_of_day = agitiser_notify::quiet::local_minute_of_day();
+    let matched = rules::evaluate(&local_state.notifications, &event, minute_of_day);
+    let message = match &matched {
+        Some(rule) if rule.action == RuleAction::Drop => format!(
+            "matched rule {}: drop ({})",
+            rule.name,
+            rules::drop_reason(rule)
+        ),
+        Some(rule) => format!("matched rule {}: {}", rule.name, rules::describe(rule)),
+        None => format!(
+            "no rule matched; {} event for {} is announced as usual",
+            event.event_kind, event.project_name
+        ),
+    };
+    output.value("config.rules.test", message, matched);
+    Ok(())
+}
+
+fn mute_status(quiet: &state::QuietConfig, now: u64) -> String {
+    match (quiet.muted, quiet.muted_until) {
+        (true, None) => "until unmute".to_string(),
+        (true, Some(until)) if now < until => {
+            format!("until {}", history::format_local_time(until))
+        }
+        _ => "no".to_string(),
+    }
+}
+
+fn mute(output: &mut Output, duration: Option<&str>) -> Result<()> {
+    let duration = duration.map(agitiser_notify::duration::parse).transpose()?;
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    local_state.quiet.muted = true;
+    local_state.quiet.muted_until = duration.map(|duration| unix_now() + duration.as_secs());
+    state::save(&state_path, &local_state)?;
+    output.line(
+        "mute",
+        Status::Ok,
+        format!("muted {}", mute_status(&local_state.quiet, unix_now())),
+    );
+    Ok(())
+}
+
+fn unmute(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    if !local_state.quiet.muted {
+        output.line("unmute", Status::Ok, "not muted");
+        return Ok(());
+    }
+
+    local_state.quiet.muted = false;
+    local_state.quiet.muted_until = None;
+    state::save(&state_path, &local_state)?;
+    let pending = digest::load(&paths::pending_path()?)?.events.len();
+    if pending > 0 {
+        output.line("unmute", Status::Ok, format!("unmuted; {pending} missed announcement(s), run `agitiser-notify digest` to hear them"));
+    } else {
+        output.line("unmute", Status::Ok, "unmuted");
+    }
+    Ok(())
+}
+
+fn speak_digest(output: &mut Output, dry_run: bool) -> Result<()> {
+    let pending_path = paths::pending_path()?;
+    let events = match dry_run {
+        true => digest::load(&pending_path)?.events,
+        false => digest::drain(&pending_path)?,
+    };
+    if events.is_empty() {
+        output.line("digest", Status::Ok, "nothing missed");
+        return Ok(());
+    }
+
+    let local_state = state::load(&paths::local_state_path()?).unwrap_or_default();
+    let message = digest::compose(&events, local_state.templates.digest.as_deref());
+    output.value("digest", message.as_str(), events.len());
+    if dry_run {
+        return Ok(());
+    }
+
+    let settings = speech::resolve_settings("digest", &local_state.speech);
+    if let Err(error) = speech::speak(&message, &settings) {
+        digest::requeue(&pending_path, events)?;
+        return Err(error);
+    }
+    Ok(())
+}
+
+fn summary_get(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let local_state = state::load(&state_path)?;
+    let max_words = local_state.summary.max_words;
+    output.value("config.summary.max_words", max_words.to_string(), max_words);
+    Ok(())
+}
+
+fn summary_set(output: &mut Output, max_words: usize) -> Result<()> {
+    if max_words == 0 {
+        bail!("summary max words must be at least 1");
+    }
+
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    if local_state.summary.max_words == max_words {
+        output.line("config.summary", Status::Ok, "summary max words unchanged");
+        return Ok(());
+    }
+
+    local_state.summary.max_words = max_words;
+    state::save(&state_path, &local_state)?;
+    output.line("config.summary", Status::Ok, "summary max words updated");
+    Ok(())
+}
+
+fn codex_chain_get(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let local_state = state::load(&state_path)?;
+    let enabled = local_state.codex.chain_previous_notify;
+    output.value("config.codex-chain", enabled.to_string(), enabled);
+    Ok(())
+}
+
+fn codex_chain_set(output: &mut Output, enabled: bool) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    if local_state.codex.chain_previous_notify == enabled {
+        output.line(
+            "config.codex-chain",
+            Status::Ok,
+            "codex notify chaining unchanged",
+        );
+        return Ok(());
+    }
+
+    local_state.codex.chain_previous_notify = enabled;
+    state::save(&state_path, &local_state)?;
+    output.line(
+        "config.codex-chain",
+        Status::Ok,
+        "codex notify chaining updated",
+    );
+    Ok(())
+}
+
+fn claude_hooks_list(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let local_state = state::load(&state_path)?;
+    for hook in claude::managed_hooks(&local_state.claude) {
+        let line = match &hook.event_kind {
+            Some(event_kind) => format!("{} {} {}", hook.event, hook.matcher, event_kind),
+            None => format!("{} {}", hook.event, hook.matcher),
+        };
+        output.value(&format!("config.claude-hooks.{}", hook.event), line, &hook);
+    }
+    Ok(())
+}
+
+fn claude_hooks_add(
+    output: &mut Output,
+    event: &str,
+    matcher: &str,
+    event_kind: Option<&str>,
+) -> Result<()> {
+    let event = event.trim();
+    if event.is_empty() {
+        bail!("hook event must not be empty");
+    }
+    let matcher = match matcher.trim() {
+        "" | "*" => "*",
+        matcher => {
+            agitiser_notify::event::matcher_regex(matcher)
+                .with_context(|| format!("invalid matcher {matcher:?}"))?;
+            matcher
+        }
+    };
+    let event_kind = match event_kind {
+        Some(event_kind) => Some(normalize_event_kind_key(event_kind)?),
+        None => None,
+    };
+
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let desired = state::ClaudeHookConfig {
+        event: event.to_string(),
+        matcher: matcher.to_string(),
+        event_kind,
+    };
+    if claude::managed_hooks(&local_state.claude).contains(&desired) {
+        output.line(
+            "config.claude-hooks",
+            Status::Ok,
+            format!("claude hook {event} {matcher} unchanged"),
+        );
+        return Ok(());
+    }
+
+    let key = desired.key();
+    let claude_state = &mut local_state.claude;
+    claude_state
+        .disabled_hooks
+        .retain(|disabled| *disabled != key);
+    claude_state.hooks.retain(|hook| hook.key() != key);
+    if !claude::default_managed_hooks().contains(&desired) {
+        claude_state.hooks.push(desired);
+    }
+    state::save(&state_path, &local_state)?;
+    output.line("config.claude-hooks", Status::Ok, format!("claude hook {event} {matcher} added; run `agitiser-notify setup --agents claude` to install"));
+    Ok(())
+}
+
+fn claude_hooks_remove(output: &mut Output, event: &str, matcher: Option<&str>) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let selected = |hook: &state::ClaudeHookConfig| {
+        hook.event == event.trim() && matcher.is_none_or(|matcher| hook.matcher == matcher.trim())
+    };
+    let removed: Vec<state::ClaudeHookKey> = claude::managed_hooks(&local_state.claude)
+        .iter()
+        .filter(|hook| selected(hook))
+        .map(state::ClaudeHookConfig::key)
+        .collect();
+    if removed.is_empty() {
+        output.line(
+            "config.claude-hooks",
+            Status::Info,
+            format!("claude hook {} not managed", event.trim()),
+        );
+        return Ok(());
+    }
+
+    let claude_state = &mut local_state.claude;
+    claude_state.hooks.retain(|hook| !selected(hook));
+    claude_state
+        .disabled_hooks
+        .extend(removed.into_iter().filter(claude::is_default_hook));
+    state::save(&state_path, &local_state)?;
+    output.line(
+        "config.claude-hooks",
+        Status::Ok,
+        format!(
+            "claude hook {} removed; run `agitiser-notify setup --agents claude` to apply",
+            event.trim()
+        ),
+    );
+    Ok(())
+}
+
+fn claude_hooks_reset(output: &mut Output) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    if local_state.claude == state::ClaudeState::default() {
+        output.line(
+            "config.claude-hooks",
+            Status::Ok,
+            "claude hooks already default",
+        );
+        return Ok(());
+    }
+
+    local_state.claude = state::ClaudeState::default();
+    state::save(&state_path, &local_state)?;
+    output.line(
+        "config.claude-hooks",
+        Status::Ok,
+        "claude hooks reset to default",
+    );
+    Ok(())
+}
+
+fn setup_agents(
+    output: &mut Output,
+    agents: Vec<SetupAgent>,
+    scope: SettingsScope,
+    dir: Option<&Path>,
+) -> Result<()> {
+    let claude_path = paths::claude_scope_settings_path(scope, dir)?;
+    let codex_path = paths::codex_config_path()?;
+    let aider_path = paths::aider_config_path()?;
+    let state_path = paths::local_state_path()?;
+
+    let mut local_state = state::load(&state_path)?;
+    let initial_state = local_state.clone();
+    let executable_path = relink::wanted_executable(&local_state.command)?;
+    if scope == SettingsScope::Project && !integrations::program_exists(relink::COMMAND_NAME) {
+        output.line(
+            "setup.command",
+            Status::Warn,
+            format!(
+                "{} is not on PATH; project hooks run it by name and fail here until it is",
+                relink::COMMAND_NAME
+            ),
+        );
+    }
+
+    for agent in dedup_agents(agents) {
+        match agent {
+            SetupAgent::Claude => {
+                let hooks = claude::managed_hooks(&local_state.claude);
+                let executable_path = relink::scope_executable(scope, &executable_path);
+                let changed = claude::setup(&claude_path, executable_path, &hooks)?;
+                if changed {
+                    output.line(
+                        "setup.claude",
+                        Status::Ok,
+                        format!(
+                            "Claude setup: installed managed hooks in {} ({} scope)",
+                            claude_path.display(),
+                            scope.label()
+                        ),
+                    );
+                } else {
+                    output.line(
+                        "setup.claude",
+                        Status::Ok,
+                        format!("Claude setup: already configured ({} scope)", scope.label()),
+                    );
+                }
+            }
+            SetupAgent::Codex if scope != SettingsScope::User => {
+                output.line(
+                    "setup.codex",
+                    Status::Info,
+                    format!(
+                        "Codex setup: skipped; {} scope is not supported for Codex",
+                        scope.label()
+                    ),
+                );
+            }
+            SetupAgent::Codex => {
+                let changed = codex::setup(&codex_path, &mut local_state, &executable_path)?;
+                if changed {
+                    output.line(
+                        "setup.codex",
+                        Status::Ok,
+                        format!(
+                            "Codex setup: configured notify command in {}",
+                            codex_path.display()
+                        ),
+                    );
+                } else {
+                    output.line("setup.codex", Status::Ok, "Codex setup: already configured");
+                }
+            }
+            SetupAgent::Aider if scope != SettingsScope::User => {
+                output.line(
+                    "setup.aider",
+                    Status::Info,
+                    format!(
+                        "Aider setup: skipped; {} scope is not supported for Aider",
+                        scope.label()
+                    ),
+                );
+            }
+            SetupAgent::Aider => {
+                let changed = aider::setup(&aider_path, &mut local_state, &executable_path)?;
+                if changed {
+                    output.line(
+                        "setup.aider",
+                        Status::Ok,
+                        format!(
+                            "Aider setup: configured notifications command in {}",
+                            aider_path.display()
+                        ),
+                    );
+                } else {
+                    output.line("setup.aider", Status::Ok, "Aider setup: already configured");
+                }
+            }
+            SetupAgent::Gemini => match paths::gemini_scope_settings_path(scope, dir)? {
+                Some(settings_path) => {
+                    let executable_path = relink::scope_executable(scope, &executable_path);
+                    let changed = gemini::setup(&settings_path, executable_path)?;
+                    if changed {
+                        output.line(
+                            "setup.gemini",
+                            Status::Ok,
+                            format!(
+                                "Gemini setup: installed managed hooks in {} ({} scope)",
+                                settings_path.display(),
+                                scope.label()
+                            ),
+                        );
+                    } else {
+                        output.line(
+                            "setup.gemini",
+                            Status::Ok,
+                            format!("Gemini setup: already configured ({} scope)", scope.label()),
+                        );
+                    }
+                }
+                None => output.line(
+                    "setup.gemini",
+                    Status::Info,
+                    format!(
+                        "Gemini setup: skipped; {} scope is not supported for Gemini",
+                        scope.label()
+                    ),
+                ),
+            },
+            SetupAgent::Opencode => match paths::opencode_plugin_dir(scope, dir)? {
+                Some(plugin_dir) => {
+                    let executable_path = relink::scope_executable(scope, &executable_path);
+                    let changed = opencode::setup(&plugin_dir, executable_path)?;
+                    if changed {
+                        output.line(
+                            "setup.opencode",
+                            Status::Ok,
+                            format!(
+                                "OpenCode setup: installed managed plugin in {} ({} scope)",
+                                opencode::plugin_path(&plugin_dir).display(),
+                                scope.label()
+                            ),
+                        );
+                    } else {
+                        output.line(
+                            "setup.opencode",
+                            Status::Ok,
+                            format!(
+                                "OpenCode setup: already configured ({} scope)",
+                                scope.label()
+                            ),
+                        );
+                    }
+                }
+                None => output.line(
+                    "setup.opencode",
+                    Status::Info,
+                    format!(
+                        "OpenCode setup: skipped; {} scope is not supported for OpenCode",
+                        scope.label()
+                    ),
+                ),
+            },
+        }
+    }
+
+    if local_state != initial_state {
+        state::save(&state_path, &local_state)?;
+    }
+
+    Ok(())
+}
+
+fn remove_agents(
+    output: &mut Output,
+    agents: Vec<SetupAgent>,
+    scope: SettingsScope,
+    dir: Option<&Path>,
+) -> Result<()> {
+    let claude_path = paths::claude_scope_settings_path(scope, dir)?;
+    let codex_path = paths::codex_config_path()?;
+    let aider_path = paths::aider_config_path()?;
+    let state_path = paths::local_state_path()?;
+
+    let mut local_state = state::load(&state_path)?;
+    let initial_state = local_state.clone();
+
+    for agent in dedup_agents(agents) {
+        match agent {
+            SetupAgent::Claude => {
+                let changed = claude::remove(&claude_path)?;
+                if changed {
+                    output.line(
+                        "remove.claude",
+                        Status::Ok,
+                        format!(
+                            "Claude remove: removed managed hooks from {} ({} scope)",
+                            claude_path.display(),
+                            scope.label()
+                        ),
+                    );
+                } else {
+                    output.line(
+                        "remove.claude",
+                        Status::Ok,
+                        format!(
+                            "Claude remove: no managed hook found ({} scope)",
+                            scope.label()
+                        ),
+                    );
+                }
+            }
+            SetupAgent::Codex if scope != SettingsScope::User => {
+                output.line(
+                    "remove.codex",
+                    Status::Info,
+                    format!(
+                        "Codex remove: skipped; {} scope is not supported for Codex",
+                        scope.label()
+                    ),
+                );
+            }
+            SetupAgent::Codex => {
+                let changed = codex::remove(&codex_path, &mut local_state)?;
+                if changed {
+                    output.line(
+                        "remove.codex",
+                        Status::Ok,
+                        "Codex remove: removed managed notify command",
+                    );
+                } else {
+                    output.line(
+                        "remove.codex",
+                        Status::Ok,
+                        "Codex remove: no managed notify command found",
+                    );
+                }
+            }
+            SetupAgent::Aider if scope != SettingsScope::User => {
+                output.line(
+                    "remove.aider",
+                    Status::Info,
+                    format!(
+                        "Aider remove: skipped; {} scope is not supported for Aider",
+                        scope.label()
+                    ),
+                );
+            }
+            SetupAgent::Aider => {
+                let changed = aider::remove(&aider_path, &mut local_state)?;
+                if changed {
+                    output.line(
+                        "remove.aider",
+                        Status::Ok,
+                        "Aider remove: removed managed notifications command",
+                    );
+                } else {
+                    output.line(
+                        "remove.aider",
+                        Status::Ok,
+                        "Aider remove: no managed notifications command found",
+                    );
+                }
+            }
+            SetupAgent::Gemini => match paths::gemini_scope_settings_path(scope, dir)? {
+                Some(settings_path) => {
+                    let changed = gemini::remove(&settings_path)?;
+                    if changed {
+                        output.line(
+                            "remove.gemini",
+                            Status::Ok,
+                            format!(
+                                "Gemini remove: removed managed hooks from {} ({} scope)",
+                                settings_path.display(),
+                                scope.label()
+                            ),
+                        );
+                    } else {
+                        output.line(
+                            "remove.gemini",
+                            Status::Ok,
+                            format!(
+                                "Gemini remove: no managed hook found ({} scope)",
+                                scope.label()
+                            ),
+                        );
+                    }
+                }
+                None => output.line(
+                    "remove.gemini",
+                    Status::Info,
+                    format!(
+                        "Gemini remove: skipped; {} scope is not supported for Gemini",
+                        scope.label()
+                    ),
+                ),
+            },
+            SetupAgent::Opencode => match paths::opencode_plugin_dir(scope, dir)? {
+                Some(plugin_dir) => {
+                    let changed = opencode::remove(&plugin_dir)?;
+                    if changed {
+                        output.line(
+                            "remove.opencode",
+                            Status::Ok,
+                            format!(
+                                "OpenCode remove: removed managed plugin {} ({} scope)",
+                                opencode::plugin_path(&plugin_dir).display(),
+                                scope.label()
+                            ),
+                        );
+                    } else {
+                        output.line(
+                            "remove.opencode",
+                            Status::Ok,
+                            format!(
+                                "OpenCode remove: no managed plugin found ({} scope)",
+                                scope.label()
+                            ),
+                        );
+                    }
+                }
+                None => output.line(
+                    "remove.opencode",
+                    Status::Info,
+                    format!(
+                        "OpenCode remove: skipped; {} scope is not supported for OpenCode",
+                        scope.label()
+                    ),
+                ),
+            },
+        }
+    }
+
+    if local_state != initial_state {
+        state::save(&state_path, &local_state)?;
+    }
+
+    Ok(())
+}
+
+fn relink_integrations(
+    output: &mut Output,
+    dir: Option<&Path>,
+    use_path: bool,
+    absolute: bool,
+) -> Result<()> {
+    let state_path = paths::local_state_path()?;
+    let mut local_state = state::load(&state_path)?;
+    let initial_state = local_state.clone();
+    if use_path || absolute {
+        local_state.command.use_path = use_path;
+    }
+    let wanted = relink::wanted_executable(&local_state.command)?;
+
+    let installations = relink::installations(&SettingsScope::ALL, dir, &local_state)?;
+    if installations.is_empty() {
+        output.line(
+            "relink",
+            Status::Info,
+            "relink: no managed integrations installed",
+        );
+    }
+    for installation in installations {
+        let name = Agent::from(installation.agent).display_name();
+        let scope = installation.scope.label();
+        let agent = installation
+            .agent
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        let id = format!("relink.{agent}.{scope}");
+        let wanted = relink::scope_executable(installation.scope, &wanted);
+        if installation.link(wanted) == relink::Link::Current {
+            output.line(
+                &id,
+                Status::Ok,
+                format!(
+                    "{name} relink: already runs {} ({scope} scope)",
+                    wanted.display()
+                ),
+            );
+            continue;
+        }
+        relink::install(
+            installation.agent,
+            &installation.location,
+            &mut local_state,
+            wanted,
+        )?;
+        output.line(
+            &id,
+            Status::Ok,
+            format!(
+                "{name} relink: {} -> {} ({scope} scope)",
+                installation.programs.join(", "),
+                wanted.display()
+            ),
+        );
+    }
+
+    if local_state != initial_state {
+        state::save(&state_path, &local_state)?;
+    }
+    Ok(())
+}
+
+fn ingest_event(
+    agent: Agent,
+    name: Option<String>,
+    payload: Option<String>,
+    trailing_payload: Option<String>,
+    source: Option<String>,
+    verbose: bool,
+) -> Result<()> {
+    let payload_text = match payload.or(trailing_payload) {
+        Some(payload_text) => payload_text,
+        None => {
+            if std::io::stdin().is_terminal() {
+                bail!("no payload provided and stdin is a terminal; pass --payload or pipe JSON via stdin");
+            }
+            let mut stdin_payload = String::new();
+            std::io::stdin()
+                .read_to_string(&mut stdin_payload)
+                .context("failed to read payload from stdin")?;
+            stdin_payload
+        }
+    };
+
+    let mut entry = HistoryEntry::new(agent, source.as_deref(), unix_now());
+    if payload_text.trim().is_empty() {
+        if verbose {
+            eprintln!("ingest: empty payload, skipping");
+        }
+        entry.filter("empty payload");
+        record_history(&entry, verbose);
+        return Ok(());
+    }
+
+    let state_path = paths::local_state_path()?;
+    let local_state = match state::load(&state_path) {
+        Ok(state) => state,
+        Err(error) => {
+            if verbose {
+                eprintln!(
+                    "ingest: failed to load {} ({error:#}), using default template",
+                    state_path.display()
+                );
+            }
+            state::LocalState::default()
+        }
+    };
+
+    if name.is_some() && agent != Agent::Custom {
+        bail!("--name is only valid with --agent custom");
+    }
+    let name = name.filter(|name| {
+        let known = local_state.custom_agents.contains_key(name);
+        if !known {
+            if verbose {
+                eprintln!(
+                    "ingest: unknown custom agent {name}; declare it under [custom_agents.{name}] in {}, using the default name",
+                    state_path.display()
+                );
+            }
+            entry.unknown_custom_agent = Some(name.clone());
+        }
+        known
+    });
+
+    let result = announce_payload(
+        agent,
+        name.as_deref(),
+        &payload_text,
+        &local_state,
+        verbose,
+        &mut entry,
+    );
+    record_history(&entry, verbose);
+
+    if agent == Agent::Codex {
+        match codex::run_previous_notify(&local_state.codex, &payload_text) {
+            Ok(true) if verbose => eprintln!("ingest: chained previous codex notify command"),
+            Ok(_) => {}
+            Err(error) => {
+                if verbose {
+                    eprintln!("ingest: previous codex notify command failed ({error:#})");
+                }
+            }
+        }
     }
-    ordered
+
+    result
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// History is best effort: a failed write never fails the ingest that produced it.
+fn record_history(entry: &HistoryEntry, verbose: bool) {
+    if let Err(error) = paths::history_path().and_then(|path| history::append(&path, entry)) {
+        if verbose {
+            eprintln!("ingest: failed to record history ({error:#})");
+        }
+    }
+}
+
+fn announce_payload(
+    agent: Agent,
+    name: Option<&str>,
+    payload_text: &str,
+    local_state: &state::LocalState,
+    verbose: bool,
+    entry: &mut HistoryEntry,
+) -> Result<()> {
+    let parsed_payload = match serde_json::from_str::<Value>(payload_text) {
+        Ok(value) => value,
+        Err(error) => {
+            if verbose {
+                eprintln!("ingest: invalid JSON payload ({error})");
+            }
+            entry.filter(format!("invalid JSON payload ({error})"));
+            return Ok(());
+        }
+    };
+
+    let turn_duration = match agent {
+        Agent::Codex => paths::codex_sessions_dir().and_then(|sessions_dir| {
+            turns::codex_turn_duration(&sessions_dir, &parsed_payload, entry.timestamp)
+        }),
+        _ => paths::turns_path().and_then(|turns_path| {
+            turns::track(&turns_path, agent, &parsed_payload, entry.timestamp)
+        }),
+    };
+    let turn_duration = match turn_duration {
+        Ok(duration) => duration,
+        Err(error) => {
+            if verbose {
+                eprintln!("ingest: failed to track turn start ({error:#})");
+            }
+            None
+        }
+    };
+
+    let raw_kind = ["hook_event_name", "type"]
+        .iter()
+        .find_map(|key| parsed_payload.get(*key).and_then(Value::as_str))
+        .map(ToOwned::to_owned);
+    let raw_cwd = parsed_payload
+        .get("cwd")
+        .and_then(Value::as_str)
+        .map(ToOwned::to_owned);
+    let normalized = match name {
+        Some(name) => normalize_custom(name, parsed_payload, local_state),
+        None => normalize_with_state(agent, parsed_payload, local_state),
+    };
+    let Some(mut event) = normalized else {
+        if verbose {
+            eprintln!("ingest: payload is not a terminal event for {agent:?}");
+        }
+        entry.project = raw_cwd
+            .as_deref()
+            .map(|cwd| project_name_from_cwd(Some(cwd)));
+        entry.cwd = raw_cwd;
+        entry.filter(match raw_kind {
+            Some(kind) => format!("not a terminal event ({kind})"),
+            None => "not a terminal event".to_string(),
+        });
+        return Ok(());
+    };
+    event.duration = turn_duration;
+
+    pipeline::announce(event, name, local_state, verbose, entry)
+}
+
+/// Runs `command` to completion and announces its outcome; returns the exit code to
+/// propagate. Announcement problems are reported but never change the exit code.
+fn run_command(
+    output: &mut Output,
+    command: &[String],
+    name: Option<&str>,
+    verbose: bool,
+) -> Result<i32> {
+    let outcome = agitiser_notify::run::execute(command, output.is_json())?;
+    let exit_code = outcome.exit_status();
+
+    let local_state = match paths::local_state_path().and_then(|path| state::load(&path)) {
+        Ok(state) => state,
+        Err(error) => {
+            if verbose {
+                eprintln!("run: failed to load local state ({error:#}), using defaults");
+            }
+            state::LocalState::default()
+        }
+    };
+    let cwd = std::env::current_dir().ok();
+    let event = agitiser_notify::run::event(command, &outcome, cwd, name);
+    let mut entry = HistoryEntry::new(Agent::Generic, Some("run"), unix_now());
+    let result = pipeline::announce(event, None, &local_state, verbose, &mut entry);
+    record_history(&entry, verbose);
+    let command_line = agitiser_notify::run::command_line(command);
+    match result {
+        Ok(()) => output.record(
+            "run",
+            match entry.delivery {
+                Delivery::Failed => Status::Error,
+                _ => Status::Ok,
+            },
+            format!(
+                "{command_line} exited with {exit_code}; {}",
+                describe_delivery(&entry)
+            ),
+            &entry,
+        ),
+        Err(error) => {
+            eprintln!("run: announcement failed ({error:#})");
+            output.record(
+                "run",
+                Status::Error,
+                format!("{command_line} exited with {exit_code}; announcement failed ({error:#})"),
+                &entry,
+            );
+        }
+    }
+    Ok(exit_code)
+}
+
+fn replay_last(output: &mut Output, count: usize, speak: bool) -> Result<()> {
+    let recent = recent::load(&paths::recent_path()?)?;
+    if recent.entries.is_empty() {
+        output.line("last", Status::Ok, "no announcements yet");
+        return Ok(());
+    }
+
+    let local_state = state::load(&paths::local_state_path()?).unwrap_or_default();
+    for announcement in recent.latest(count) {
+        output.value(
+            "last",
+            format!(
+                "{}  {}  {}",
+                history::format_local_time(announcement.timestamp),
+                announcement.event_kind,
+                announcement.message
+            ),
+            announcement,
+        );
+        if speak {
+            let settings = speech::resolve_settings(&announcement.event_kind, &local_state.speech);
+            speech::speak(&announcement.message, &settings)?;
+        }
+    }
+    Ok(())
+}
+
+fn show_history(
+    output: &mut Output,
+    agent: Option<Agent>,
+    project: Option<String>,
+    since: Option<&str>,
+    limit: usize,
+) -> Result<()> {
+    let since = since
+        .map(agitiser_notify::duration::parse)
+        .transpose()?
+        .map(|window| unix_now().saturating_sub(window.as_secs()));
+    let filter = history::HistoryFilter {
+        agent,
+        project,
+        since,
+    };
+    let entries = history::query(history::load(&paths::history_path()?)?, &filter, limit);
+
+    if entries.is_empty() {
+        output.line("history", Status::Ok, "no matching history entries");
+        return Ok(());
+    }
+    for entry in &entries {
+        output.value(
+            "history",
+            format!(
+                "{}  {}  {}  {}  {}",
+                entry.local_time(),
+                entry.display_name(),
+                entry.event_kind.as_deref().unwrap_or("-"),
+                entry.project.as_deref().unwrap_or("-"),
+                describe_delivery(entry)
+            ),
+            entry,
+        );
+    }
+    Ok(())
+}
+
+/// `announced: "<message>"`, or the delivery with the reason it was not announced.
+fn describe_delivery(entry: &HistoryEntry) -> String {
+    let detail = match entry.delivery {
+        Delivery::Announced => entry
+            .message
+            .as_deref()
+            .map(|message| format!("\"{message}\"")),
+        Delivery::Filtered | Delivery::Queued | Delivery::Failed => entry.reason.clone(),
+    };
+    match detail {
+        Some(detail) => format!("{}: {detail}", entry.delivery.label()),
+        None => entry.delivery.label().to_string(),
+    }
+}
+
+fn show_stats(output: &mut Output, since: Option<&str>, by: stats::GroupBy) -> Result<()> {
+    let since = since
+        .map(agitiser_notify::duration::parse)
+        .transpose()?
+        .map(|window| unix_now().saturating_sub(window.as_secs()));
+    let filter = history::HistoryFilter {
+        since,
+        ..history::HistoryFilter::default()
+    };
+    let entries = history::query(history::load(&paths::history_path()?)?, &filter, 0);
+    let report = stats::compute(&entries, by);
+
+    let summary = if entries.is_empty() {
+        "no matching history entries".to_string()
+    } else {
+        let window = since
+            .map(|since| format!(" since {}", history::format_local_time(since)))
+            .unwrap_or_default();
+        format!(
+            "{} events{window}: {}",
+            report.total.events,
+            describe_counts(&report.total)
+        )
+    };
+    output.value("stats", summary, &report);
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    output.line("stats", Status::Info, format!("by {}:", by.label()));
+    let width = report
+        .groups
+        .iter()
+        .map(|group| group.key.len())
+        .max()
+        .unwrap_or(0);
+    for group in &report.groups {
+        output.line(
+            "stats",
+            Status::Info,
+            format!(
+                "  {:<width$}  {} events: {}",
+                group.key,
+                group.counts.events,
+                describe_counts(&group.counts)
+            ),
+        );
+    }
+    let busiest = report
+        .busiest_projects
+        .iter()
+        .map(|project| format!("{} ({})", project.project, project.events))
+        .collect::<Vec<_>>();
+    if !busiest.is_empty() {
+        output.line(
+            "stats",
+            Status::Info,
+            format!("busiest projects: {}", busiest.join(", ")),
+        );
+    }
+    Ok(())
+}
+
+fn describe_counts(counts: &stats::Counts) -> String {
+    let mut parts = vec![
+        format!("{} announced", counts.announced),
+        format!("{} filtered", counts.filtered),
+    ];
+    if counts.queued > 0 {
+        parts.push(format!("{} queued", counts.queued));
+    }
+    if counts.failed > 0 {
+        parts.push(format!("{} failed", counts.failed));
+    }
+    if let Some(median) = counts.median_duration_secs {
+        parts.push(format!(
+            "median turn {}",
+            agitiser_notify::duration::humanize(Duration::from_secs(median))
+        ));
+    }
+    parts.join(", ")
+}
+
+/// A problem `doctor --fix` repairs by re-running a setup step.
+struct FixableProblem {
+    id: String,
+    status: Status,
+    message: String,
+}
+
+/// Reports `problems`, all repaired by the same setup step. With `fix` the step runs once
+/// and each problem is reported as fixed, or as an error when the step failed. Returns
+/// whether an error remains.
+fn report_fixable(
+    output: &mut Output,
+    problems: &[FixableProblem],
+    setup_command: &str,
+    fix: bool,
+    repair: impl FnOnce() -> Result<()>,
+) -> bool {
+    if problems.is_empty() {
+        return false;
+    }
+    if !fix {
+        let remediation = format!("run `agitiser-notify doctor --fix` or `{setup_command}`");
+        for problem in problems {
+            output.check(
+                &problem.id,
+                problem.status,
+                problem.message.as_str(),
+                Some(&remediation),
+            );
+        }
+        return problems
+            .iter()
+            .any(|problem| problem.status == Status::Error);
+    }
+
+    match repair() {
+        Ok(()) => {
+            for problem in problems {
+                output.check(
+                    &problem.id,
+                    Status::Ok,
+                    format!("{}; fixed by `{setup_command}`", problem.message),
+                    None,
+                );
+            }
+            false
+        }
+        Err(error) => {
+            let remediation = format!("run `{setup_command}` and check its error");
+            for problem in problems {
+                output.check(
+                    &problem.id,
+                    Status::Error,
+                    format!("{}; fix failed: {error:#}", problem.message),
+                    Some(&remediation),
+                );
+            }
+            true
+        }
+    }
+}
+
+/// Problems with the programs an installed integration runs, for `doctor`. Without a
+/// `wanted` executable only missing programs are reported.
+fn program_problems(
+    id: &str,
+    label: &str,
+    programs: &[String],
+    wanted: Option<&Path>,
+) -> Vec<FixableProblem> {
+    let mut problems = Vec::new();
+    for program in programs {
+        let link = match wanted {
+            Some(wanted) => relink::classify(program, wanted),
+            None if integrations::program_exists(program) => relink::Link::Current,
+            None => relink::Link::Missing,
+        };
+        let message = match (link, wanted) {
+            (relink::Link::Missing, _) => format!("{label} runs {program}, which does not exist"),
+            (relink::Link::Different, Some(wanted)) => {
+                format!("{label} runs {program} instead of {}", wanted.display())
+            }
+            _ => continue,
+        };
+        problems.push(FixableProblem {
+            id: format!("{id}.command"),
+            status: match link {
+                relink::Link::Missing => Status::Error,
+                _ => Status::Warn,
+            },
+            message,
+        });
+    }
+    problems
 }
 
-fn is_claude_subagent_event(event: &agitiser_notify::event::NormalizedEvent) -> bool {
-    event.agent == Agent::Claude
-        && event
-            .raw_payload
-            .get("hook_event_name")
-            .and_then(Value::as_str)
-            == Some("SubagentStop")
+/// Re-runs the setup of `agent` at `location` for `doctor --fix`, saving any state it
+/// changes.
+fn reinstall(
+    agent: SetupAgent,
+    location: &Path,
+    state_path: &Path,
+    local_state: &mut state::LocalState,
+    state_ok: bool,
+    wanted: Option<&Path>,
+) -> Result<()> {
+    let executable = wanted.context("no executable to install; see the command check")?;
+    if !state_ok && matches!(agent, SetupAgent::Codex | SetupAgent::Aider) {
+        bail!("the local state file does not parse");
+    }
+    let initial_state = local_state.clone();
+    relink::install(agent, location, local_state, executable)?;
+    if *local_state != initial_state {
+        state::save(state_path, local_state)?;
+    }
+    Ok(())
+}
+
+fn describe_hook(hook: &state::ClaudeHookConfig) -> String {
+    if hook.matcher == "*" {
+        hook.event.clone()
+    } else {
+        format!("{} ({})", hook.event, hook.matcher)
+    }
+}
+
+/// Every configured template with an id and a label for `doctor`.
+fn configured_templates(local_state: &state::LocalState) -> Vec<(String, String, &str)> {
+    let mut templates = Vec::new();
+    let targets = std::iter::once((None, true))
+        .chain(std::iter::once((None, false)))
+        .chain(
+            Agent::value_variants()
+                .iter()
+                .map(|agent| (Some(*agent), false)),
+        );
+    for (agent, digest) in targets {
+        if let Some(template) = template_slot(&local_state.templates, agent, digest) {
+            let label = template_target_label(agent, digest);
+            templates.push((
+                format!("template.{label}"),
+                format!("{label} template"),
+                template.as_str(),
+            ));
+        }
+    }
+    for rule in &local_state.notifications.rules {
+        if let Some(template) = &rule.template {
+            templates.push((
+                format!("rules.{}.template", rule.name),
+                format!("template of rule {}", rule.name),
+                template.as_str(),
+            ));
+        }
+    }
+    templates
+}
+
+fn doctor(output: &mut Output, dir: Option<&Path>, fix: bool, speak: bool) -> Result<()> {
+    let codex_path = paths::codex_config_path()?;
+    let state_path = paths::local_state_path()?;
+
+    let mut has_errors = false;
+
+    let (mut local_state, state_ok) = match state::load(&state_path) {
+        Ok(local_state) if state_path.exists() => {
+            output.check(
+                "state",
+                Status::Ok,
+                format!("state: {} is valid", state_path.display()),
+                None,
+            );
+            (local_state, true)
+        }
+        Ok(local_state) => {
+            output.check(
+                "state",
+                Status::Info,
+                format!(
+                    "state: {} does not exist yet; using defaults",
+                    state_path.display()
+                ),
+                None,
+            );
+            (local_state, true)
+        }
+        Err(error) => {
+            output.check(
+                "state",
+                Status::Error,
+                format!("state: {error:#}; using defaults"),
+                Some("fix the file by hand, or move it aside to start over from defaults"),
+            );
+            has_errors = true;
+            (state::LocalState::default(), false)
+        }
+    };
+
+    let templates = configured_templates(&local_state);
+    let mut invalid_templates = 0;
+    for (id, label, template) in &templates {
+        if let Err(error) = agitiser_notify::template::validate_template(template) {
+            invalid_templates += 1;
+            output.check(
+                id,
+                Status::Warn,
+                format!("templates: {label} is invalid ({error:#}); the default is spoken instead"),
+                Some(&format!("fix it in {}", state_path.display())),
+            );
+        }
+    }
+    if invalid_templates == 0 && !templates.is_empty() {
+        output.check(
+            "templates",
+            Status::Ok,
+            format!(
+                "templates: {} configured template(s) are valid",
+                templates.len()
+            ),
+            None,
+        );
+    }
+
+    let wanted = match relink::wanted_executable(&local_state.command) {
+        Ok(wanted) => Some(wanted),
+        Err(error) => {
+            output.check(
+                "command",
+                Status::Error,
+                format!("command: {error:#}"),
+                Some("put agitiser-notify on PATH, or run `agitiser-notify relink --absolute`"),
+            );
+            has_errors = true;
+            None
+        }
+    };
+
+    match speech::spd_say_path() {
+        Some(path) => output.check(
+            "speech-dispatcher",
+            Status::Ok,
+            format!("speech-dispatcher: found spd-say at {}", path.display()),
+            None,
+        ),
+        None => {
+            output.check(
+                "speech-dispatcher",
+                Status::Error,
+                "speech-dispatcher: spd-say not found in PATH",
+                Some("install speech-dispatcher and make sure spd-say is on PATH"),
+            );
+            has_errors = true;
+        }
+    }
+    if speak && speech::spd_say_path().is_some() {
+        let settings = speech::resolve_settings("doctor", &local_state.speech);
+        match speech::speak("agitiser-notify speech test", &settings) {
+            Ok(()) => output.check(
+                "speech-dispatcher.test",
+                Status::Ok,
+                "speech-dispatcher: test utterance sent; you should hear \"agitiser-notify speech test\"",
+                None,
+            ),
+            Err(error) => {
+                output.check(
+                    "speech-dispatcher.test",
+                    Status::Error,
+                    format!("speech-dispatcher: test utterance failed ({error:#})"),
+                    Some("check that the speech-dispatcher service runs and has an output module"),
+                );
+                has_errors = true;
+            }
+        }
+    }
+
+    match speech::earcon_player_path() {
+        Some(path) => output.check(
+            "earcons",
+            Status::Ok,
+            format!("earcons: found {}", path.display()),
+            None,
+        ),
+        None => output.check(
+            "earcons",
+            Status::Info,
+            "earcons: none of paplay, pw-play or aplay found; earcons are skipped",
+            Some("install pulseaudio-utils, pipewire or alsa-utils to play earcons"),
+        ),
+    }
+    for (kind, config) in &local_state.speech.kinds {
+        let missing_earcon = config
+            .earcon
+            .as_deref()
+            .map(str::trim)
+            .filter(|earcon| !earcon.is_empty())
+            .filter(|earcon| !Path::new(earcon).is_file());
+        if let Some(earcon) = missing_earcon {
+            output.check(
+                &format!("speech.{kind}.earcon"),
+                Status::Warn,
+                format!("speech ({kind}): earcon {earcon} does not exist"),
+                Some(&format!(
+                    "point it at an existing file with `agitiser-notify config speech set {kind} --earcon PATH`"
+                )),
+            );
+        }
+    }
+
+    if let Some(hours) = local_state.quiet.hours.as_deref() {
+        if let Err(error) = agitiser_notify::quiet::parse_quiet_hours(hours) {
+            output.check(
+                "quiet.hours",
+                Status::Warn,
+                format!("quiet: {error:#}; quiet hours are ignored"),
+                Some("fix them with `agitiser-notify config quiet set --hours HH:MM-HH:MM`"),
+            );
+        }
+    }
+    let held_back = agitiser_notify::quiet::suppression_reason(
+        &local_state.quiet,
+        unix_now(),
+        agitiser_notify::quiet::local_minute_of_day(),
+        || false,
+    );
+    if let Some(reason) = held_back {
+        output.check(
+            "quiet.active",
+            Status::Info,
+            format!("quiet: announcements are held back for digest ({reason})"),
+            None,
+        );
+    }
+    let pending = digest::load(&paths::pending_path()?)
+        .map(|queue| queue.events.len())
+        .unwrap_or(0);
+    if pending > 0 {
+        output.check(
+            "digest.pending",
+            Status::Info,
+            format!(
+                "digest: {pending} missed announcement(s) waiting; run `agitiser-notify digest`"
+            ),
+            None,
+        );
+    }
+
+    let claude_hooks = claude::managed_hooks(&local_state.claude);
+    let mut configured_scopes = Vec::new();
+    for scope in SettingsScope::ALL {
+        let wanted = wanted
+            .as_deref()
+            .map(|wanted| relink::scope_executable(scope, wanted));
+        let claude_path = paths::claude_scope_settings_path(scope, dir)?;
+        let id = format!("claude.{}", scope.label());
+        let inspection = claude::inspect(&claude_path, &claude_hooks)?;
+        if inspection.programs.is_empty() {
+            output.check(
+                &id,
+                Status::Info,
+                format!("claude ({}): managed hooks not configured", scope.label()),
+                None,
+            );
+            continue;
+        }
+        configured_scopes.push(scope.label());
+
+        let mut problems = Vec::new();
+        if !inspection.missing.is_empty() {
+            let missing = inspection
+                .missing
+                .iter()
+                .map(describe_hook)
+                .collect::<Vec<_>>();
+            problems.push(FixableProblem {
+                id: format!("{id}.hooks"),
+                status: Status::Warn,
+                message: format!(
+                    "claude ({}): managed hooks missing for {}",
+                    scope.label(),
+                    missing.join(", ")
+                ),
+            });
+        }
+        problems.extend(program_problems(
+            &id,
+            &format!("claude ({}): hook command", scope.label()),
+            &inspection.programs,
+            wanted,
+        ));
+        if problems.is_empty() {
+            output.check(
+                &id,
+                Status::Ok,
+                format!(
+                    "claude ({}): managed hooks configured in {}",
+                    scope.label(),
+                    claude_path.display()
+                ),
+                None,
+            );
+            continue;
+        }
+        let setup_command = format!(
+            "agitiser-notify setup --agents claude --scope {}",
+            scope.label()
+        );
+        has_errors |= report_fixable(output, &problems, &setup_command, fix, || {
+            reinstall(
+                SetupAgent::Claude,
+                &claude_path,
+                &state_path,
+                &mut local_state,
+                state_ok,
+                wanted,
+            )
+        });
+    }
+    if configured_scopes.len() > 1 {
+        output.check(
+            "claude.scopes",
+            Status::Warn,
+            format!(
+                "claude: managed hooks configured in several scopes ({}); events will be announced more than once",
+                configured_scopes.join(", ")
+            ),
+            Some("keep one scope and run `agitiser-notify remove --agents claude --scope SCOPE` for the others"),
+        );
+    }
+
+    let codex_problems = match codex::inspect(&codex_path)? {
+        codex::NotifyStatus::Missing | codex::NotifyStatus::Unmanaged(_) => {
+            output.check(
+                "codex",
+                Status::Info,
+                "codex: managed notify command not configured",
+                None,
+            );
+            Vec::new()
+        }
+        codex::NotifyStatus::Malformed => vec![FixableProblem {
+            id: "codex".to_string(),
+            status: Status::Warn,
+            message: format!(
+                "codex: notify in {} is not an array of strings; Codex cannot run it",
+                codex_path.display()
+            ),
+        }],
+        codex::NotifyStatus::Managed(notify) => {
+            let problems = program_problems(
+                "codex",
+                "codex: notify command",
+                &notify[..1],
+                wanted.as_deref(),
+            );
+            if problems.is_empty() {
+                output.check(
+                    "codex",
+                    Status::Ok,
+                    "codex: managed notify command configured",
+                    None,
+                );
+            }
+            problems
+        }
+    };
+    has_errors |= report_fixable(
+        output,
+        &codex_problems,
+        "agitiser-notify setup --agents codex",
+        fix,
+        || {
+            reinstall(
+                SetupAgent::Codex,
+                &codex_path,
+                &state_path,
+                &mut local_state,
+                state_ok,
+                wanted.as_deref(),
+            )
+        },
+    );
+
+    let aider_path = paths::aider_config_path()?;
+    match aider::is_configured(&aider_path)? {
+        true => {
+            let programs =
+                relink::installed_programs(SetupAgent::Aider, &aider_path, &local_state)?;
+            let problems = program_problems(
+                "aider",
+                "aider: notifications command",
+                &programs,
+                wanted.as_deref(),
+            );
+            if problems.is_empty() {
+                output.check(
+                    "aider",
+                    Status::Ok,
+                    "aider: managed notifications command configured",
+                    None,
+                );
+            }
+            has_errors |= report_fixable(
+                output,
+                &problems,
+                "agitiser-notify setup --agents aider",
+                fix,
+                || {
+                    reinstall(
+                        SetupAgent::Aider,
+                        &aider_path,
+                        &state_path,
+                        &mut local_state,
+                        state_ok,
+                        wanted.as_deref(),
+                    )
+                },
+            );
+        }
+        false => output.check(
+            "aider",
+            Status::Info,
+            "aider: managed notifications command not configured",
+            None,
+        ),
+    }
+
+    for scope in SettingsScope::ALL {
+        let wanted = wanted
+            .as_deref()
+            .map(|wanted| relink::scope_executable(scope, wanted));
+        let Some(settings_path) = paths::gemini_scope_settings_path(scope, dir)? else {
+            continue;
+        };
+        match gemini::is_configured(&settings_path)? {
+            true => {
+                let id = format!("gemini.{}", scope.label());
+                let problems = program_problems(
+                    &id,
+                    &format!("gemini ({}): hook command", scope.label()),
+                    &gemini::programs(&settings_path)?,
+                    wanted,
+                );
+                if problems.is_empty() {
+                    output.check(
+                        &id,
+                        Status::Ok,
+                        format!(
+                            "gemini ({}): managed hooks configured in {}",
+                            scope.label(),
+                            settings_path.display()
+                        ),
+                        None,
+                    );
+                }
+                let setup_command = format!(
+                    "agitiser-notify setup --agents gemini --scope {}",
+                    scope.label()
+                );
+                has_errors |= report_fixable(output, &problems, &setup_command, fix, || {
+                    reinstall(
+                        SetupAgent::Gemini,
+                        &settings_path,
+                        &state_path,
+                        &mut local_state,
+                        state_ok,
+                        wanted,
+                    )
+                });
+            }
+            false => output.check(
+                &format!("gemini.{}", scope.label()),
+                Status::Info,
+                format!("gemini ({}): managed hooks not configured", scope.label()),
+                None,
+            ),
+        }
+    }
+
+    for scope in SettingsScope::ALL {
+        let wanted = wanted
+            .as_deref()
+            .map(|wanted| relink::scope_executable(scope, wanted));
+        let Some(plugin_dir) = paths::opencode_plugin_dir(scope, dir)? else {
+            continue;
+        };
+        match opencode::is_configured(&plugin_dir)? {
+            true => {
+                let id = format!("opencode.{}", scope.label());
+                let programs = opencode::program(&plugin_dir)?
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                let problems = program_problems(
+                    &id,
+                    &format!("opencode ({}): plugin", scope.label()),
+                    &programs,
+                    wanted,
+                );
+                if problems.is_empty() {
+                    output.check(
+                        &id,
+                        Status::Ok,
+                        format!(
+                            "opencode ({}): managed plugin installed at {}",
+                            scope.label(),
+                            opencode::plugin_path(&plugin_dir).display()
+                        ),
+                        None,
+                    );
+                }
+                let setup_command = format!(
+                    "agitiser-notify setup --agents opencode --scope {}",
+                    scope.label()
+                );
+                has_errors |= report_fixable(output, &problems, &setup_command, fix, || {
+                    reinstall(
+                        SetupAgent::Opencode,
+                        &plugin_dir,
+                        &state_path,
+                        &mut local_state,
+                        state_ok,
+                        wanted,
+                    )
+                });
+            }
+            false => output.check(
+                &format!("opencode.{}", scope.label()),
+                Status::Info,
+                format!("opencode ({}): managed plugin not installed", scope.label()),
+                None,
+            ),
+        }
+    }
+
+    if let Some(pattern) = local_state.generic.terminal_pattern.as_deref() {
+        if regex::Regex::new(pattern).is_err() {
+            output.check(
+                "generic.terminal_pattern",
+                Status::Warn,
+                format!(
+                    "generic: terminal_pattern {pattern:?} is not a valid regex; it never matches"
+                ),
+                Some("fix it with `agitiser-notify config generic set --terminal-pattern REGEX`"),
+            );
+        }
+    }
+
+    for (name, definition) in &local_state.custom_agents {
+        let invalid_template = definition
+            .template
+            .iter()
+            .chain(definition.kind_templates.values())
+            .any(|template| agitiser_notify::template::validate_template(template).is_err());
+        let id = format!("custom-agent.{name}");
+        if definition.kinds.is_empty() {
+            output.check(
+                &id,
+                Status::Warn,
+                format!("custom agent {name}: no kinds mapped; nothing will be announced"),
+                Some("map event values to kinds under [custom_agents.NAME.kinds] in config.toml"),
+            );
+        } else if invalid_template {
+            output.check(
+                &id,
+                Status::Warn,
+                format!("custom agent {name}: template has invalid syntax"),
+                Some("fix the template under [custom_agents.NAME] in config.toml"),
+            );
+        } else {
+            output.check(
+                &id,
+                Status::Ok,
+                format!(
+                    "custom agent {name}: {} event kind(s) mapped from {}",
+                    definition.kinds.len(),
+                    definition.event_field
+                ),
+                None,
+            );
+        }
+    }
+
+    let unknown_custom_agents: BTreeSet<String> = paths::history_path()
+        .and_then(|path| history::load(&path))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|entry| entry.unknown_custom_agent)
+        .filter(|name| !local_state.custom_agents.contains_key(name))
+        .collect();
+    for name in unknown_custom_agents {
+        output.check(
+            &format!("custom-agent.{name}"),
+            Status::Warn,
+            format!(
+                "custom agent {name}: ingested but not declared; announced with the default name"
+            ),
+            Some(&format!(
+                "declare it under [custom_agents.{name}] in {}",
+                state_path.display()
+            )),
+        );
+    }
+
+    if has_errors {
+        bail!("doctor found critical issues");
+    }
+
+    Ok(())
+}
+
+fn dedup_agents(agents: Vec<SetupAgent>) -> Vec<SetupAgent> {
+    let mut ordered = Vec::new();
+    for agent in agents {
+        if !ordered.contains(&agent) {
+            ordered.push(agent);
+        }
+    }
+    ordered
 }
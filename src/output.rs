@@ -0,0 +1,236 @@
+//! Command output. In the default text format every line is printed as soon as a command
+//! produces it. With `--output json` the same lines are collected as [`Record`]s and
+//! printed as one JSON document when the command finishes, so provisioning scripts get a
+//! stable structure instead of prose.
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Status {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Info => "info",
+            Status::Warn => "warn",
+            Status::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Record {
+    /// Stable identifier, for example `speech-dispatcher` or `config.dedup.window_secs`.
+    pub id: String,
+    pub status: Status,
+    /// The line printed in the text format, without the `[status]` prefix.
+    pub message: String,
+    /// What to do about a `warn` or `error`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+    /// The value behind the message, such as a setting (`null` when unset) or a history
+    /// entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+/// The document printed by `--output json`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    /// False when the command failed or any record is an `error`.
+    pub ok: bool,
+    pub results: Vec<Record>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Where a command's results go; created by `main` from `--output` and passed to every
+/// command.
+#[derive(Debug, Default)]
+pub struct Output {
+    format: OutputFormat,
+    records: Vec<Record>,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            records: Vec::new(),
+        }
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+
+    /// A plain result line, such as `dedup window updated`.
+    pub fn line(&mut self, id: &str, status: Status, message: impl Into<String>) {
+        let message = message.into();
+        self.emit(
+            message.clone(),
+            Record {
+                id: id.to_string(),
+                status,
+                message,
+                remediation: None,
+                value: None,
+            },
+        );
+    }
+
+    /// A line describing `value`; JSON output carries the value itself.
+    pub fn value(&mut self, id: &str, message: impl Into<String>, value: impl Serialize) {
+        let message = message.into();
+        self.emit(
+            message.clone(),
+            Record {
+                id: id.to_string(),
+                status: Status::Ok,
+                message,
+                remediation: None,
+                value: Some(serde_json::to_value(value).unwrap_or(Value::Null)),
+            },
+        );
+    }
+
+    /// A `doctor` check, printed as `[status] message` in the text format.
+    pub fn check(
+        &mut self,
+        id: &str,
+        status: Status,
+        message: impl Into<String>,
+        remediation: Option<&str>,
+    ) {
+        let message = message.into();
+        self.emit(
+            format!("[{}] {message}", status.as_str()),
+            Record {
+                id: id.to_string(),
+                status,
+                message,
+                remediation: remediation.map(ToOwned::to_owned),
+                value: None,
+            },
+        );
+    }
+
+    /// A result that only belongs in the JSON document, for commands whose stdout is not
+    /// ours in the text format (`run` passes it to the wrapped command).
+    pub fn record(
+        &mut self,
+        id: &str,
+        status: Status,
+        message: impl Into<String>,
+        value: impl Serialize,
+    ) {
+        if !self.is_json() {
+            return;
+        }
+        self.records.push(Record {
+            id: id.to_string(),
+            status,
+            message: message.into(),
+            remediation: None,
+            value: Some(serde_json::to_value(value).unwrap_or(Value::Null)),
+        });
+    }
+
+    fn emit(&mut self, text: String, record: Record) {
+        if self.is_json() {
+            self.records.push(record);
+        } else {
+            println!("{text}");
+        }
+    }
+
+    /// Everything recorded so far, with the command's error if it failed.
+    pub fn report(&mut self, error: Option<&anyhow::Error>) -> Report {
+        let results = std::mem::take(&mut self.records);
+        Report {
+            ok: error.is_none() && results.iter().all(|record| record.status != Status::Error),
+            results,
+            error: error.map(|error| format!("{error:#}")),
+        }
+    }
+
+    /// Prints the JSON document; does nothing in the text format.
+    pub fn finish(&mut self, error: Option<&anyhow::Error>) {
+        if !self.is_json() {
+            return;
+        }
+        let report = self.report(error);
+        match serde_json::to_string_pretty(&report) {
+            Ok(raw) => println!("{raw}"),
+            Err(error) => eprintln!("error: failed to serialize output ({error})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn json_output_collects_records_into_the_report() {
+        let mut output = Output::new(OutputFormat::Json);
+        output.line("mute", Status::Ok, "muted");
+        output.record("run", Status::Error, "make exited with 2", 2);
+
+        let report = output.report(None);
+        assert!(!report.ok);
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(report.results[1].value, Some(json!(2)));
+        assert!(output.report(None).results.is_empty());
+
+        let mut text = Output::new(OutputFormat::Text);
+        text.record("run", Status::Ok, "make exited with 0", 0);
+        assert!(text.report(None).results.is_empty());
+    }
+
+    #[test]
+    fn records_serialize_without_empty_fields() {
+        let record = Record {
+            id: "config.dedup.window_secs".to_string(),
+            status: Status::Ok,
+            message: "window = 10s".to_string(),
+            remediation: None,
+            value: Some(json!(10)),
+        };
+        assert_eq!(
+            serde_json::to_value(&record).expect("json"),
+            json!({"id": "config.dedup.window_secs", "status": "ok", "message": "window = 10s", "value": 10})
+        );
+
+        let unset = Record {
+            value: Some(Value::Null),
+            ..record
+        };
+        assert_eq!(
+            serde_json::to_value(&unset).expect("json")["value"],
+            Value::Null
+        );
+    }
+}
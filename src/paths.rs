@@ -1,21 +1,210 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum SettingsScope {
+    User,
+    Project,
+    Local,
+}
+
+impl SettingsScope {
+    pub const ALL: [SettingsScope; 3] = [
+        SettingsScope::User,
+        SettingsScope::Project,
+        SettingsScope::Local,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingsScope::User => "user",
+            SettingsScope::Project => "project",
+            SettingsScope::Local => "local",
+        }
+    }
+}
 
 pub fn home_dir() -> Result<PathBuf> {
     dirs::home_dir().context("could not resolve home directory")
 }
 
+/// Roots every config location at `dir` for the rest of the process: Claude settings
+/// under `dir/claude`, Codex config under `dir/codex` and local state under
+/// `dir/agitiser-notify`. Environment variables are ignored while an override is set.
+pub fn set_config_dir_override(dir: PathBuf) {
+    let _ = CONFIG_DIR_OVERRIDE.set(dir);
+}
+
+pub fn config_dir_override() -> Option<&'static Path> {
+    CONFIG_DIR_OVERRIDE.get().map(PathBuf::as_path)
+}
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+pub fn claude_config_dir() -> Result<PathBuf> {
+    if let Some(dir) = config_dir_override() {
+        return Ok(dir.join("claude"));
+    }
+    match env_dir("CLAUDE_CONFIG_DIR") {
+        Some(dir) => Ok(dir),
+        None => Ok(home_dir()?.join(".claude")),
+    }
+}
+
+pub fn codex_home() -> Result<PathBuf> {
+    if let Some(dir) = config_dir_override() {
+        return Ok(dir.join("codex"));
+    }
+    match env_dir("CODEX_HOME") {
+        Some(dir) => Ok(dir),
+        None => Ok(home_dir()?.join(".codex")),
+    }
+}
+
+pub fn gemini_config_dir() -> Result<PathBuf> {
+    if let Some(dir) = config_dir_override() {
+        return Ok(dir.join("gemini"));
+    }
+    // Gemini CLI treats GEMINI_CLI_HOME as a replacement home directory.
+    match env_dir("GEMINI_CLI_HOME") {
+        Some(dir) => Ok(dir.join(".gemini")),
+        None => Ok(home_dir()?.join(".gemini")),
+    }
+}
+
+pub fn opencode_config_dir() -> Result<PathBuf> {
+    if let Some(dir) = config_dir_override() {
+        return Ok(dir.join("opencode"));
+    }
+    if let Some(dir) = env_dir("OPENCODE_CONFIG_DIR") {
+        return Ok(dir);
+    }
+    match env_dir("XDG_CONFIG_HOME").filter(|dir| dir.is_absolute()) {
+        Some(dir) => Ok(dir.join("opencode")),
+        None => Ok(home_dir()?.join(".config").join("opencode")),
+    }
+}
+
+pub fn local_state_dir() -> Result<PathBuf> {
+    if let Some(dir) = config_dir_override() {
+        return Ok(dir.join("agitiser-notify"));
+    }
+    // The XDG base directory spec requires relative values to be ignored.
+    match env_dir("XDG_CONFIG_HOME").filter(|dir| dir.is_absolute()) {
+        Some(dir) => Ok(dir.join("agitiser-notify")),
+        None => Ok(home_dir()?.join(".config").join("agitiser-notify")),
+    }
+}
+
 pub fn claude_settings_path() -> Result<PathBuf> {
-    Ok(home_dir()?.join(".claude").join("settings.json"))
+    Ok(claude_config_dir()?.join("settings.json"))
+}
+
+pub fn claude_scope_settings_path(scope: SettingsScope, dir: Option<&Path>) -> Result<PathBuf> {
+    match scope {
+        SettingsScope::User => claude_settings_path(),
+        SettingsScope::Project => Ok(project_root(dir)?.join(".claude").join("settings.json")),
+        SettingsScope::Local => Ok(project_root(dir)?
+            .join(".claude")
+            .join("settings.local.json")),
+    }
+}
+
+/// Gemini CLI reads user and project (`<repo>/.gemini/settings.json`) settings only;
+/// `None` is returned for the local scope.
+pub fn gemini_scope_settings_path(
+    scope: SettingsScope,
+    dir: Option<&Path>,
+) -> Result<Option<PathBuf>> {
+    match scope {
+        SettingsScope::User => Ok(Some(gemini_config_dir()?.join("settings.json"))),
+        SettingsScope::Project => Ok(Some(
+            project_root(dir)?.join(".gemini").join("settings.json"),
+        )),
+        SettingsScope::Local => Ok(None),
+    }
+}
+
+/// OpenCode has no local settings file, so only the user (global) and project plugin
+/// directories exist; `None` is returned for the local scope.
+pub fn opencode_plugin_dir(scope: SettingsScope, dir: Option<&Path>) -> Result<Option<PathBuf>> {
+    match scope {
+        SettingsScope::User => Ok(Some(opencode_config_dir()?.join("plugin"))),
+        SettingsScope::Project => Ok(Some(project_root(dir)?.join(".opencode").join("plugin"))),
+        SettingsScope::Local => Ok(None),
+    }
+}
+
+/// Resolves the project directory for project-scoped settings: `dir` when given,
+/// otherwise the enclosing git repository of the current directory, falling back to
+/// the current directory itself.
+pub fn project_root(dir: Option<&Path>) -> Result<PathBuf> {
+    if let Some(dir) = dir {
+        return Ok(dir.to_path_buf());
+    }
+
+    let cwd = std::env::current_dir().context("failed to resolve current directory")?;
+    Ok(cwd
+        .ancestors()
+        .find(|candidate| candidate.join(".git").exists())
+        .map(Path::to_path_buf)
+        .unwrap_or(cwd))
 }
 
 pub fn codex_config_path() -> Result<PathBuf> {
-    Ok(home_dir()?.join(".codex").join("config.toml"))
+    Ok(codex_home()?.join("config.toml"))
+}
+
+/// Codex session logs, `sessions/YYYY/MM/DD/rollout-*-<thread-id>.jsonl`.
+pub fn codex_sessions_dir() -> Result<PathBuf> {
+    Ok(codex_home()?.join("sessions"))
+}
+
+/// Aider's home-directory config; with `--config-dir` it lives at `DIR/aider/.aider.conf.yml`.
+pub fn aider_config_path() -> Result<PathBuf> {
+    match config_dir_override() {
+        Some(dir) => Ok(dir.join("aider").join(".aider.conf.yml")),
+        None => Ok(home_dir()?.join(".aider.conf.yml")),
+    }
 }
 
 pub fn local_state_path() -> Result<PathBuf> {
-    Ok(home_dir()?
-        .join(".config")
-        .join("agitiser-notify")
-        .join("config.toml"))
+    Ok(local_state_dir()?.join("config.toml"))
+}
+
+pub fn turns_path() -> Result<PathBuf> {
+    Ok(local_state_dir()?.join("turns.json"))
+}
+
+pub fn transcripts_path() -> Result<PathBuf> {
+    Ok(local_state_dir()?.join("transcripts.json"))
+}
+
+pub fn history_path() -> Result<PathBuf> {
+    Ok(local_state_dir()?.join("history.jsonl"))
+}
+
+pub fn recent_path() -> Result<PathBuf> {
+    Ok(local_state_dir()?.join("recent.json"))
+}
+
+pub fn pending_path() -> Result<PathBuf> {
+    Ok(local_state_dir()?.join("pending.json"))
+}
+
+pub fn throttle_path() -> Result<PathBuf> {
+    Ok(local_state_dir()?.join("throttle.json"))
+}
+
+pub fn dedup_path() -> Result<PathBuf> {
+    Ok(local_state_dir()?.join("dedup.json"))
 }
@@ -0,0 +1,422 @@
+//! The stages a normalized event goes through before it is spoken: enrich, filter, rules,
+//! dedup, render and dispatch. Each stage either passes the event on or records in the
+//! history entry why it stopped.
+
+use anyhow::Result;
+use serde_json::Value;
+use std::time::Duration;
+
+use agitiser_notify::digest::PendingEvent;
+use agitiser_notify::duration::humanize;
+use agitiser_notify::event::{NormalizedEvent, COMPLETION_EVENT_KINDS};
+use agitiser_notify::history::{Delivery, HistoryEntry};
+use agitiser_notify::recent::RecentAnnouncement;
+use agitiser_notify::rules::{RuleAction, Sink};
+use agitiser_notify::state::{CustomAgentConfig, LocalState, RuleConfig};
+use agitiser_notify::template::{render_announcement, resolve_event_kind_label, resolve_template};
+use agitiser_notify::transcript::TranscriptStats;
+use agitiser_notify::{dedup, digest, paths, quiet, recent, rules, speech, throttle, transcript};
+
+/// Runs `event` through every stage, recording the outcome in `entry`. `name` is the custom
+/// agent the event was normalized for.
+pub fn announce(
+    mut event: NormalizedEvent,
+    name: Option<&str>,
+    local_state: &LocalState,
+    verbose: bool,
+    entry: &mut HistoryEntry,
+) -> Result<()> {
+    entry.describe(&event);
+    enrich(&mut event, local_state, verbose, entry);
+
+    let definition = name.and_then(|name| local_state.custom_agents.get(name));
+    if let Some(reason) = below_min_duration(&event, definition, local_state) {
+        skip(entry, reason, verbose);
+        return Ok(());
+    }
+    let rule = match match_rule(&event, local_state, verbose) {
+        Ok(rule) => rule,
+        Err(reason) => {
+            entry.filter(reason);
+            return Ok(());
+        }
+    };
+    if let Some(reason) = duplicate(&event, local_state, verbose, entry.timestamp) {
+        skip(entry, reason, verbose);
+        return Ok(());
+    }
+
+    let message = render(&event, definition, rule.as_ref(), local_state);
+    entry.message = Some(message.clone());
+    dispatch(
+        &event,
+        definition,
+        rule.as_ref(),
+        message,
+        local_state,
+        verbose,
+        entry,
+    )
+}
+
+fn skip(entry: &mut HistoryEntry, reason: String, verbose: bool) {
+    if verbose {
+        eprintln!("ingest: {reason}, skipping");
+    }
+    entry.filter(reason);
+}
+
+/// Fills in the transcript metadata, the summary and a turn duration the payload lacked.
+fn enrich(
+    event: &mut NormalizedEvent,
+    local_state: &LocalState,
+    verbose: bool,
+    entry: &mut HistoryEntry,
+) {
+    if let Err(error) = paths::transcripts_path()
+        .and_then(|path| transcript::enrich_event(event, &path, entry.timestamp))
+    {
+        if verbose {
+            eprintln!("ingest: failed to read transcript ({error:#})");
+        }
+    }
+    event.summarize(local_state.summary.max_words);
+    event.duration = event.duration.or_else(|| {
+        event
+            .transcript
+            .as_ref()
+            .and_then(TranscriptStats::turn_duration)
+    });
+    entry.duration_secs = event.duration.map(|duration| duration.as_secs());
+}
+
+/// Why a completion was too short to announce, if it was. Failures and attention requests
+/// are announced however quickly they happen. A custom agent's own `min_duration` takes the
+/// place of the shared one.
+fn below_min_duration(
+    event: &NormalizedEvent,
+    definition: Option<&CustomAgentConfig>,
+    local_state: &LocalState,
+) -> Option<String> {
+    if !COMPLETION_EVENT_KINDS
+        .iter()
+        .any(|kind| kind.eq_ignore_ascii_case(&event.event_kind))
+    {
+        return None;
+    }
+    let min_duration = definition
+        .and_then(|definition| definition.min_duration)
+        .or_else(|| local_state.notifications.min_duration.resolve(event.agent))?;
+    let duration = event.duration?;
+    (duration.as_secs() < min_duration).then(|| {
+        format!(
+            "turn took {}, below minimum duration of {}",
+            humanize(duration),
+            humanize(Duration::from_secs(min_duration))
+        )
+    })
+}
+
+/// The first matching rule, or the reason a `drop` rule filtered the event.
+fn match_rule(
+    event: &NormalizedEvent,
+    local_state: &LocalState,
+    verbose: bool,
+) -> Result<Option<RuleConfig>, String> {
+    let Some(rule) = rules::evaluate(
+        &local_state.notifications,
+        event,
+        quiet::local_minute_of_day(),
+    ) else {
+        return Ok(None);
+    };
+    if rule.action == RuleAction::Drop {
+        let reason = rules::drop_reason(&rule);
+        if verbose {
+            if rule.name == rules::SUBAGENT_RULE_NAME {
+                eprintln!("ingest: claude subagent notification disabled, skipping");
+            } else {
+                eprintln!("ingest: {reason}, skipping");
+            }
+        }
+        return Err(reason);
+    }
+    if verbose {
+        eprintln!("ingest: matched rule {}", rule.name);
+    }
+    Ok(Some(rule))
+}
+
+/// Why `event` repeats one seen within the dedup window, if it does. Failing to read the
+/// dedup state lets the event through.
+fn duplicate(
+    event: &NormalizedEvent,
+    local_state: &LocalState,
+    verbose: bool,
+    now: u64,
+) -> Option<String> {
+    let key = dedup::key(event)?;
+    let window = local_state.dedup.window_secs;
+    match paths::dedup_path().and_then(|path| dedup::admit(&path, &key, window, now)) {
+        Ok(Some(first_seen)) => Some(format!(
+            "duplicate of {key} seen {} ago",
+            humanize(Duration::from_secs(now.saturating_sub(first_seen)))
+        )),
+        Ok(None) => {
+            if verbose && window > 0 {
+                eprintln!("ingest: dedup key {key} is new within {window}s");
+            }
+            None
+        }
+        Err(error) => {
+            if verbose {
+                eprintln!("ingest: failed to check for duplicates ({error:#}), continuing");
+            }
+            None
+        }
+    }
+}
+
+/// A matching rule's template replaces every configured one, and a custom agent's own
+/// templates and labels take the place of the shared `custom` overrides.
+fn render(
+    event: &NormalizedEvent,
+    definition: Option<&CustomAgentConfig>,
+    rule: Option<&RuleConfig>,
+    local_state: &LocalState,
+) -> String {
+    let template = rule
+        .and_then(|rule| rule.template.as_deref())
+        .or_else(|| definition.and_then(|definition| definition.template_for(&event.event_kind)))
+        .or_else(|| resolve_template(&local_state.templates, event.agent));
+    let label = definition.and_then(|definition| definition.label_for(&event.event_kind));
+    render_announcement(event, template, label, &local_state.event_kind_labels)
+}
+
+/// Sends `message` to the rule's sinks: queued for the digest when routed there or while
+/// speech is suppressed, spoken otherwise unless a rate limit holds it back.
+fn dispatch(
+    event: &NormalizedEvent,
+    definition: Option<&CustomAgentConfig>,
+    rule: Option<&RuleConfig>,
+    message: String,
+    local_state: &LocalState,
+    verbose: bool,
+    entry: &mut HistoryEntry,
+) -> Result<()> {
+    let route = rule
+        .and_then(|rule| rule.route_to.clone())
+        .unwrap_or_else(|| vec![Sink::Speech]);
+    let to_speech = route.contains(&Sink::Speech);
+    let suppressed = to_speech
+        .then(|| {
+            quiet::suppression_reason(
+                &local_state.quiet,
+                entry.timestamp,
+                quiet::local_minute_of_day(),
+                quiet::screen_locked,
+            )
+        })
+        .flatten();
+    let rule_name = rule.map(|rule| rule.name.as_str()).unwrap_or("-");
+    let queue_reason = suppressed.clone().or_else(|| {
+        route
+            .contains(&Sink::Digest)
+            .then(|| format!("routed to digest by rule {rule_name}"))
+    });
+    if let Some(reason) = &queue_reason {
+        let pending = PendingEvent {
+            timestamp: entry.timestamp,
+            agent: event.agent,
+            agent_name: event.display_name().to_string(),
+            event_kind: event.event_kind.clone(),
+            event_kind_label: definition
+                .and_then(|definition| definition.label_for(&event.event_kind))
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|| resolve_event_kind_label(event, &local_state.event_kind_labels)),
+            project: event.project_name.clone(),
+            message: message.clone(),
+            reason: reason.clone(),
+        };
+        digest::enqueue(&paths::pending_path()?, pending)?;
+        if verbose {
+            eprintln!("ingest: {reason}, queued for digest");
+        }
+    }
+    if !to_speech || suppressed.is_some() {
+        match queue_reason {
+            Some(reason) => {
+                entry.delivery = Delivery::Queued;
+                entry.reason = Some(reason);
+            }
+            None => entry.filter(format!("rule {rule_name} routes to no sink")),
+        }
+        return Ok(());
+    }
+
+    if let Some(reason) = throttled(event, local_state, verbose, entry.timestamp) {
+        skip(entry, reason, verbose);
+        return Ok(());
+    }
+    speak(event, rule, message, local_state, verbose, entry)
+}
+
+/// Why a rate limit holds `event` back, if one does. Failing to read the throttle state
+/// lets the event through.
+fn throttled(
+    event: &NormalizedEvent,
+    local_state: &LocalState,
+    verbose: bool,
+    now: u64,
+) -> Option<String> {
+    let throttled = paths::throttle_path().and_then(|path| {
+        throttle::admit(
+            &path,
+            &local_state.rate_limit,
+            event.display_name(),
+            &event.project_name,
+            &event.event_kind,
+            now,
+        )
+    });
+    match throttled {
+        Ok(reason) => reason,
+        Err(error) => {
+            if verbose {
+                eprintln!("ingest: failed to apply rate limits ({error:#}), announcing anyway");
+            }
+            None
+        }
+    }
+}
+
+fn speak(
+    event: &NormalizedEvent,
+    rule: Option<&RuleConfig>,
+    message: String,
+    local_state: &LocalState,
+    verbose: bool,
+    entry: &mut HistoryEntry,
+) -> Result<()> {
+    let mut settings = speech::resolve_settings(&event.event_kind, &local_state.speech);
+    if let Some(priority) = rule.and_then(|rule| rule.priority) {
+        settings.priority = Some(priority);
+    }
+    let announcement = RecentAnnouncement {
+        timestamp: entry.timestamp,
+        agent: event.agent,
+        event_kind: event.event_kind.clone(),
+        message: message.clone(),
+    };
+    if let Err(error) = paths::recent_path().and_then(|path| recent::record(&path, announcement)) {
+        if verbose {
+            eprintln!("ingest: failed to record recent announcement ({error:#})");
+        }
+    }
+    if let Err(error) = speech::speak(&message, &settings) {
+        entry.delivery = Delivery::Failed;
+        entry.reason = Some(format!("{error:#}"));
+        return Err(error);
+    }
+    entry.delivery = Delivery::Announced;
+    entry.reason = None;
+
+    if verbose {
+        let cwd = event
+            .cwd
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "<unknown>".to_string());
+        let payload_type = event
+            .raw_payload
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or("<none>");
+        let source_label = entry.source.as_deref().unwrap_or("<none>");
+        eprintln!(
+            "ingest: announced {} event for project {} (cwd: {}, type: {}, source: {})",
+            event.event_kind, event.project_name, cwd, payload_type, source_label
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agitiser_notify::agent::Agent;
+    use agitiser_notify::event::normalize;
+    use serde_json::json;
+
+    fn generic_event(kind: &str, secs: u64) -> NormalizedEvent {
+        let mut event = normalize(Agent::Generic, json!({"type": "done", "cwd": "/srv/api"}))
+            .expect("generic event");
+        event.event_kind = kind.to_string();
+        event.duration = Some(Duration::from_secs(secs));
+        event
+    }
+
+    fn min_duration_state(secs: u64) -> LocalState {
+        let mut local_state = LocalState::default();
+        local_state.notifications.min_duration.global = Some(secs);
+        local_state
+    }
+
+    #[test]
+    fn quick_failures_pass_the_minimum_duration() {
+        let local_state = min_duration_state(30);
+
+        assert!(below_min_duration(&generic_event("task-failed", 2), None, &local_state).is_none());
+        assert!(below_min_duration(&generic_event("error", 2), None, &local_state).is_none());
+        assert!(below_min_duration(&generic_event("task-end", 2), None, &local_state).is_some());
+    }
+
+    #[test]
+    fn only_completions_are_held_to_the_minimum_duration() {
+        let local_state = min_duration_state(30);
+
+        assert_eq!(
+            below_min_duration(&generic_event("task-end", 2), None, &local_state).as_deref(),
+            Some("turn took 2 seconds, below minimum duration of 30 seconds")
+        );
+        assert!(below_min_duration(&generic_event("plan-end", 29), None, &local_state).is_some());
+        assert!(below_min_duration(&generic_event("task-end", 30), None, &local_state).is_none());
+        assert!(below_min_duration(&generic_event("attention", 2), None, &local_state).is_none());
+        assert!(below_min_duration(&generic_event("build-done", 2), None, &local_state).is_none());
+    }
+
+    #[test]
+    fn custom_agents_use_their_own_minimum_duration_and_labels() {
+        let mut local_state = min_duration_state(30);
+        local_state.notifications.min_duration.agents.custom = Some(60);
+        local_state
+            .event_kind_labels
+            .agents
+            .custom
+            .insert("task-end".to_string(), "job".to_string());
+        let definition = CustomAgentConfig {
+            template: Some("{{event_kind}} done".to_string()),
+            event_kind_labels: [("task-end".to_string(), "build".to_string())].into(),
+            min_duration: Some(5),
+            ..CustomAgentConfig::default()
+        };
+        let mut event = generic_event("task-end", 10);
+        event.agent = Agent::Custom;
+
+        assert!(below_min_duration(&event, Some(&definition), &local_state).is_none());
+        assert!(below_min_duration(&event, None, &local_state).is_some());
+        assert_eq!(
+            render(&event, Some(&definition), None, &local_state),
+            "build done"
+        );
+        assert_eq!(
+            render(
+                &event,
+                Some(&CustomAgentConfig::default()),
+                None,
+                &local_state
+            ),
+            "Agent finished a job in the api project"
+        );
+    }
+}
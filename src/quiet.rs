@@ -0,0 +1,160 @@
+//! Decides whether an announcement is held back for `digest`: while muted, during quiet
+//! hours, or while the session is locked.
+
+use anyhow::{bail, Context, Result};
+use chrono::{Local, Timelike};
+use std::process::Command;
+
+use crate::state::QuietConfig;
+
+/// A local-time window in minutes after midnight; `end` before `start` wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl QuietHours {
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute_of_day)
+        } else {
+            minute_of_day >= self.start || minute_of_day < self.end
+        }
+    }
+}
+
+/// Parses `HH:MM-HH:MM`, for example `22:00-07:30`.
+pub fn parse_quiet_hours(value: &str) -> Result<QuietHours> {
+    let trimmed = value.trim();
+    let Some((start, end)) = trimmed.split_once('-') else {
+        bail!("invalid quiet hours {trimmed:?}; use HH:MM-HH:MM, for example 22:00-07:00");
+    };
+    let quiet_hours = QuietHours {
+        start: parse_clock(start).with_context(|| format!("invalid quiet hours {trimmed:?}"))?,
+        end: parse_clock(end).with_context(|| format!("invalid quiet hours {trimmed:?}"))?,
+    };
+    if quiet_hours.start == quiet_hours.end {
+        bail!("invalid quiet hours {trimmed:?}; start and end must differ");
+    }
+    Ok(quiet_hours)
+}
+
+fn parse_clock(value: &str) -> Result<u32> {
+    let value = value.trim();
+    let (hours, minutes) = value.split_once(':').unwrap_or((value, "0"));
+    let hours = hours.trim().parse::<u32>().ok().filter(|hours| *hours < 24);
+    let minutes = minutes
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|minutes| *minutes < 60);
+    match (hours, minutes) {
+        (Some(hours), Some(minutes)) => Ok(hours * 60 + minutes),
+        _ => bail!("{value:?} is not a time of day (HH:MM)"),
+    }
+}
+
+/// Why an announcement made at `now` (Unix seconds, `minute_of_day` local) is held back,
+/// if it is. `screen_locked` is only consulted when `when_locked` is enabled.
+pub fn suppression_reason(
+    config: &QuietConfig,
+    now: u64,
+    minute_of_day: u32,
+    screen_locked: impl FnOnce() -> bool,
+) -> Option<String> {
+    if config.muted && config.muted_until.is_none_or(|until| now < until) {
+        return Some("muted".to_string());
+    }
+    if let Some(hours) = config.hours.as_deref() {
+        if parse_quiet_hours(hours).is_ok_and(|quiet_hours| quiet_hours.contains(minute_of_day)) {
+            return Some(format!("quiet hours {}", hours.trim()));
+        }
+    }
+    if config.when_locked && screen_locked() {
+        return Some("screen locked".to_string());
+    }
+    None
+}
+
+/// Minutes after local midnight, as used by [`QuietHours::contains`].
+pub fn local_minute_of_day() -> u32 {
+    let now = Local::now();
+    now.hour() * 60 + now.minute()
+}
+
+/// Asks logind whether the current session is locked; any failure counts as unlocked.
+pub fn screen_locked() -> bool {
+    let session = std::env::var("XDG_SESSION_ID")
+        .ok()
+        .filter(|session| !session.is_empty())
+        .unwrap_or_else(|| "self".to_string());
+    Command::new("loginctl")
+        .args(["show-session", &session, "--property", "LockedHint"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .is_some_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "LockedHint=yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_wraps_quiet_hours() {
+        let night = parse_quiet_hours("22:00-07:30").expect("valid window");
+        assert_eq!(
+            night,
+            QuietHours {
+                start: 1320,
+                end: 450
+            }
+        );
+        assert!(night.contains(23 * 60));
+        assert!(night.contains(7 * 60 + 29));
+        assert!(!night.contains(7 * 60 + 30));
+        assert!(!night.contains(12 * 60));
+
+        let lunch = parse_quiet_hours("12-13").expect("bare hours");
+        assert!(lunch.contains(12 * 60 + 59));
+        assert!(!lunch.contains(13 * 60));
+
+        for invalid in ["22:00", "25:00-07:00", "08:00-08:00", "noon-1"] {
+            assert!(parse_quiet_hours(invalid).is_err(), "{invalid} should fail");
+        }
+    }
+
+    #[test]
+    fn mute_wins_until_it_expires() {
+        let config = QuietConfig {
+            muted: true,
+            muted_until: Some(1_000),
+            hours: Some("22:00-07:00".to_string()),
+            when_locked: true,
+        };
+
+        assert_eq!(
+            suppression_reason(&config, 999, 12 * 60, || false).as_deref(),
+            Some("muted")
+        );
+        assert_eq!(
+            suppression_reason(&config, 1_000, 23 * 60, || false).as_deref(),
+            Some("quiet hours 22:00-07:00")
+        );
+        assert_eq!(
+            suppression_reason(&config, 1_000, 12 * 60, || true).as_deref(),
+            Some("screen locked")
+        );
+        assert_eq!(suppression_reason(&config, 1_000, 12 * 60, || false), None);
+    }
+
+    #[test]
+    fn lock_state_is_ignored_unless_enabled() {
+        let config = QuietConfig::default();
+        assert_eq!(
+            suppression_reason(&config, 0, 0, || panic!("should not be checked")),
+            None
+        );
+    }
+}
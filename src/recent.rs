@@ -0,0 +1,101 @@
+//! Ring buffer of the last rendered announcements, replayed by `agitiser-notify last`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+
+use crate::agent::Agent;
+use crate::lockfile;
+
+pub const CAPACITY: usize = 10;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecentAnnouncements {
+    /// Oldest first.
+    #[serde(default)]
+    pub entries: VecDeque<RecentAnnouncement>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecentAnnouncement {
+    /// Unix timestamp in seconds.
+    pub timestamp: u64,
+    pub agent: Agent,
+    /// Normalized kind, used to pick the speech settings on replay.
+    pub event_kind: String,
+    pub message: String,
+}
+
+impl RecentAnnouncements {
+    pub fn push(&mut self, announcement: RecentAnnouncement) {
+        self.entries.push_back(announcement);
+        while self.entries.len() > CAPACITY {
+            self.entries.pop_front();
+        }
+    }
+
+    /// The newest `count` announcements, oldest first.
+    pub fn latest(&self, count: usize) -> impl Iterator<Item = &RecentAnnouncement> {
+        self.entries
+            .iter()
+            .skip(self.entries.len().saturating_sub(count))
+    }
+}
+
+pub fn load(path: &Path) -> Result<RecentAnnouncements> {
+    lockfile::read(path)
+}
+
+/// Appends `announcement` to the buffer at `path` under the file lock.
+pub fn record(path: &Path, announcement: RecentAnnouncement) -> Result<()> {
+    lockfile::update(path, |recent: &mut RecentAnnouncements| {
+        recent.push(announcement)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn announcement(timestamp: u64) -> RecentAnnouncement {
+        RecentAnnouncement {
+            timestamp,
+            agent: Agent::Codex,
+            event_kind: "task-end".to_string(),
+            message: format!("message {timestamp}"),
+        }
+    }
+
+    #[test]
+    fn keeps_only_the_newest_announcements() {
+        let mut recent = RecentAnnouncements::default();
+        for timestamp in 0..(CAPACITY as u64 + 3) {
+            recent.push(announcement(timestamp));
+        }
+
+        assert_eq!(recent.entries.len(), CAPACITY);
+        assert_eq!(recent.entries[0].timestamp, 3);
+        let latest = recent
+            .latest(2)
+            .map(|entry| entry.timestamp)
+            .collect::<Vec<_>>();
+        assert_eq!(latest, [11, 12]);
+        assert_eq!(recent.latest(50).count(), CAPACITY);
+    }
+
+    #[test]
+    fn record_persists_across_loads() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("recent.json");
+
+        record(&path, announcement(1)).expect("record");
+        record(&path, announcement(2)).expect("record");
+
+        let recent = load(&path).expect("load");
+        assert_eq!(
+            recent.latest(1).next().map(|entry| entry.message.as_str()),
+            Some("message 2")
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Managed integration entries bake in the path of the binary that ran `setup`, so moving
+//! or reinstalling agitiser-notify leaves them pointing at a missing or outdated binary.
+//! This module finds such entries and rewrites them by re-running the integration's setup.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::agent::SetupAgent;
+use crate::integrations::{self, aider, claude, codex, gemini, opencode};
+use crate::paths::{self, SettingsScope};
+use crate::state::{CommandConfig, LocalState};
+
+/// Installed instead of an absolute path in the project scope, and everywhere when
+/// [`CommandConfig::use_path`] is set.
+pub const COMMAND_NAME: &str = "agitiser-notify";
+
+pub const AGENTS: [SetupAgent; 5] = [
+    SetupAgent::Claude,
+    SetupAgent::Codex,
+    SetupAgent::Gemini,
+    SetupAgent::Opencode,
+    SetupAgent::Aider,
+];
+
+/// How an installed program compares with the one managed entries should run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Link {
+    Current,
+    Different,
+    Missing,
+}
+
+/// The executable managed entries should run: this binary, or [`COMMAND_NAME`] when it is
+/// to be looked up on `PATH`.
+pub fn wanted_executable(config: &CommandConfig) -> Result<PathBuf> {
+    if config.use_path {
+        which::which(COMMAND_NAME).with_context(|| {
+            format!("{COMMAND_NAME} is not on PATH; add it or relink with --absolute")
+        })?;
+        return Ok(PathBuf::from(COMMAND_NAME));
+    }
+    std::env::current_exe().context("failed to resolve current executable path")
+}
+
+/// What managed entries in `scope` should run given the `wanted` executable. Project
+/// settings are committed and shared, so they always run [`COMMAND_NAME`] from `PATH`.
+pub fn scope_executable(scope: SettingsScope, wanted: &Path) -> &Path {
+    match scope {
+        SettingsScope::Project => Path::new(COMMAND_NAME),
+        SettingsScope::User | SettingsScope::Local => wanted,
+    }
+}
+
+/// Paths are compared after resolving symlinks, so a hook that reaches this binary through
+/// a symlink is current; a bare name only matches the same bare name.
+pub fn classify(program: &str, wanted: &Path) -> Link {
+    if !integrations::program_exists(program) {
+        return Link::Missing;
+    }
+    let program = Path::new(program);
+    if program == wanted {
+        return Link::Current;
+    }
+    let is_name = |path: &Path| !path.to_string_lossy().contains('/');
+    if is_name(program) || is_name(wanted) {
+        return Link::Different;
+    }
+    match (fs::canonicalize(program), fs::canonicalize(wanted)) {
+        (Ok(program), Ok(wanted)) if program == wanted => Link::Current,
+        _ => Link::Different,
+    }
+}
+
+/// Where `agent` keeps its managed entries in `scope`; `None` when it has no such scope.
+pub fn location(
+    agent: SetupAgent,
+    scope: SettingsScope,
+    dir: Option<&Path>,
+) -> Result<Option<PathBuf>> {
+    match agent {
+        SetupAgent::Claude => paths::claude_scope_settings_path(scope, dir).map(Some),
+        SetupAgent::Codex if scope == SettingsScope::User => paths::codex_config_path().map(Some),
+        SetupAgent::Aider if scope == SettingsScope::User => paths::aider_config_path().map(Some),
+        SetupAgent::Codex | SetupAgent::Aider => Ok(None),
+        SetupAgent::Gemini => paths::gemini_scope_settings_path(scope, dir),
+        SetupAgent::Opencode => paths::opencode_plugin_dir(scope, dir),
+    }
+}
+
+/// The programs the managed entries of `agent` at `location` run; empty when none is
+/// installed.
+pub fn installed_programs(
+    agent: SetupAgent,
+    location: &Path,
+    local_state: &LocalState,
+) -> Result<Vec<String>> {
+    Ok(match agent {
+        SetupAgent::Claude => {
+            claude::inspect(location, &claude::managed_hooks(&local_state.claude))?.programs
+        }
+        SetupAgent::Codex => match codex::inspect(location)? {
+            codex::NotifyStatus::Managed(notify) => notify.into_iter().take(1).collect(),
+            _ => Vec::new(),
+        },
+        SetupAgent::Gemini => gemini::programs(location)?,
+        SetupAgent::Opencode => opencode::program(location)?.into_iter().collect(),
+        SetupAgent::Aider => aider::program(location)?.into_iter().collect(),
+    })
+}
+
+/// Re-runs the setup of `agent` at `location` with `executable`; returns whether anything
+/// changed.
+pub fn install(
+    agent: SetupAgent,
+    location: &Path,
+    local_state: &mut LocalState,
+    executable: &Path,
+) -> Result<bool> {
+    match agent {
+        SetupAgent::Claude => {
+            let hooks = claude::managed_hooks(&local_state.claude);
+            claude::setup(location, executable, &hooks)
+        }
+        SetupAgent::Codex => codex::setup(location, local_state, executable),
+        SetupAgent::Gemini => gemini::setup(location, executable),
+        SetupAgent::Opencode => opencode::setup(location, executable),
+        SetupAgent::Aider => aider::setup(location, local_state, executable),
+    }
+}
+
+/// Managed entries of one agent in one scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Installation {
+    pub agent: SetupAgent,
+    pub scope: SettingsScope,
+    pub location: PathBuf,
+    pub programs: Vec<String>,
+}
+
+impl Installation {
+    /// The worst link among the installed programs.
+    pub fn link(&self, wanted: &Path) -> Link {
+        self.programs
+            .iter()
+            .map(|program| classify(program, wanted))
+            .max()
+            .unwrap_or(Link::Current)
+    }
+}
+
+/// Every agent with managed entries in one of `scopes`.
+pub fn installations(
+    scopes: &[SettingsScope],
+    dir: Option<&Path>,
+    local_state: &LocalState,
+) -> Result<Vec<Installation>> {
+    let mut installations = Vec::new();
+    for agent in AGENTS {
+        for scope in scopes {
+            let Some(location) = location(agent, *scope, dir)? else {
+                continue;
+            };
+            let programs = installed_programs(agent, &location, local_state)?;
+            if !programs.is_empty() {
+                installations.push(Installation {
+                    agent,
+                    scope: *scope,
+                    location,
+                    programs,
+                });
+            }
+        }
+    }
+    Ok(installations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn classify_resolves_symlinks_and_missing_programs() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let binary = dir.path().join("agitiser-notify");
+        fs::write(&binary, "").expect("write binary");
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&binary, &link).expect("symlink");
+        let other = dir.path().join("other");
+        fs::write(&other, "").expect("write other");
+
+        let program = |path: &Path| path.to_string_lossy().to_string();
+        assert_eq!(classify(&program(&binary), &binary), Link::Current);
+        assert_eq!(classify(&program(&link), &binary), Link::Current);
+        assert_eq!(classify(&program(&other), &binary), Link::Different);
+        assert_eq!(
+            classify(&program(&dir.path().join("gone")), &binary),
+            Link::Missing
+        );
+        assert_eq!(classify("sh", Path::new(COMMAND_NAME)), Link::Different);
+    }
+
+    #[test]
+    fn project_scope_runs_the_command_name() {
+        let wanted = Path::new("/usr/local/bin/agitiser-notify");
+        assert_eq!(
+            scope_executable(SettingsScope::Project, wanted),
+            Path::new(COMMAND_NAME)
+        );
+        assert_eq!(scope_executable(SettingsScope::User, wanted), wanted);
+        assert_eq!(scope_executable(SettingsScope::Local, wanted), wanted);
+    }
+
+    #[test]
+    fn installations_report_the_worst_link() {
+        let installation = Installation {
+            agent: SetupAgent::Claude,
+            scope: SettingsScope::User,
+            location: PathBuf::from("/tmp/settings.json"),
+            programs: vec!["sh".to_string(), "/nonexistent/agitiser-notify".to_string()],
+        };
+        assert_eq!(installation.link(Path::new("sh")), Link::Missing);
+    }
+}
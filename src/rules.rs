@@ -0,0 +1,353 @@
+//! Ordered notification rules. The first rule whose conditions all match an event decides
+//! what happens to it: dropped, or announced with the rule's template, priority and sinks.
+//! Disabling Claude subagent notifications adds the built-in [`SUBAGENT_RULE_NAME`] rule
+//! in front of the configured ones.
+
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::agent::Agent;
+use crate::event::{select_field, NormalizedEvent};
+use crate::quiet::parse_quiet_hours;
+use crate::state::{NotificationConfig, RuleConfig};
+use crate::transcript::TranscriptStats;
+
+pub const SUBAGENT_RULE_NAME: &str = "claude-subagent";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleAction {
+    #[default]
+    Speak,
+    Drop,
+}
+
+/// Where an announced event goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum Sink {
+    /// Spoken right away (still subject to quiet hours and rate limits).
+    Speech,
+    /// Queued for the next `digest`.
+    Digest,
+}
+
+impl Sink {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Sink::Speech => "speech",
+            Sink::Digest => "digest",
+        }
+    }
+}
+
+/// The rule behind `config subagent set --enabled false`.
+pub fn subagent_rule() -> RuleConfig {
+    RuleConfig {
+        name: SUBAGENT_RULE_NAME.to_string(),
+        agent: Some(Agent::Claude),
+        fields: BTreeMap::from([("hook_event_name".to_string(), "SubagentStop".to_string())]),
+        action: RuleAction::Drop,
+        reason: Some("claude subagent notifications disabled".to_string()),
+        ..RuleConfig::default()
+    }
+}
+
+/// Built-in rules first, then the configured ones, in evaluation order.
+pub fn effective_rules(notifications: &NotificationConfig) -> Vec<RuleConfig> {
+    let mut rules = Vec::new();
+    if !notifications.claude_subagent {
+        rules.push(subagent_rule());
+    }
+    rules.extend(notifications.rules.iter().cloned());
+    rules
+}
+
+/// The first rule matching `event` at local `minute_of_day`, if any.
+pub fn evaluate(
+    notifications: &NotificationConfig,
+    event: &NormalizedEvent,
+    minute_of_day: u32,
+) -> Option<RuleConfig> {
+    effective_rules(notifications)
+        .into_iter()
+        .find(|rule| matches(rule, event, minute_of_day))
+}
+
+pub fn matches(rule: &RuleConfig, event: &NormalizedEvent, minute_of_day: u32) -> bool {
+    let duration = event.duration.map(|duration| duration.as_secs());
+    let session = event
+        .transcript
+        .as_ref()
+        .and_then(TranscriptStats::session_duration)
+        .map(|duration| duration.as_secs());
+    rule.agent.is_none_or(|agent| agent == event.agent)
+        && rule
+            .event_kind
+            .as_deref()
+            .is_none_or(|kind| kind.trim().eq_ignore_ascii_case(&event.event_kind))
+        && rule
+            .project
+            .as_deref()
+            .is_none_or(|pattern| glob_matches(pattern, &event.project_name, true))
+        && rule.cwd.as_deref().is_none_or(|pattern| {
+            event
+                .cwd
+                .as_ref()
+                .is_some_and(|cwd| glob_matches(pattern, &cwd.to_string_lossy(), false))
+        })
+        && rule.fields.iter().all(|(path, expected)| {
+            select_field(&event.raw_payload, path).is_some_and(|value| match value {
+                Value::String(value) => value == expected,
+                value => {
+                    serde_json::from_str::<Value>(expected).is_ok_and(|expected| expected == *value)
+                }
+            })
+        })
+        && rule.hours.as_deref().is_none_or(|hours| {
+            parse_quiet_hours(hours).is_ok_and(|window| window.contains(minute_of_day))
+        })
+        && rule
+            .min_duration_secs
+            .is_none_or(|min| duration.is_some_and(|duration| duration >= min))
+        && rule
+            .max_duration_secs
+            .is_none_or(|max| duration.is_some_and(|duration| duration <= max))
+        && rule
+            .min_session_secs
+            .is_none_or(|min| session.is_some_and(|session| session >= min))
+        && rule
+            .max_session_secs
+            .is_none_or(|max| session.is_some_and(|session| session <= max))
+}
+
+/// `*` and `?` stay within one path segment, `**` crosses segments.
+pub fn glob_matches(pattern: &str, value: &str, case_insensitive: bool) -> bool {
+    let mut regex = String::from(if case_insensitive { "(?i)^" } else { "^" });
+    let mut chars = pattern.trim().chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            ch => regex.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).is_ok_and(|regex| regex.is_match(value))
+}
+
+/// Why a `drop` rule filtered an event, as recorded in the history.
+pub fn drop_reason(rule: &RuleConfig) -> String {
+    rule.reason
+        .clone()
+        .unwrap_or_else(|| format!("dropped by rule {}", rule.name))
+}
+
+/// One-line summary for `config rules list`, for example
+/// `agent=codex project=infra-* -> speak, route_to=digest`.
+pub fn describe(rule: &RuleConfig) -> String {
+    let mut conditions = Vec::new();
+    if let Some(agent) = rule.agent {
+        conditions.push(format!("agent={}", agent_key(agent)));
+    }
+    if let Some(kind) = &rule.event_kind {
+        conditions.push(format!("kind={kind}"));
+    }
+    if let Some(project) = &rule.project {
+        conditions.push(format!("project={project}"));
+    }
+    if let Some(cwd) = &rule.cwd {
+        conditions.push(format!("cwd={cwd}"));
+    }
+    for (path, value) in &rule.fields {
+        conditions.push(format!("{path}={value}"));
+    }
+    if let Some(hours) = &rule.hours {
+        conditions.push(format!("hours={hours}"));
+    }
+    if let Some(min) = rule.min_duration_secs {
+        conditions.push(format!("duration>={min}s"));
+    }
+    if let Some(max) = rule.max_duration_secs {
+        conditions.push(format!("duration<={max}s"));
+    }
+    if let Some(min) = rule.min_session_secs {
+        conditions.push(format!("session>={min}s"));
+    }
+    if let Some(max) = rule.max_session_secs {
+        conditions.push(format!("session<={max}s"));
+    }
+    if conditions.is_empty() {
+        conditions.push("any event".to_string());
+    }
+
+    let mut actions = vec![match rule.action {
+        RuleAction::Speak => "speak".to_string(),
+        RuleAction::Drop => "drop".to_string(),
+    }];
+    if rule.action == RuleAction::Speak {
+        if let Some(sinks) = &rule.route_to {
+            let sinks = sinks.iter().map(|sink| sink.as_str()).collect::<Vec<_>>();
+            actions.push(format!("route_to={}", sinks.join(",")));
+        }
+        if let Some(priority) = rule.priority {
+            actions.push(format!("priority={}", priority.as_str()));
+        }
+        if let Some(template) = &rule.template {
+            actions.push(format!("template={template:?}"));
+        }
+    }
+    format!("{} -> {}", conditions.join(" "), actions.join(", "))
+}
+
+fn agent_key(agent: Agent) -> String {
+    agent
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::normalize;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn codex_event(cwd: &str) -> NormalizedEvent {
+        normalize(
+            Agent::Codex,
+            json!({"type": "agent-turn-complete", "cwd": cwd, "turn-id": "7"}),
+        )
+        .expect("codex event")
+    }
+
+    #[test]
+    fn globs_stay_within_segments_unless_doubled() {
+        assert!(glob_matches("infra-*", "infra-prod", false));
+        assert!(glob_matches("INFRA-?", "infra-1", true));
+        assert!(!glob_matches("/srv/*", "/srv/a/b", false));
+        assert!(glob_matches("/srv/**", "/srv/a/b", false));
+        assert!(!glob_matches("api", "api-gateway", false));
+    }
+
+    #[test]
+    fn all_conditions_must_match() {
+        let mut event = codex_event("/srv/work/infra-prod");
+        event.duration = Some(Duration::from_secs(90));
+        let rule = RuleConfig {
+            name: "slow-infra".to_string(),
+            agent: Some(Agent::Codex),
+            event_kind: Some("task-end".to_string()),
+            project: Some("infra-*".to_string()),
+            cwd: Some("/srv/**".to_string()),
+            fields: BTreeMap::from([("turn-id".to_string(), "7".to_string())]),
+            hours: Some("09:00-17:00".to_string()),
+            min_duration_secs: Some(60),
+            ..RuleConfig::default()
+        };
+
+        assert!(matches(&rule, &event, 10 * 60));
+        assert!(!matches(&rule, &event, 20 * 60));
+        event.duration = Some(Duration::from_secs(30));
+        assert!(!matches(&rule, &event, 10 * 60));
+        event.duration = None;
+        assert!(!matches(&rule, &event, 10 * 60));
+    }
+
+    #[test]
+    fn session_bounds_use_the_transcript() {
+        let mut event = normalize(
+            Agent::Claude,
+            json!({
+                "hook_event_name": "Stop",
+                "cwd": "/srv/api",
+                "transcript_path": concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/claude-transcript.jsonl")
+            }),
+        )
+        .expect("claude event");
+        let rule = RuleConfig {
+            name: "long-sessions".to_string(),
+            min_session_secs: Some(1),
+            ..RuleConfig::default()
+        };
+        assert!(!matches(&rule, &event, 0));
+
+        let stats = crate::transcript::read_stats(std::path::Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/claude-transcript.jsonl"
+        )))
+        .expect("read fixture transcript");
+        let session = stats
+            .session_duration()
+            .expect("session duration")
+            .as_secs();
+        event.transcript = Some(stats);
+        assert!(matches(&rule, &event, 0));
+        let rule = RuleConfig {
+            max_session_secs: Some(session.saturating_sub(1)),
+            ..rule
+        };
+        assert!(!matches(&rule, &event, 0));
+        assert_eq!(
+            describe(&rule),
+            format!(
+                "session>=1s session<={}s -> speak",
+                session.saturating_sub(1)
+            )
+        );
+    }
+
+    #[test]
+    fn first_matching_rule_wins_after_builtins() {
+        let subagent = normalize(
+            Agent::Claude,
+            json!({"hook_event_name": "SubagentStop", "cwd": "/srv/api"}),
+        )
+        .expect("claude event");
+        let speak_everything = RuleConfig {
+            name: "everything".to_string(),
+            ..RuleConfig::default()
+        };
+        let mut notifications = NotificationConfig {
+            rules: vec![speak_everything],
+            ..NotificationConfig::default()
+        };
+
+        let rule = evaluate(&notifications, &subagent, 0).expect("rule");
+        assert_eq!(rule.name, "everything");
+
+        notifications.claude_subagent = false;
+        let rule = evaluate(&notifications, &subagent, 0).expect("rule");
+        assert_eq!(rule.name, SUBAGENT_RULE_NAME);
+        assert_eq!(drop_reason(&rule), "claude subagent notifications disabled");
+        assert_eq!(
+            evaluate(&notifications, &codex_event("/srv/api"), 0).map(|rule| rule.name),
+            Some("everything".to_string())
+        );
+    }
+
+    #[test]
+    fn describes_conditions_and_actions() {
+        let rule = RuleConfig {
+            name: "ci".to_string(),
+            project: Some("infra-*".to_string()),
+            route_to: Some(vec![Sink::Digest]),
+            ..RuleConfig::default()
+        };
+        assert_eq!(describe(&rule), "project=infra-* -> speak, route_to=digest");
+        assert_eq!(
+            describe(&subagent_rule()),
+            "agent=claude hook_event_name=SubagentStop -> drop"
+        );
+    }
+}
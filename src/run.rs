@@ -0,0 +1,212 @@
+//! `agitiser-notify run -- <command>` wraps tools without a hook system: the child inherits
+//! stdin/stdout/stderr (stdout goes to stderr with `--output json`) and its exit is
+//! reported as a generic event.
+
+use anyhow::{bail, Context, Result};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::agent::Agent;
+use crate::event::{project_name_from_cwd, NormalizedEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunOutcome {
+    /// `None` when the child was killed by a signal.
+    pub exit_code: Option<i32>,
+    /// The signal that killed the child.
+    pub signal: Option<i32>,
+    pub duration: Duration,
+}
+
+impl RunOutcome {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// The status `run` exits with: the child's exit code, or 128 plus the signal number
+    /// like a shell reports it.
+    pub fn exit_status(&self) -> i32 {
+        match (self.exit_code, self.signal) {
+            (Some(code), _) => code,
+            (None, Some(signal)) => 128 + signal,
+            (None, None) => 1,
+        }
+    }
+}
+
+/// Runs `command`; with `stdout_to_stderr` its standard output is sent to our stderr so
+/// stdout carries only the JSON document.
+pub fn execute(command: &[String], stdout_to_stderr: bool) -> Result<RunOutcome> {
+    let Some((program, args)) = command.split_first() else {
+        bail!("no command given; usage: agitiser-notify run -- <command> [args...]");
+    };
+
+    let mut child = Command::new(program);
+    child.args(args);
+    if stdout_to_stderr {
+        child.stdout(Stdio::from(std::io::stderr()));
+    }
+    let started = Instant::now();
+    let status = child
+        .status()
+        .with_context(|| format!("failed to run {program}"))?;
+    Ok(RunOutcome {
+        exit_code: status.code(),
+        signal: signal(&status),
+        duration: started.elapsed(),
+    })
+}
+
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Joins `command` for display, single-quoting arguments that a shell would split.
+pub fn command_line(command: &[String]) -> String {
+    command
+        .iter()
+        .map(|arg| {
+            let plain = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || "-_./=:,+@%".contains(ch));
+            if plain {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', "'\"'\"'"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Builds the announced event: `task-end` for exit code 0, `task-failed` otherwise. The
+/// spoken name is `name` or the program's file name (`cargo`, `pytest`).
+pub fn event(
+    command: &[String],
+    outcome: &RunOutcome,
+    cwd: Option<PathBuf>,
+    name: Option<&str>,
+) -> NormalizedEvent {
+    let event_kind = if outcome.succeeded() {
+        "task-end"
+    } else {
+        "task-failed"
+    };
+    let line = command_line(command);
+    let agent_name = name
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(ToOwned::to_owned)
+        .or_else(|| {
+            command
+                .first()
+                .and_then(|program| Path::new(program).file_name())
+                .and_then(|program| program.to_str())
+                .map(ToOwned::to_owned)
+        });
+    let cwd_str = cwd.as_deref().and_then(Path::to_str);
+
+    NormalizedEvent {
+        agent: Agent::Generic,
+        event_kind: event_kind.to_string(),
+        project_name: project_name_from_cwd(cwd_str),
+        raw_payload: json!({
+            "type": event_kind,
+            "command": line,
+            "exit_code": outcome.exit_code,
+            "signal": outcome.signal,
+            "duration_secs": outcome.duration.as_secs(),
+            "cwd": cwd_str,
+        }),
+        cwd,
+        session_id: None,
+        turn_id: None,
+        last_message: None,
+        user_prompt: None,
+        summary: None,
+        transcript: None,
+        duration: Some(outcome.duration),
+        agent_name,
+        command: Some(line),
+        exit_code: outcome.exit_code.map(i64::from),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn quotes_arguments_a_shell_would_split() {
+        assert_eq!(
+            command_line(&args(&["cargo", "test", "--", "it's", "a b", ""])),
+            r#"cargo test -- 'it'"'"'s' 'a b' ''"#
+        );
+    }
+
+    #[test]
+    fn failed_runs_become_task_failed_events() {
+        let outcome = RunOutcome {
+            exit_code: Some(101),
+            signal: None,
+            duration: Duration::from_secs(95),
+        };
+        let event = event(
+            &args(&["/usr/bin/cargo", "test"]),
+            &outcome,
+            Some(PathBuf::from("/srv/backend")),
+            None,
+        );
+
+        assert_eq!(event.event_kind, "task-failed");
+        assert_eq!(event.display_name(), "cargo");
+        assert_eq!(event.project_name, "backend");
+        assert_eq!(event.command.as_deref(), Some("/usr/bin/cargo test"));
+        assert_eq!(event.exit_code, Some(101));
+        assert_eq!(event.duration, Some(Duration::from_secs(95)));
+        assert_eq!(event.raw_payload["exit_code"], 101);
+    }
+
+    #[test]
+    fn successful_runs_use_the_given_name() {
+        let outcome = RunOutcome {
+            exit_code: Some(0),
+            signal: None,
+            duration: Duration::from_secs(3),
+        };
+        let event = event(&args(&["make"]), &outcome, None, Some("Build"));
+
+        assert_eq!(event.event_kind, "task-end");
+        assert_eq!(event.display_name(), "Build");
+        assert_eq!(event.project_name, "unknown project");
+    }
+
+    #[test]
+    fn signals_count_as_failures() {
+        let outcome = RunOutcome {
+            exit_code: None,
+            signal: Some(15),
+            duration: Duration::ZERO,
+        };
+        assert!(!outcome.succeeded());
+        assert_eq!(outcome.exit_status(), 143);
+        assert_eq!(
+            event(&args(&["sleep", "60"]), &outcome, None, None).event_kind,
+            "task-failed"
+        );
+    }
+}
@@ -1,20 +1,132 @@
 use anyhow::{bail, Context, Result};
-use std::path::PathBuf;
-use std::process::Command;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
-use crate::event::NormalizedEvent;
-use crate::state::LocalState;
-use crate::template::render_announcement_message;
+use crate::event::FAILURE_EVENT_KINDS;
+use crate::state::SpeechConfig;
+
+/// Played before failure announcements unless a kind configures its own earcon.
+pub const FAILURE_EARCON: &str = "/usr/share/sounds/freedesktop/stereo/dialog-error.oga";
+
+/// Tried in order; the first one found in PATH plays earcons.
+const EARCON_PLAYERS: [&str; 3] = ["paplay", "pw-play", "aplay"];
+
+/// speech-dispatcher message priorities, passed to `spd-say --priority`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum SpeechPriority {
+    Important,
+    Message,
+    Text,
+    Notification,
+    Progress,
+}
+
+impl SpeechPriority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpeechPriority::Important => "important",
+            SpeechPriority::Message => "message",
+            SpeechPriority::Text => "text",
+            SpeechPriority::Notification => "notification",
+            SpeechPriority::Progress => "progress",
+        }
+    }
+}
+
+/// speech-dispatcher voice types, passed to `spd-say --voice-type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[value(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceType {
+    Male1,
+    Male2,
+    Male3,
+    Female1,
+    Female2,
+    Female3,
+    ChildMale,
+    ChildFemale,
+}
+
+impl VoiceType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VoiceType::Male1 => "male1",
+            VoiceType::Male2 => "male2",
+            VoiceType::Male3 => "male3",
+            VoiceType::Female1 => "female1",
+            VoiceType::Female2 => "female2",
+            VoiceType::Female3 => "female3",
+            VoiceType::ChildMale => "child_male",
+            VoiceType::ChildFemale => "child_female",
+        }
+    }
+}
+
+/// Speech settings for one event kind after applying the built-in failure defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpeechSettings {
+    pub priority: Option<SpeechPriority>,
+    pub voice: Option<VoiceType>,
+    pub earcon: Option<PathBuf>,
+}
+
+/// Failure kinds default to `important` priority and [`FAILURE_EARCON`]; configured
+/// values win field by field, and an empty earcon turns the sound off.
+pub fn resolve_settings(event_kind: &str, config: &SpeechConfig) -> SpeechSettings {
+    let key = event_kind.trim().to_ascii_lowercase();
+    let is_failure = FAILURE_EVENT_KINDS.contains(&key.as_str());
+    let configured = config.kinds.get(&key);
+
+    let earcon = match configured.and_then(|kind| kind.earcon.as_deref()) {
+        Some(earcon) => Some(earcon.trim())
+            .filter(|earcon| !earcon.is_empty())
+            .map(PathBuf::from),
+        None => is_failure.then(|| PathBuf::from(FAILURE_EARCON)),
+    };
+    SpeechSettings {
+        priority: configured
+            .and_then(|kind| kind.priority)
+            .or(is_failure.then_some(SpeechPriority::Important)),
+        voice: configured.and_then(|kind| kind.voice),
+        earcon,
+    }
+}
+
+pub fn spd_say_args(message: &str, settings: &SpeechSettings) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(priority) = settings.priority {
+        args.extend(["--priority".to_string(), priority.as_str().to_string()]);
+    }
+    if let Some(voice) = settings.voice {
+        args.extend(["--voice-type".to_string(), voice.as_str().to_string()]);
+    }
+    args.push(message.to_string());
+    args
+}
 
 pub fn spd_say_path() -> Option<PathBuf> {
     which::which("spd-say").ok()
 }
 
-pub fn speak(event: &NormalizedEvent, state: &LocalState) -> Result<()> {
+pub fn earcon_player_path() -> Option<PathBuf> {
+    EARCON_PLAYERS
+        .iter()
+        .find_map(|player| which::which(player).ok())
+}
+
+pub fn speak(message: &str, settings: &SpeechSettings) -> Result<()> {
     let spd_say = spd_say_path().context("spd-say not found in PATH; install speech-dispatcher")?;
-    let message = render_announcement_message(event, &state.templates, &state.event_kind_labels);
+    if let Some(earcon) = settings.earcon.as_deref() {
+        play_earcon(earcon);
+    }
+
     let status = Command::new(&spd_say)
-        .arg(message)
+        .args(spd_say_args(message, settings))
         .status()
         .with_context(|| format!("failed to execute {}", spd_say.display()))?;
     if !status.success() {
@@ -23,3 +135,83 @@ pub fn speak(event: &NormalizedEvent, state: &LocalState) -> Result<()> {
 
     Ok(())
 }
+
+/// Best effort: a missing sound file or player never blocks the spoken announcement.
+fn play_earcon(earcon: &Path) {
+    if !earcon.is_file() {
+        return;
+    }
+    if let Some(player) = earcon_player_path() {
+        let _ = Command::new(player)
+            .arg(earcon)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::state::KindSpeechConfig;
+
+    #[test]
+    fn failure_kinds_default_to_important_priority_and_earcon() {
+        let settings = resolve_settings("task-failed", &SpeechConfig::default());
+        assert_eq!(settings.priority, Some(SpeechPriority::Important));
+        assert_eq!(settings.earcon, Some(PathBuf::from(FAILURE_EARCON)));
+
+        assert_eq!(
+            resolve_settings("task-end", &SpeechConfig::default()),
+            SpeechSettings::default()
+        );
+    }
+
+    #[test]
+    fn configured_kind_overrides_defaults_field_by_field() {
+        let config = SpeechConfig {
+            kinds: BTreeMap::from([
+                (
+                    "error".to_string(),
+                    KindSpeechConfig {
+                        priority: None,
+                        voice: Some(VoiceType::Female2),
+                        earcon: Some(String::new()),
+                    },
+                ),
+                (
+                    "task-end".to_string(),
+                    KindSpeechConfig {
+                        priority: Some(SpeechPriority::Message),
+                        voice: None,
+                        earcon: Some("/tmp/done.oga".to_string()),
+                    },
+                ),
+            ]),
+        };
+
+        let error = resolve_settings("Error", &config);
+        assert_eq!(error.priority, Some(SpeechPriority::Important));
+        assert_eq!(error.voice, Some(VoiceType::Female2));
+        assert_eq!(error.earcon, None);
+        assert_eq!(
+            spd_say_args("Codex hit an error in api.", &error),
+            [
+                "--priority",
+                "important",
+                "--voice-type",
+                "female2",
+                "Codex hit an error in api."
+            ]
+        );
+
+        let done = resolve_settings("task-end", &config);
+        assert_eq!(done.earcon, Some(PathBuf::from("/tmp/done.oga")));
+        assert_eq!(
+            spd_say_args("done", &done),
+            ["--priority", "message", "done"]
+        );
+    }
+}
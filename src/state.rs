@@ -4,22 +4,193 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
+use crate::agent::Agent;
+use crate::rules::{RuleAction, Sink};
+use crate::speech::{SpeechPriority, VoiceType};
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct LocalState {
+    #[serde(default)]
+    pub claude: ClaudeState,
     #[serde(default)]
     pub codex: CodexState,
     #[serde(default)]
+    pub aider: AiderState,
+    #[serde(default)]
     pub templates: TemplateConfig,
     #[serde(default)]
     pub event_kind_labels: EventKindLabelsConfig,
     #[serde(default)]
     pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub summary: SummaryConfig,
+    #[serde(default)]
+    pub speech: SpeechConfig,
+    #[serde(default)]
+    pub quiet: QuietConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    #[serde(default)]
+    pub command: CommandConfig,
+    #[serde(default)]
+    pub generic: GenericConfig,
+    /// Custom agents keyed by the name passed to `ingest --agent custom --name`.
+    #[serde(default)]
+    pub custom_agents: BTreeMap<String, CustomAgentConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClaudeState {
+    /// Hooks managed on top of `integrations::claude::default_managed_hooks`; an entry with a
+    /// default's event and matcher overrides that default.
+    #[serde(default)]
+    pub hooks: Vec<ClaudeHookConfig>,
+    /// Default hooks removed with `config claude-hooks remove`.
+    #[serde(default)]
+    pub disabled_hooks: Vec<ClaudeHookKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClaudeHookConfig {
+    pub event: String,
+    #[serde(default = "default_matcher")]
+    pub matcher: String,
+    /// Normalized event kind announced for hook events without a built-in mapping.
+    #[serde(default)]
+    pub event_kind: Option<String>,
+}
+
+impl ClaudeHookConfig {
+    pub fn key(&self) -> ClaudeHookKey {
+        ClaudeHookKey {
+            event: self.event.clone(),
+            matcher: self.matcher.clone(),
+        }
+    }
+}
+
+/// The event and matcher that identify a managed Claude hook.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClaudeHookKey {
+    pub event: String,
+    #[serde(default = "default_matcher")]
+    pub matcher: String,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct CodexState {
     #[serde(default)]
     pub previous_notify: Option<Vec<String>>,
+    /// A `notify` value setup replaced that was not an array of strings, as TOML source, so
+    /// `remove` can put it back.
+    #[serde(default)]
+    pub previous_notify_raw: Option<String>,
+    /// Re-invoke `previous_notify` with the same payload after each ingest.
+    #[serde(default)]
+    pub chain_previous_notify: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AiderState {
+    #[serde(default)]
+    pub previous_notifications_command: Option<String>,
+    /// Setup turned `notifications` on, so remove turns it back off.
+    #[serde(default)]
+    pub enabled_notifications: bool,
+    /// Raw YAML value `notifications` had before setup turned it on; remove writes it back,
+    /// or drops the key when there was none.
+    #[serde(default)]
+    pub previous_notifications: Option<String>,
+}
+
+/// Field selectors and terminal-kind rules for `--agent generic` payloads. Unset selectors
+/// fall back to the built-in keys; without `terminal_kinds` or `terminal_pattern` a kind
+/// is terminal when it contains a word like `done`, `finished` or `completed`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GenericConfig {
+    #[serde(default)]
+    pub kind_field: Option<String>,
+    #[serde(default)]
+    pub cwd_field: Option<String>,
+    #[serde(default)]
+    pub project_field: Option<String>,
+    #[serde(default)]
+    pub agent_name_field: Option<String>,
+    #[serde(default)]
+    pub message_field: Option<String>,
+    /// Exact (case-insensitive) kinds that are announced.
+    #[serde(default)]
+    pub terminal_kinds: Vec<String>,
+    /// Regex matched against the kind; combined with `terminal_kinds` as either-or.
+    #[serde(default)]
+    pub terminal_pattern: Option<String>,
+}
+
+/// A tool without a built-in normalizer. Field paths are dotted (`event.type`, `items.0`)
+/// or JSON pointers (`/event/type`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CustomAgentConfig {
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default = "default_event_field")]
+    pub event_field: String,
+    #[serde(default = "default_cwd_field")]
+    pub cwd_field: String,
+    #[serde(default = "default_session_field")]
+    pub session_field: String,
+    #[serde(default = "default_message_field")]
+    pub message_field: String,
+    /// Raw event values mapped to normalized kinds; unmapped values are not announced.
+    #[serde(default)]
+    pub kinds: BTreeMap<String, String>,
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Templates per normalized kind, taking precedence over `template`.
+    #[serde(default)]
+    pub kind_templates: BTreeMap<String, String>,
+    /// Spoken `{{event_kind}}` per normalized kind, ahead of the configured labels.
+    #[serde(default)]
+    pub event_kind_labels: BTreeMap<String, String>,
+    /// Completions shorter than this many seconds are skipped, in place of the
+    /// `min_duration` configured for `custom`.
+    #[serde(default)]
+    pub min_duration: Option<u64>,
+}
+
+impl CustomAgentConfig {
+    pub fn template_for(&self, event_kind: &str) -> Option<&str> {
+        self.kind_templates
+            .get(event_kind)
+            .or(self.template.as_ref())
+            .map(String::as_str)
+            .filter(|template| !template.trim().is_empty())
+    }
+
+    pub fn label_for(&self, event_kind: &str) -> Option<&str> {
+        self.event_kind_labels
+            .get(&event_kind.trim().to_ascii_lowercase())
+            .map(|label| label.trim())
+            .filter(|label| !label.is_empty())
+    }
+}
+
+impl Default for CustomAgentConfig {
+    fn default() -> Self {
+        Self {
+            display_name: None,
+            event_field: default_event_field(),
+            cwd_field: default_cwd_field(),
+            session_field: default_session_field(),
+            message_field: default_message_field(),
+            kinds: BTreeMap::new(),
+            template: None,
+            kind_templates: BTreeMap::new(),
+            event_kind_labels: BTreeMap::new(),
+            min_duration: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -28,6 +199,9 @@ pub struct TemplateConfig {
     pub global: Option<String>,
     #[serde(default)]
     pub agents: AgentTemplateConfig,
+    /// Spoken by `digest`; `{{events}}` lists what was missed and `{{count}}` totals it.
+    #[serde(default)]
+    pub digest: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -37,7 +211,16 @@ pub struct AgentTemplateConfig {
     #[serde(default)]
     pub codex: Option<String>,
     #[serde(default)]
+    pub gemini: Option<String>,
+    #[serde(default)]
+    pub opencode: Option<String>,
+    #[serde(default)]
+    pub aider: Option<String>,
+    #[serde(default)]
     pub generic: Option<String>,
+    /// Used by custom agents without a `template` of their own.
+    #[serde(default)]
+    pub custom: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -55,27 +238,263 @@ pub struct AgentEventKindLabelsConfig {
     #[serde(default)]
     pub codex: BTreeMap<String, String>,
     #[serde(default)]
+    pub gemini: BTreeMap<String, String>,
+    #[serde(default)]
+    pub opencode: BTreeMap<String, String>,
+    #[serde(default)]
+    pub aider: BTreeMap<String, String>,
+    #[serde(default)]
     pub generic: BTreeMap<String, String>,
+    /// Used by custom agents for kinds missing from their own `event_kind_labels`.
+    #[serde(default)]
+    pub custom: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct NotificationConfig {
+    /// `false` adds the built-in `claude-subagent` drop rule in front of `rules`.
     #[serde(default = "default_true")]
     pub claude_subagent: bool,
+    #[serde(default)]
+    pub min_duration: MinDurationConfig,
+    /// Evaluated in order; the first matching rule decides. See [`crate::rules`].
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
 }
 
 impl Default for NotificationConfig {
     fn default() -> Self {
         Self {
             claude_subagent: true,
+            min_duration: MinDurationConfig::default(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Conditions are ANDed; unset ones match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RuleConfig {
+    pub name: String,
+    #[serde(default)]
+    pub agent: Option<Agent>,
+    #[serde(default)]
+    pub event_kind: Option<String>,
+    /// Glob over the project name (case-insensitive).
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Glob over the working directory; `**` crosses path segments.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Payload values keyed by dotted path or JSON pointer, compared as strings.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Local `HH:MM-HH:MM` window.
+    #[serde(default)]
+    pub hours: Option<String>,
+    #[serde(default)]
+    pub min_duration_secs: Option<u64>,
+    #[serde(default)]
+    pub max_duration_secs: Option<u64>,
+    /// Bounds on the session length read from the Claude transcript.
+    #[serde(default)]
+    pub min_session_secs: Option<u64>,
+    #[serde(default)]
+    pub max_session_secs: Option<u64>,
+    #[serde(default)]
+    pub action: RuleAction,
+    /// Sinks for announced events; unset means `speech` only.
+    #[serde(default)]
+    pub route_to: Option<Vec<Sink>>,
+    /// Replaces the configured templates for matching events.
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub priority: Option<SpeechPriority>,
+    /// Recorded in the history when the rule drops an event.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Turns shorter than these thresholds (in seconds) are not announced.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MinDurationConfig {
+    #[serde(default)]
+    pub global: Option<u64>,
+    #[serde(default)]
+    pub agents: AgentMinDurationConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AgentMinDurationConfig {
+    #[serde(default)]
+    pub claude: Option<u64>,
+    #[serde(default)]
+    pub codex: Option<u64>,
+    #[serde(default)]
+    pub gemini: Option<u64>,
+    #[serde(default)]
+    pub opencode: Option<u64>,
+    #[serde(default)]
+    pub aider: Option<u64>,
+    #[serde(default)]
+    pub generic: Option<u64>,
+    /// Used by custom agents without a `min_duration` of their own.
+    #[serde(default)]
+    pub custom: Option<u64>,
+}
+
+impl MinDurationConfig {
+    pub fn resolve(&self, agent: Agent) -> Option<u64> {
+        let agent_value = match agent {
+            Agent::Claude => self.agents.claude,
+            Agent::Codex => self.agents.codex,
+            Agent::Gemini => self.agents.gemini,
+            Agent::Opencode => self.agents.opencode,
+            Agent::Aider => self.agents.aider,
+            Agent::Generic => self.agents.generic,
+            Agent::Custom => self.agents.custom,
+        };
+        agent_value.or(self.global)
+    }
+}
+
+/// Announcements made while muted, during quiet hours or (with `when_locked`) while the
+/// session is locked are queued for `digest` instead of spoken.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuietConfig {
+    /// Local `HH:MM-HH:MM` window, wrapping past midnight when the end is earlier.
+    #[serde(default)]
+    pub hours: Option<String>,
+    #[serde(default)]
+    pub when_locked: bool,
+    #[serde(default)]
+    pub muted: bool,
+    /// Unix timestamp at which a timed `mute` ends; `None` mutes until `unmute`.
+    #[serde(default)]
+    pub muted_until: Option<u64>,
+}
+
+/// Caps how often announcements are spoken; enforced by [`crate::throttle`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// Announcements one agent may make in one project back to back; `None` disables the
+    /// token bucket.
+    #[serde(default)]
+    pub burst: Option<u32>,
+    /// Seconds for the bucket to win back one announcement.
+    #[serde(default = "default_refill_secs")]
+    pub refill_secs: u64,
+    /// Minimum seconds between announcements of the same kind from one agent in one project.
+    #[serde(default)]
+    pub cooldown_secs: Option<u64>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            burst: None,
+            refill_secs: default_refill_secs(),
+            cooldown_secs: None,
         }
     }
 }
 
+fn default_refill_secs() -> u64 {
+    crate::throttle::DEFAULT_REFILL_SECS
+}
+
+/// Repeats of an event already seen within the window are not announced again; see
+/// [`crate::dedup`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DedupConfig {
+    /// Seconds an event key is remembered; 0 turns deduplication off.
+    #[serde(default = "default_dedup_window_secs")]
+    pub window_secs: u64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: default_dedup_window_secs(),
+        }
+    }
+}
+
+fn default_dedup_window_secs() -> u64 {
+    crate::dedup::DEFAULT_WINDOW_SECS
+}
+
+/// How managed integrations invoke agitiser-notify; see [`crate::relink`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommandConfig {
+    /// Install the bare `agitiser-notify` name, looked up on `PATH` each time a hook runs,
+    /// instead of the absolute path of the binary that ran setup.
+    #[serde(default)]
+    pub use_path: bool,
+}
+
+/// spd-say options and earcons keyed by normalized event kind.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SpeechConfig {
+    #[serde(default)]
+    pub kinds: BTreeMap<String, KindSpeechConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KindSpeechConfig {
+    #[serde(default)]
+    pub priority: Option<SpeechPriority>,
+    #[serde(default)]
+    pub voice: Option<VoiceType>,
+    /// Sound file played before the announcement; empty disables the built-in earcon.
+    #[serde(default)]
+    pub earcon: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SummaryConfig {
+    /// Word cap for `{{summary}}` when the first sentence runs long.
+    #[serde(default = "default_summary_max_words")]
+    pub max_words: usize,
+}
+
+impl Default for SummaryConfig {
+    fn default() -> Self {
+        Self {
+            max_words: default_summary_max_words(),
+        }
+    }
+}
+
+fn default_summary_max_words() -> usize {
+    crate::summary::DEFAULT_MAX_WORDS
+}
+
 fn default_true() -> bool {
     true
 }
 
+fn default_matcher() -> String {
+    "*".to_string()
+}
+
+fn default_event_field() -> String {
+    "type".to_string()
+}
+
+fn default_cwd_field() -> String {
+    "cwd".to_string()
+}
+
+fn default_session_field() -> String {
+    "session_id".to_string()
+}
+
+fn default_message_field() -> String {
+    "message".to_string()
+}
+
 pub fn load(path: &Path) -> Result<LocalState> {
     if !path.exists() {
         return Ok(LocalState::default());
@@ -0,0 +1,246 @@
+//! Aggregates the event history for `agitiser-notify stats`.
+
+use chrono::{DateTime, Local, Timelike};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use crate::history::{Delivery, HistoryEntry};
+
+/// Projects listed under "busiest projects".
+pub const BUSIEST_PROJECTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum GroupBy {
+    Agent,
+    Project,
+    Kind,
+    /// Local hour of day the event arrived.
+    Hour,
+}
+
+impl GroupBy {
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupBy::Agent => "agent",
+            GroupBy::Project => "project",
+            GroupBy::Kind => "kind",
+            GroupBy::Hour => "hour",
+        }
+    }
+
+    fn key(self, entry: &HistoryEntry) -> String {
+        match self {
+            GroupBy::Agent => entry.display_name().to_string(),
+            GroupBy::Project => entry.project.clone().unwrap_or_else(|| "-".to_string()),
+            GroupBy::Kind => entry.event_kind.clone().unwrap_or_else(|| "-".to_string()),
+            GroupBy::Hour => i64::try_from(entry.timestamp)
+                .ok()
+                .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+                .map(|time| format!("{:02}:00", time.with_timezone(&Local).hour()))
+                .unwrap_or_else(|| "-".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Counts {
+    pub events: usize,
+    pub announced: usize,
+    pub filtered: usize,
+    pub queued: usize,
+    pub failed: usize,
+    /// Median of the recorded turn durations, in seconds.
+    pub median_duration_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Group {
+    pub key: String,
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectCount {
+    pub project: String,
+    pub events: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub by: GroupBy,
+    pub total: Counts,
+    /// Busiest first for agent, project and kind; in clock order for hour.
+    pub groups: Vec<Group>,
+    pub busiest_projects: Vec<ProjectCount>,
+}
+
+/// Only entries that normalized to an event are counted; payloads dropped before that,
+/// such as every `UserPromptSubmit` ("not a terminal event") or invalid JSON, carry no
+/// event kind and would otherwise inflate the busiest projects.
+pub fn compute(entries: &[HistoryEntry], by: GroupBy) -> Report {
+    let entries = entries
+        .iter()
+        .filter(|entry| entry.event_kind.is_some())
+        .collect::<Vec<_>>();
+    let mut grouped: BTreeMap<String, Vec<&HistoryEntry>> = BTreeMap::new();
+    for entry in &entries {
+        grouped.entry(by.key(entry)).or_default().push(entry);
+    }
+
+    let mut groups = grouped
+        .into_iter()
+        .map(|(key, entries)| Group {
+            key,
+            counts: count(entries),
+        })
+        .collect::<Vec<_>>();
+    if by != GroupBy::Hour {
+        groups.sort_by_key(|group| Reverse(group.counts.events));
+    }
+
+    let mut projects: BTreeMap<&str, usize> = BTreeMap::new();
+    for project in entries.iter().filter_map(|entry| entry.project.as_deref()) {
+        *projects.entry(project).or_default() += 1;
+    }
+    let mut busiest_projects = projects
+        .into_iter()
+        .map(|(project, events)| ProjectCount {
+            project: project.to_string(),
+            events,
+        })
+        .collect::<Vec<_>>();
+    busiest_projects.sort_by_key(|project| Reverse(project.events));
+    busiest_projects.truncate(BUSIEST_PROJECTS);
+
+    Report {
+        by,
+        total: count(entries.iter().copied()),
+        groups,
+        busiest_projects,
+    }
+}
+
+fn count<'a>(entries: impl IntoIterator<Item = &'a HistoryEntry>) -> Counts {
+    let mut counts = Counts::default();
+    let mut durations = Vec::new();
+    for entry in entries {
+        counts.events += 1;
+        match entry.delivery {
+            Delivery::Announced => counts.announced += 1,
+            Delivery::Filtered => counts.filtered += 1,
+            Delivery::Queued => counts.queued += 1,
+            Delivery::Failed => counts.failed += 1,
+        }
+        durations.extend(entry.duration_secs);
+    }
+    counts.median_duration_secs = median(&mut durations);
+    counts
+}
+
+fn median(values: &mut [u64]) -> Option<u64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[middle - 1] + values[middle]) / 2)
+    } else {
+        Some(values[middle])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+
+    fn entry(
+        agent: Agent,
+        project: &str,
+        delivery: Delivery,
+        duration: Option<u64>,
+    ) -> HistoryEntry {
+        HistoryEntry {
+            event_kind: Some("task-end".to_string()),
+            project: Some(project.to_string()),
+            delivery,
+            duration_secs: duration,
+            ..HistoryEntry::new(agent, None, 0)
+        }
+    }
+
+    #[test]
+    fn groups_by_project_busiest_first() {
+        let entries = vec![
+            entry(Agent::Codex, "infra", Delivery::Announced, Some(30)),
+            entry(Agent::Claude, "backend", Delivery::Announced, Some(60)),
+            entry(Agent::Claude, "backend", Delivery::Filtered, Some(10)),
+            entry(Agent::Claude, "backend", Delivery::Queued, None),
+            entry(Agent::Codex, "backend", Delivery::Failed, Some(300)),
+        ];
+
+        let report = compute(&entries, GroupBy::Project);
+        assert_eq!(report.total.events, 5);
+        assert_eq!(report.total.median_duration_secs, Some(45));
+        assert_eq!(report.groups[0].key, "backend");
+        assert_eq!(
+            report.groups[0].counts,
+            Counts {
+                events: 4,
+                announced: 1,
+                filtered: 1,
+                queued: 1,
+                failed: 1,
+                median_duration_secs: Some(60),
+            }
+        );
+        assert_eq!(
+            report.busiest_projects,
+            vec![
+                ProjectCount {
+                    project: "backend".to_string(),
+                    events: 4
+                },
+                ProjectCount {
+                    project: "infra".to_string(),
+                    events: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_payloads_that_were_not_terminal_events() {
+        let mut prompt = HistoryEntry::new(Agent::Claude, None, 0);
+        prompt.project = Some("api".to_string());
+        prompt.filter("not a terminal event (UserPromptSubmit)");
+        let entries = vec![prompt.clone(), prompt.clone(), prompt];
+
+        let report = compute(&entries, GroupBy::Project);
+        assert_eq!(report.total, Counts::default());
+        assert!(report.groups.is_empty());
+        assert!(report.busiest_projects.is_empty());
+    }
+
+    #[test]
+    fn groups_by_agent_name() {
+        let mut named = entry(Agent::Generic, "ci", Delivery::Announced, None);
+        named.agent_name = Some("Nightly".to_string());
+        let entries = vec![
+            named,
+            entry(Agent::Generic, "ci", Delivery::Announced, None),
+        ];
+
+        let keys = compute(&entries, GroupBy::Agent)
+            .groups
+            .into_iter()
+            .map(|group| group.key)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["Agent", "Nightly"]);
+    }
+}
@@ -0,0 +1,236 @@
+//! Turns an agent's final message into a short phrase that reads well aloud.
+
+pub const DEFAULT_MAX_WORDS: usize = 20;
+
+const CODE_EXTENSIONS: &[&str] = &[
+    "c", "cc", "cpp", "cs", "css", "go", "h", "hpp", "html", "java", "js", "json", "jsx", "kt",
+    "lock", "lua", "md", "php", "py", "rb", "rs", "sh", "sql", "swift", "toml", "ts", "tsx", "txt",
+    "yaml", "yml",
+];
+
+/// Strips markdown, code, URLs and file paths from `message` and keeps its first
+/// sentence, capped at `max_words` words. Returns `None` when nothing speakable is left.
+pub fn summarize(message: &str, max_words: usize) -> Option<String> {
+    let mut prose = Vec::new();
+    let mut in_code_block = false;
+    for line in message.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || trimmed.is_empty() {
+            if !prose.is_empty() && trimmed.is_empty() {
+                prose.push(String::new());
+            }
+            continue;
+        }
+        if let Some(cleaned) = clean_line(trimmed) {
+            prose.push(cleaned);
+        }
+    }
+
+    let paragraph = prose
+        .split(String::is_empty)
+        .map(|lines| lines.join(" "))
+        .find(|paragraph| !paragraph.trim().is_empty())?;
+
+    let words = paragraph
+        .split_whitespace()
+        .filter_map(speakable_word)
+        .collect::<Vec<_>>();
+    let sentence = first_sentence(&words, max_words.max(1));
+    let sentence = sentence.trim_end_matches([',', ';', ':', '-']).trim();
+    if sentence.is_empty() {
+        None
+    } else {
+        Some(sentence.to_string())
+    }
+}
+
+fn clean_line(line: &str) -> Option<String> {
+    if line.starts_with('#') || line.starts_with('|') {
+        return None;
+    }
+    if line
+        .chars()
+        .all(|ch| matches!(ch, '-' | '*' | '_' | '=' | ' '))
+    {
+        return None;
+    }
+
+    let mut line = line.trim_start_matches('>').trim_start();
+    for marker in ["- [ ] ", "- [x] ", "- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            line = rest;
+            break;
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        if let Some(rest) = line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") "))
+        {
+            line = rest;
+        }
+    }
+
+    Some(strip_inline_markdown(line))
+}
+
+fn strip_inline_markdown(line: &str) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(ch) = rest.chars().next() {
+        if ch == '`' {
+            let after = &rest[1..];
+            match after.find('`') {
+                Some(end) => {
+                    let code = &after[..end];
+                    if !looks_like_path(code) {
+                        output.push_str(code);
+                    }
+                    rest = &after[end + 1..];
+                }
+                None => rest = after,
+            }
+            continue;
+        }
+        if ch == '!' && rest[1..].starts_with('[') {
+            rest = &rest[1..];
+            continue;
+        }
+        if ch == '[' {
+            if let Some((text, remainder)) = split_link(rest) {
+                output.push_str(text);
+                rest = remainder;
+                continue;
+            }
+        }
+        if matches!(ch, '*' | '~') {
+            rest = &rest[1..];
+            continue;
+        }
+        if ch == '_' {
+            let prev_is_word = output.chars().last().is_some_and(char::is_alphanumeric);
+            let next_is_word = rest[1..].chars().next().is_some_and(char::is_alphanumeric);
+            if !(prev_is_word && next_is_word) {
+                rest = &rest[1..];
+                continue;
+            }
+        }
+        output.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    output
+}
+
+/// Splits `[text](target)` into the link text and whatever follows the link.
+fn split_link(rest: &str) -> Option<(&str, &str)> {
+    let close = rest.find(']')?;
+    let text = &rest[1..close];
+    let after = rest[close + 1..].strip_prefix('(')?;
+    let end = after.find(')')?;
+    Some((text, &after[end + 1..]))
+}
+
+fn speakable_word(word: &str) -> Option<&str> {
+    let core = word.trim_matches(|ch: char| {
+        matches!(
+            ch,
+            '(' | ')' | '"' | '\'' | ',' | '.' | ':' | ';' | '!' | '?'
+        )
+    });
+    if core.is_empty() {
+        return if word.chars().any(char::is_alphanumeric) {
+            None
+        } else {
+            Some(word).filter(|word| matches!(*word, "-" | "&"))
+        };
+    }
+    if looks_like_url(core) || looks_like_path(core) {
+        return None;
+    }
+    Some(word)
+}
+
+fn looks_like_url(word: &str) -> bool {
+    word.contains("://") || word.starts_with("www.")
+}
+
+fn looks_like_path(word: &str) -> bool {
+    if word.contains(char::is_whitespace) {
+        return false;
+    }
+    if word.contains('/') || word.contains('\\') || word.starts_with('~') {
+        return true;
+    }
+    match word.rsplit_once('.') {
+        Some((stem, extension)) => {
+            !stem.is_empty()
+                && !stem.ends_with('.')
+                && CODE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+        }
+        None => false,
+    }
+}
+
+fn first_sentence(words: &[&str], max_words: usize) -> String {
+    let mut sentence = Vec::new();
+    for word in words.iter().take(max_words) {
+        sentence.push(*word);
+        if word.ends_with(['.', '!', '?']) {
+            break;
+        }
+    }
+    sentence.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_first_sentence() {
+        assert_eq!(
+            summarize("All 42 tests pass. I also tidied the imports.", 20).as_deref(),
+            Some("All 42 tests pass.")
+        );
+    }
+
+    #[test]
+    fn strips_markdown_code_and_links() {
+        let message = "## Summary\n\n**Fixed** the `parse_config` bug in [the parser](https://example.com/x).\n\n```rust\nfn main() {}\n```\n";
+        assert_eq!(
+            summarize(message, 20).as_deref(),
+            Some("Fixed the parse_config bug in the parser.")
+        );
+    }
+
+    #[test]
+    fn drops_urls_and_file_paths() {
+        let message = "Updated src/event.rs and `Cargo.toml`, see https://example.com for details";
+        assert_eq!(
+            summarize(message, 20).as_deref(),
+            Some("Updated and see for details")
+        );
+    }
+
+    #[test]
+    fn caps_word_count() {
+        assert_eq!(
+            summarize("one two three four five six", 3).as_deref(),
+            Some("one two three")
+        );
+    }
+
+    #[test]
+    fn strips_list_markers_and_skips_code_only_messages() {
+        assert_eq!(
+            summarize("- Added retries\n- Fixed flakes", 20).as_deref(),
+            Some("Added retries Fixed flakes")
+        );
+        assert_eq!(summarize("```\nls -la\n```", 20), None);
+    }
+}
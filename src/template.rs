@@ -4,13 +4,19 @@ use serde::Serialize;
 use std::collections::BTreeMap;
 
 use crate::agent::Agent;
+use crate::duration;
 use crate::event::NormalizedEvent;
 use crate::state::{EventKindLabelsConfig, TemplateConfig};
+use crate::transcript::TranscriptStats;
 
 const TEMPLATE_NAME: &str = "announcement";
 const BUILTIN_DEFAULT_TEMPLATE: &str =
     "{{agent}} finished a {{event_kind}} in the {{project}} project";
 const BUILTIN_PLAN_END_TEMPLATE: &str = "{{agent}} finished planning in {{project}}.";
+const BUILTIN_ATTENTION_TEMPLATE: &str = "{{agent}} needs your attention in {{project}}.";
+const BUILTIN_TASK_FAILED_TEMPLATE: &str = "{{agent}} failed in {{project}}.";
+const BUILTIN_ERROR_TEMPLATE: &str = "{{agent}} hit an error in {{project}}.";
+const BUILTIN_DIGEST_TEMPLATE: &str = "While you were away: {{events}}.";
 
 #[derive(Debug, Serialize)]
 struct AnnouncementContext<'a> {
@@ -19,13 +25,37 @@ struct AnnouncementContext<'a> {
     event_kind_raw: &'a str,
     project: &'a str,
     cwd: &'a str,
+    session_id: &'a str,
+    turn_id: &'a str,
+    last_message: &'a str,
+    user_prompt: &'a str,
+    summary: &'a str,
+    first_prompt: &'a str,
+    tool_calls: Option<u64>,
+    files_edited: Option<usize>,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    session_duration: String,
+    duration: String,
+    command: &'a str,
+    exit_code: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct DigestContext<'a> {
+    events: &'a str,
+    count: usize,
 }
 
 fn agent_template(templates: &TemplateConfig, agent: Agent) -> Option<&str> {
     match agent {
         Agent::Claude => templates.agents.claude.as_deref(),
         Agent::Codex => templates.agents.codex.as_deref(),
+        Agent::Gemini => templates.agents.gemini.as_deref(),
+        Agent::Opencode => templates.agents.opencode.as_deref(),
+        Agent::Aider => templates.agents.aider.as_deref(),
         Agent::Generic => templates.agents.generic.as_deref(),
+        Agent::Custom => templates.agents.custom.as_deref(),
     }
 }
 
@@ -54,11 +84,15 @@ fn agent_event_kind_labels(
     match agent {
         Agent::Claude => &labels.agents.claude,
         Agent::Codex => &labels.agents.codex,
+        Agent::Gemini => &labels.agents.gemini,
+        Agent::Opencode => &labels.agents.opencode,
+        Agent::Aider => &labels.agents.aider,
         Agent::Generic => &labels.agents.generic,
+        Agent::Custom => &labels.agents.custom,
     }
 }
 
-fn resolve_event_kind_label(event: &NormalizedEvent, labels: &EventKindLabelsConfig) -> String {
+pub fn resolve_event_kind_label(event: &NormalizedEvent, labels: &EventKindLabelsConfig) -> String {
     let key = normalize_event_kind_key(&event.event_kind);
     let resolved = agent_event_kind_labels(labels, event.agent)
         .get(&key)
@@ -67,6 +101,8 @@ fn resolve_event_kind_label(event: &NormalizedEvent, labels: &EventKindLabelsCon
         .or(match key.as_str() {
             "task-end" => Some("task"),
             "plan-end" => Some("plan"),
+            "task-failed" => Some("failed task"),
+            "error" => Some("error"),
             _ => None,
         })
         .map(|label| label.trim())
@@ -87,23 +123,50 @@ fn context_from_event<'a>(
         .as_ref()
         .and_then(|path| path.to_str())
         .unwrap_or_default();
+    let transcript = event.transcript.as_ref();
 
     AnnouncementContext {
-        agent: event.agent.display_name(),
+        agent: event.display_name(),
         event_kind: event_kind_label,
         event_kind_raw: &event.event_kind,
         project: &event.project_name,
         cwd,
+        session_id: event.session_id.as_deref().unwrap_or_default(),
+        turn_id: event.turn_id.as_deref().unwrap_or_default(),
+        last_message: event.last_message.as_deref().unwrap_or_default(),
+        user_prompt: event.user_prompt.as_deref().unwrap_or_default(),
+        summary: event.summary.as_deref().unwrap_or_default(),
+        first_prompt: transcript
+            .and_then(|stats| stats.first_user_prompt.as_deref())
+            .unwrap_or_default(),
+        tool_calls: transcript.map(|stats| stats.tool_calls),
+        files_edited: transcript.map(|stats| stats.files_edited.len()),
+        input_tokens: transcript.map(|stats| stats.input_tokens),
+        output_tokens: transcript.map(|stats| stats.output_tokens),
+        session_duration: transcript
+            .and_then(TranscriptStats::session_duration)
+            .map(duration::humanize)
+            .unwrap_or_default(),
+        duration: event.duration.map(duration::humanize).unwrap_or_default(),
+        command: event.command.as_deref().unwrap_or_default(),
+        exit_code: event.exit_code,
     }
 }
 
+fn new_renderer() -> Handlebars<'static> {
+    let mut renderer = Handlebars::new();
+    renderer.set_strict_mode(false);
+    // Announcements are spoken, not HTML; escaping would read out entities like `&#x27;`.
+    renderer.register_escape_fn(handlebars::no_escape);
+    renderer
+}
+
 fn render_template(
     template: &str,
     event: &NormalizedEvent,
     event_kind_label: &str,
 ) -> Option<String> {
-    let mut renderer = Handlebars::new();
-    renderer.set_strict_mode(false);
+    let mut renderer = new_renderer();
 
     if renderer
         .register_template_string(TEMPLATE_NAME, template)
@@ -118,9 +181,29 @@ fn render_template(
         .filter(|rendered| !rendered.trim().is_empty())
 }
 
+/// Renders the `digest` summary; falls back to the built-in template when `template` is
+/// unset, invalid or renders to nothing.
+pub fn render_digest(template: Option<&str>, events: &str, count: usize) -> String {
+    let context = DigestContext { events, count };
+    let render = |template: &str| {
+        let mut renderer = new_renderer();
+        renderer
+            .register_template_string(TEMPLATE_NAME, template)
+            .ok()?;
+        renderer
+            .render(TEMPLATE_NAME, &context)
+            .ok()
+            .filter(|rendered| !rendered.trim().is_empty())
+    };
+
+    normalize_template(template)
+        .and_then(render)
+        .or_else(|| render(BUILTIN_DIGEST_TEMPLATE))
+        .unwrap_or_else(|| format!("While you were away: {events}."))
+}
+
 pub fn validate_template(template: &str) -> Result<()> {
-    let mut renderer = Handlebars::new();
-    renderer.set_strict_mode(false);
+    let mut renderer = new_renderer();
     renderer
         .register_template_string(TEMPLATE_NAME, template)
         .context("invalid template syntax")?;
@@ -137,32 +220,60 @@ pub fn render_announcement_message(
     templates: &TemplateConfig,
     event_kind_labels: &EventKindLabelsConfig,
 ) -> String {
-    let event_kind_label = resolve_event_kind_label(event, event_kind_labels);
-    let is_plan_end = normalize_event_kind_key(&event.event_kind) == "plan-end";
-    let builtin_template = if is_plan_end {
-        BUILTIN_PLAN_END_TEMPLATE
-    } else {
-        BUILTIN_DEFAULT_TEMPLATE
+    render_announcement(
+        event,
+        resolve_template(templates, event.agent),
+        None,
+        event_kind_labels,
+    )
+}
+
+/// Renders `template`, or the built-in template for the event kind when it is unset or
+/// fails to render. `label`, when set, is spoken in place of the configured event kind label.
+pub fn render_announcement(
+    event: &NormalizedEvent,
+    template: Option<&str>,
+    label: Option<&str>,
+    event_kind_labels: &EventKindLabelsConfig,
+) -> String {
+    let event_kind_label = label
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| resolve_event_kind_label(event, event_kind_labels));
+    let event_kind_key = normalize_event_kind_key(&event.event_kind);
+    let builtin_template = match event_kind_key.as_str() {
+        "plan-end" => BUILTIN_PLAN_END_TEMPLATE,
+        "attention" => BUILTIN_ATTENTION_TEMPLATE,
+        "task-failed" => BUILTIN_TASK_FAILED_TEMPLATE,
+        "error" => BUILTIN_ERROR_TEMPLATE,
+        _ => BUILTIN_DEFAULT_TEMPLATE,
     };
     let default_message = render_template(builtin_template, event, &event_kind_label)
-        .unwrap_or_else(|| {
-            if is_plan_end {
-                format!(
-                    "{} finished planning in {}.",
-                    event.agent.display_name(),
-                    event.project_name
-                )
-            } else {
-                format!(
-                    "{} finished a {} in the {} project",
-                    event.agent.display_name(),
-                    event_kind_label,
-                    event.project_name
-                )
-            }
+        .unwrap_or_else(|| match event_kind_key.as_str() {
+            "plan-end" => format!(
+                "{} finished planning in {}.",
+                event.display_name(),
+                event.project_name
+            ),
+            "attention" => format!(
+                "{} needs your attention in {}.",
+                event.display_name(),
+                event.project_name
+            ),
+            "task-failed" => format!("{} failed in {}.", event.display_name(), event.project_name),
+            "error" => format!(
+                "{} hit an error in {}.",
+                event.display_name(),
+                event.project_name
+            ),
+            _ => format!(
+                "{} finished a {} in the {} project",
+                event.display_name(),
+                event_kind_label,
+                event.project_name
+            ),
         });
 
-    match resolve_template(templates, event.agent) {
+    match normalize_template(template) {
         Some(template) => {
             render_template(template, event, &event_kind_label).unwrap_or(default_message)
         }
@@ -212,6 +323,7 @@ mod tests {
                 codex: Some("agent".to_string()),
                 ..AgentTemplateConfig::default()
             },
+            digest: None,
         };
 
         assert_eq!(resolve_template(&templates, Agent::Codex), Some("agent"));
@@ -233,6 +345,7 @@ mod tests {
                 "{{agent}} {{event_kind}} {{event_kind_raw}} {{project}} {{cwd}}".to_string(),
             ),
             agents: AgentTemplateConfig::default(),
+            digest: None,
         };
 
         let message = render_announcement_message(&event, &templates, &empty_labels());
@@ -242,12 +355,148 @@ mod tests {
         );
     }
 
+    #[test]
+    fn render_exposes_turn_metadata_without_html_escaping() {
+        let event = normalize(
+            Agent::Codex,
+            json!({
+                "type": "agent-turn-complete",
+                "cwd": "/home/user/Projects/backend",
+                "thread-id": "thread-1",
+                "turn-id": "turn-2",
+                "input-messages": ["fix the build"],
+                "last-assistant-message": "It's fixed & green"
+            }),
+        )
+        .expect("expected codex event");
+        let templates = TemplateConfig {
+            global: Some(
+                "{{session_id}} {{turn_id}} {{user_prompt}}: {{last_message}}".to_string(),
+            ),
+            agents: AgentTemplateConfig::default(),
+            digest: None,
+        };
+
+        let message = render_announcement_message(&event, &templates, &empty_labels());
+        assert_eq!(message, "thread-1 turn-2 fix the build: It's fixed & green");
+    }
+
+    #[test]
+    fn render_exposes_command_and_exit_code() {
+        let templates = TemplateConfig {
+            global: Some(
+                "{{agent}} {{#if exit_code}}failed with exit code {{exit_code}}{{else}}finished{{/if}}: {{command}}"
+                    .to_string(),
+            ),
+            agents: AgentTemplateConfig::default(),
+            digest: None,
+        };
+        let event = |exit_code: i64| {
+            normalize(
+                Agent::Generic,
+                json!({"type": "done", "command": "cargo test", "exit_code": exit_code}),
+            )
+            .expect("expected generic event")
+        };
+
+        assert_eq!(
+            render_announcement_message(&event(101), &templates, &empty_labels()),
+            "Agent failed with exit code 101: cargo test"
+        );
+        assert_eq!(
+            render_announcement_message(&event(0), &templates, &empty_labels()),
+            "Agent finished: cargo test"
+        );
+    }
+
+    #[test]
+    fn render_announcement_uses_the_given_template() {
+        let event = normalize(Agent::Generic, json!({"type": "done", "cwd": "/srv/api"}))
+            .expect("expected generic event");
+
+        assert_eq!(
+            render_announcement(
+                &event,
+                Some("{{agent}} in {{project}}"),
+                None,
+                &empty_labels()
+            ),
+            "Agent in api"
+        );
+        assert_eq!(
+            render_announcement(&event, Some("  "), None, &empty_labels()),
+            render_announcement_message(&event, &TemplateConfig::default(), &empty_labels())
+        );
+        assert_eq!(
+            render_announcement(
+                &event,
+                Some("{{event_kind}}"),
+                Some("build"),
+                &empty_labels()
+            ),
+            "build"
+        );
+    }
+
+    #[test]
+    fn render_exposes_summary_of_last_message() {
+        let mut event = normalize(
+            Agent::Codex,
+            json!({
+                "type": "agent-turn-complete",
+                "cwd": "/home/user/Projects/backend",
+                "last-assistant-message": "**All 42 tests pass.** I updated `src/lib.rs` too."
+            }),
+        )
+        .expect("expected codex event");
+        event.summarize(20);
+        let templates = TemplateConfig {
+            global: Some("{{agent}} finished: {{summary}}".to_string()),
+            agents: AgentTemplateConfig::default(),
+            digest: None,
+        };
+
+        let message = render_announcement_message(&event, &templates, &empty_labels());
+        assert_eq!(message, "Codex finished: All 42 tests pass.");
+    }
+
+    #[test]
+    fn render_exposes_transcript_metadata() {
+        let mut event = normalize(
+            Agent::Claude,
+            json!({
+                "hook_event_name": "Stop",
+                "cwd": "/home/user/Projects/backend",
+                "transcript_path": concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/claude-transcript.jsonl")
+            }),
+        )
+        .expect("expected claude event");
+        let cache = tempfile::tempdir().expect("tempdir");
+        crate::transcript::enrich_event(&mut event, &cache.path().join("transcripts.json"), 0)
+            .expect("read fixture transcript");
+        let templates = TemplateConfig {
+            global: Some(
+                "{{tool_calls}} tools, {{files_edited}} files, {{input_tokens}}/{{output_tokens}} tokens in {{session_duration}}: {{first_prompt}}"
+                    .to_string(),
+            ),
+            agents: AgentTemplateConfig::default(),
+            digest: None,
+        };
+
+        let message = render_announcement_message(&event, &templates, &empty_labels());
+        assert_eq!(
+            message,
+            "3 tools, 1 files, 6300/300 tokens in 2 minutes 45 seconds: Fix the flaky retry test and run the suite"
+        );
+    }
+
     #[test]
     fn render_falls_back_when_template_is_invalid() {
         let event = codex_event();
         let templates = TemplateConfig {
             global: Some("{{#if".to_string()),
             agents: AgentTemplateConfig::default(),
+            digest: None,
         };
 
         let message = render_announcement_message(&event, &templates, &empty_labels());
@@ -272,12 +521,54 @@ mod tests {
         assert_eq!(message, "Codex finished planning in backend.");
     }
 
+    #[test]
+    fn render_uses_builtin_attention_template_for_gemini_notifications() {
+        let event = normalize(
+            Agent::Gemini,
+            json!({
+                "hook_event_name": "Notification",
+                "notification_type": "ToolPermission",
+                "cwd": "/tmp/backend"
+            }),
+        )
+        .expect("expected gemini notification");
+        let templates = TemplateConfig::default();
+
+        let message = render_announcement_message(&event, &templates, &empty_labels());
+        assert_eq!(message, "Gemini needs your attention in backend.");
+    }
+
+    #[test]
+    fn render_uses_builtin_failure_templates() {
+        let failed = normalize(
+            Agent::Codex,
+            json!({"type": "agent-turn-failed", "cwd": "/tmp/backend"}),
+        )
+        .expect("expected codex failure");
+        let errored = normalize(
+            Agent::Claude,
+            json!({"hook_event_name": "StopFailure", "cwd": "/tmp/backend"}),
+        )
+        .expect("expected claude error");
+        let templates = TemplateConfig::default();
+
+        assert_eq!(
+            render_announcement_message(&failed, &templates, &empty_labels()),
+            "Codex failed in backend."
+        );
+        assert_eq!(
+            render_announcement_message(&errored, &templates, &empty_labels()),
+            "Claude hit an error in backend."
+        );
+    }
+
     #[test]
     fn render_uses_configured_global_event_kind_label() {
         let event = codex_event();
         let templates = TemplateConfig {
             global: Some("{{event_kind}}".to_string()),
             agents: AgentTemplateConfig::default(),
+            digest: None,
         };
         let labels = EventKindLabelsConfig {
             global: BTreeMap::from([("task-end".to_string(), "task".to_string())]),
@@ -294,6 +585,7 @@ mod tests {
         let templates = TemplateConfig {
             global: Some("{{event_kind}}".to_string()),
             agents: AgentTemplateConfig::default(),
+            digest: None,
         };
         let labels = EventKindLabelsConfig {
             global: BTreeMap::from([("plan-end".to_string(), "plan".to_string())]),
@@ -310,6 +602,7 @@ mod tests {
         let templates = TemplateConfig {
             global: Some("{{event_kind}}".to_string()),
             agents: AgentTemplateConfig::default(),
+            digest: None,
         };
         let labels = EventKindLabelsConfig {
             global: BTreeMap::from([("task-end".to_string(), "task".to_string())]),
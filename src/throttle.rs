@@ -0,0 +1,214 @@
+//! Rate limits announcements across the short-lived `ingest` processes: a token bucket per
+//! (agent, project) and a cooldown per (agent, project, kind). The state lives in
+//! `throttle.json` and is updated through [`lockfile::update`].
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::duration::humanize;
+use crate::lockfile;
+use crate::state::RateLimitConfig;
+
+pub const DEFAULT_REFILL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ThrottleState {
+    /// Keyed by `agent/project`.
+    #[serde(default)]
+    pub buckets: BTreeMap<String, Bucket>,
+    /// Unix timestamp of the last announcement, keyed by `agent/project/kind`.
+    #[serde(default)]
+    pub last_announced: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Bucket {
+    pub tokens: u32,
+    /// When `tokens` was last brought up to date; partial refills carry over.
+    pub updated: u64,
+}
+
+impl Bucket {
+    fn refill(&mut self, burst: u32, refill_secs: u64, now: u64) {
+        let elapsed = now.saturating_sub(self.updated);
+        let gained = elapsed.checked_div(refill_secs).unwrap_or(u64::MAX);
+        let tokens = u64::from(self.tokens).saturating_add(gained);
+        if tokens >= u64::from(burst) {
+            self.tokens = burst;
+            self.updated = now;
+        } else {
+            self.tokens = tokens as u32;
+            self.updated += gained * refill_secs;
+        }
+    }
+}
+
+impl ThrottleState {
+    /// Records an announcement of `kind` by `agent` in `project` at `now` and returns
+    /// `None`, or returns why it has to be skipped without recording anything.
+    pub fn check(
+        &mut self,
+        config: &RateLimitConfig,
+        agent: &str,
+        project: &str,
+        kind: &str,
+        now: u64,
+    ) -> Option<String> {
+        self.prune(config, now);
+
+        let cooldown_key = format!("{agent}/{project}/{kind}");
+        if let Some(cooldown) = config.cooldown_secs {
+            if let Some(last) = self.last_announced.get(&cooldown_key) {
+                if now < last.saturating_add(cooldown) {
+                    return Some(format!(
+                        "cooldown: {kind} from {agent} in {project} was announced {} ago",
+                        humanize(Duration::from_secs(now.saturating_sub(*last)))
+                    ));
+                }
+            }
+        }
+
+        if let Some(burst) = config.burst.filter(|burst| *burst > 0) {
+            let bucket = self
+                .buckets
+                .entry(format!("{agent}/{project}"))
+                .or_insert(Bucket {
+                    tokens: burst,
+                    updated: now,
+                });
+            bucket.refill(burst, config.refill_secs, now);
+            if bucket.tokens == 0 {
+                return Some(format!(
+                    "rate limited: {agent} used its {burst} announcement(s) for {project}, one more every {}",
+                    humanize(Duration::from_secs(config.refill_secs))
+                ));
+            }
+            bucket.tokens -= 1;
+        }
+
+        if config.cooldown_secs.is_some() {
+            self.last_announced.insert(cooldown_key, now);
+        }
+        None
+    }
+
+    /// Drops expired cooldowns and buckets that have refilled completely, so the file only
+    /// holds state that still matters.
+    fn prune(&mut self, config: &RateLimitConfig, now: u64) {
+        let cooldown = config.cooldown_secs.unwrap_or(0);
+        self.last_announced
+            .retain(|_, last| now < last.saturating_add(cooldown));
+
+        let burst = config.burst.unwrap_or(0);
+        self.buckets.retain(|_, bucket| {
+            bucket.refill(burst, config.refill_secs, now);
+            bucket.tokens < burst
+        });
+    }
+}
+
+/// Applies [`ThrottleState::check`] to the state at `path` while holding an exclusive
+/// lock on it, so concurrent `ingest` processes cannot both spend the last token.
+pub fn admit(
+    path: &Path,
+    config: &RateLimitConfig,
+    agent: &str,
+    project: &str,
+    kind: &str,
+    now: u64,
+) -> Result<Option<String>> {
+    if config.burst.is_none_or(|burst| burst == 0) && config.cooldown_secs.is_none() {
+        return Ok(None);
+    }
+
+    lockfile::update(path, |state: &mut ThrottleState| {
+        state.check(config, agent, project, kind, now)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(burst: Option<u32>, cooldown_secs: Option<u64>) -> RateLimitConfig {
+        RateLimitConfig {
+            burst,
+            refill_secs: 60,
+            cooldown_secs,
+        }
+    }
+
+    #[test]
+    fn bucket_allows_a_burst_then_refills() {
+        let config = config(Some(2), None);
+        let mut state = ThrottleState::default();
+
+        assert_eq!(state.check(&config, "Codex", "api", "task-end", 0), None);
+        assert_eq!(state.check(&config, "Codex", "api", "task-end", 10), None);
+        let limited = state.check(&config, "Codex", "api", "task-end", 20);
+        assert!(limited.is_some_and(|reason| reason.starts_with("rate limited")));
+        assert_eq!(state.check(&config, "Claude", "api", "task-end", 20), None);
+        assert_eq!(state.check(&config, "Codex", "web", "task-end", 20), None);
+
+        assert_eq!(state.check(&config, "Codex", "api", "task-end", 60), None);
+        assert!(state
+            .check(&config, "Codex", "api", "task-end", 70)
+            .is_some());
+    }
+
+    #[test]
+    fn cooldown_applies_per_kind() {
+        let config = config(None, Some(30));
+        let mut state = ThrottleState::default();
+
+        assert_eq!(state.check(&config, "Codex", "api", "task-end", 100), None);
+        assert_eq!(
+            state
+                .check(&config, "Codex", "api", "task-end", 110)
+                .as_deref(),
+            Some("cooldown: task-end from Codex in api was announced 10 seconds ago")
+        );
+        assert_eq!(
+            state.check(&config, "Codex", "api", "task-failed", 110),
+            None
+        );
+        assert_eq!(state.check(&config, "Codex", "api", "task-end", 130), None);
+    }
+
+    #[test]
+    fn prune_forgets_expired_state() {
+        let config = config(Some(1), Some(30));
+        let mut state = ThrottleState::default();
+        state.check(&config, "Codex", "api", "task-end", 0);
+        assert_eq!(state.buckets.len(), 1);
+
+        state.prune(&config, 60);
+        assert_eq!(state, ThrottleState::default());
+    }
+
+    #[test]
+    fn admit_persists_state_between_calls() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("throttle.json");
+        let config = config(Some(1), None);
+
+        assert_eq!(
+            admit(&path, &config, "Codex", "api", "task-end", 0).expect("admit"),
+            None
+        );
+        assert!(admit(&path, &config, "Codex", "api", "task-end", 5)
+            .expect("admit")
+            .is_some());
+
+        let unused = dir.path().join("unused.json");
+        let disabled = RateLimitConfig::default();
+        assert_eq!(
+            admit(&unused, &disabled, "Codex", "api", "task-end", 5).expect("admit"),
+            None
+        );
+        assert!(!unused.exists());
+    }
+}
@@ -0,0 +1,336 @@
+//! Turn start tracking persisted between short-lived `ingest` processes, and the Codex
+//! session logs that stand in for it.
+
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::agent::Agent;
+use crate::lockfile;
+
+const CLAUDE_PROMPT_EVENT: &str = "UserPromptSubmit";
+const CLAUDE_END_EVENTS: &[&str] = &["Stop", "StopFailure"];
+const GEMINI_PROMPT_EVENT: &str = "BeforeAgent";
+const GEMINI_END_EVENTS: &[&str] = &["AfterAgent"];
+const CODEX_TURN_END_TYPES: &[&str] = &["agent-turn-complete", "agent-turn-failed"];
+/// Codex session log entries that mark the start of a turn.
+const CODEX_TURN_START_TYPES: &[&str] = &["task_started", "user_message"];
+const STALE_AFTER_SECS: u64 = 7 * 86_400;
+/// Session logs are read backwards in chunks of this size until the turn start turns up.
+const CHUNK_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TurnState {
+    #[serde(default)]
+    pub sessions: BTreeMap<String, TurnStart>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TurnStart {
+    /// Unix timestamp in seconds.
+    pub started_at: u64,
+}
+
+impl TurnState {
+    /// Records turn starts and returns the elapsed turn time for completion payloads.
+    ///
+    /// Claude turns start at `UserPromptSubmit` and end at `Stop` or `StopFailure`; Gemini turns run from
+    /// `BeforeAgent` to `AfterAgent`. Codex only reports completions; see
+    /// [`codex_turn_duration`].
+    pub fn observe(&mut self, agent: Agent, payload: &Value, now: u64) -> Option<Duration> {
+        self.sessions
+            .retain(|_, start| now.saturating_sub(start.started_at) < STALE_AFTER_SECS);
+
+        match agent {
+            Agent::Claude => self.observe_hook_turn(
+                "claude",
+                CLAUDE_PROMPT_EVENT,
+                CLAUDE_END_EVENTS,
+                payload,
+                now,
+            ),
+            Agent::Gemini => self.observe_hook_turn(
+                "gemini",
+                GEMINI_PROMPT_EVENT,
+                GEMINI_END_EVENTS,
+                payload,
+                now,
+            ),
+            Agent::Codex | Agent::Opencode | Agent::Aider | Agent::Generic | Agent::Custom => None,
+        }
+    }
+
+    fn observe_hook_turn(
+        &mut self,
+        prefix: &str,
+        start_event: &str,
+        end_events: &[&str],
+        payload: &Value,
+        now: u64,
+    ) -> Option<Duration> {
+        let session = payload.get("session_id").and_then(Value::as_str)?;
+        let key = format!("{prefix}:{session}");
+        match payload.get("hook_event_name").and_then(Value::as_str)? {
+            event if event == start_event => {
+                self.sessions.insert(key, TurnStart { started_at: now });
+                None
+            }
+            event if end_events.contains(&event) => self
+                .sessions
+                .remove(&key)
+                .map(|start| elapsed(start.started_at, now)),
+            _ => self
+                .sessions
+                .get(&key)
+                .map(|start| elapsed(start.started_at, now)),
+        }
+    }
+}
+
+/// Records turn starts in the state at `path` under the file lock and returns the elapsed
+/// turn time for completion payloads.
+pub fn track(path: &Path, agent: Agent, payload: &Value, now: u64) -> Result<Option<Duration>> {
+    if !matches!(agent, Agent::Claude | Agent::Gemini) {
+        return Ok(None);
+    }
+    lockfile::update(path, |state: &mut TurnState| {
+        state.observe(agent, payload, now)
+    })
+}
+
+/// Codex only reports completions, so a turn is measured from the last `task_started` or
+/// `user_message` entry in the thread's session log under `sessions_dir`
+/// (`YYYY/MM/DD/rollout-*-<thread-id>.jsonl`). `None` without a log.
+pub fn codex_turn_duration(
+    sessions_dir: &Path,
+    payload: &Value,
+    now: u64,
+) -> Result<Option<Duration>> {
+    let kind = payload.get("type").and_then(Value::as_str);
+    if !kind.is_some_and(|kind| CODEX_TURN_END_TYPES.contains(&kind)) {
+        return Ok(None);
+    }
+    let Some(thread) = payload.get("thread-id").and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    let Some(log) = find_codex_session_log(sessions_dir, thread)? else {
+        return Ok(None);
+    };
+    Ok(last_codex_turn_start(&log)?.map(|started_at| elapsed(started_at, now)))
+}
+
+/// Newest day directories first, since the thread being reported is usually recent.
+fn find_codex_session_log(sessions_dir: &Path, thread: &str) -> Result<Option<PathBuf>> {
+    let suffix = format!("-{thread}.jsonl");
+    let mut days = vec![sessions_dir.to_path_buf()];
+    for _ in 0..3 {
+        let mut children = Vec::new();
+        for dir in &days {
+            children.extend(sorted_dirs(dir)?);
+        }
+        days = children;
+    }
+    for day in days {
+        let entries =
+            fs::read_dir(&day).with_context(|| format!("failed to read {}", day.display()))?;
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().ends_with(&suffix) {
+                return Ok(Some(entry.path()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Subdirectories of `dir`, newest (highest name) first; none when `dir` is missing.
+fn sorted_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut dirs = fs::read_dir(dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    dirs.sort_unstable_by(|left, right| right.cmp(left));
+    Ok(dirs)
+}
+
+/// Reads the log backwards so only the current turn is parsed, however long the session.
+fn last_codex_turn_start(path: &Path) -> Result<Option<u64>> {
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut end = file
+        .metadata()
+        .with_context(|| format!("failed to read {}", path.display()))?
+        .len();
+    let mut carried = Vec::new();
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK_BYTES);
+        let mut chunk = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start))
+            .and_then(|_| file.read_exact(&mut chunk))
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        chunk.append(&mut carried);
+
+        let mut lines = chunk.split(|byte| *byte == b'\n').collect::<Vec<_>>();
+        // The first line may continue in the previous chunk.
+        let partial = if start > 0 {
+            lines.remove(0).to_vec()
+        } else {
+            Vec::new()
+        };
+        if let Some(started_at) = lines.iter().rev().find_map(|line| codex_turn_start(line)) {
+            return Ok(Some(started_at));
+        }
+        carried = partial;
+        end = start;
+    }
+    Ok(None)
+}
+
+fn codex_turn_start(line: &[u8]) -> Option<u64> {
+    let entry = serde_json::from_slice::<Value>(line).ok()?;
+    if entry.get("type").and_then(Value::as_str) != Some("event_msg") {
+        return None;
+    }
+    let kind = entry.pointer("/payload/type").and_then(Value::as_str)?;
+    if !CODEX_TURN_START_TYPES.contains(&kind) {
+        return None;
+    }
+    let timestamp = entry.get("timestamp").and_then(Value::as_str)?;
+    let timestamp = DateTime::parse_from_rfc3339(timestamp).ok()?.timestamp();
+    u64::try_from(timestamp).ok()
+}
+
+fn elapsed(started_at: u64, now: u64) -> Duration {
+    Duration::from_secs(now.saturating_sub(started_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn claude_turn_runs_from_prompt_to_stop() {
+        let mut state = TurnState::default();
+        let prompt = json!({"hook_event_name": "UserPromptSubmit", "session_id": "s1"});
+        let subagent = json!({"hook_event_name": "SubagentStop", "session_id": "s1"});
+        let stop = json!({"hook_event_name": "Stop", "session_id": "s1"});
+
+        assert_eq!(state.observe(Agent::Claude, &prompt, 1_000), None);
+        assert_eq!(
+            state.observe(Agent::Claude, &subagent, 1_030),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            state.observe(Agent::Claude, &stop, 1_090),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(state.observe(Agent::Claude, &stop, 1_100), None);
+    }
+
+    #[test]
+    fn claude_stop_failure_ends_the_turn() {
+        let mut state = TurnState::default();
+        let prompt = json!({"hook_event_name": "UserPromptSubmit", "session_id": "s1"});
+        let failure = json!({"hook_event_name": "StopFailure", "session_id": "s1"});
+
+        state.observe(Agent::Claude, &prompt, 1_000);
+        assert_eq!(
+            state.observe(Agent::Claude, &failure, 1_020),
+            Some(Duration::from_secs(20))
+        );
+        assert!(state.sessions.is_empty());
+    }
+
+    #[test]
+    fn gemini_turn_runs_from_before_agent_to_after_agent() {
+        let mut state = TurnState::default();
+        let start = json!({"hook_event_name": "BeforeAgent", "session_id": "s1"});
+        let end = json!({"hook_event_name": "AfterAgent", "session_id": "s1"});
+
+        assert_eq!(state.observe(Agent::Gemini, &start, 2_000), None);
+        assert_eq!(
+            state.observe(Agent::Gemini, &end, 2_075),
+            Some(Duration::from_secs(75))
+        );
+        assert!(state.sessions.is_empty());
+    }
+
+    #[test]
+    fn codex_turn_runs_from_the_last_turn_start_in_the_session_log() {
+        let sessions = tempfile::tempdir().expect("tempdir");
+        let day = sessions.path().join("2026").join("10").join("18");
+        fs::create_dir_all(&day).expect("create day dir");
+        let event = |timestamp: &str, kind: &str| {
+            json!({"timestamp": timestamp, "type": "event_msg", "payload": {"type": kind}})
+                .to_string()
+        };
+        let mut log = vec![
+            event("2026-10-18T10:00:00Z", "user_message"),
+            event("2026-10-18T10:00:30Z", "agent_message"),
+            event("2026-10-18T10:30:00Z", "user_message"),
+            event("2026-10-18T10:30:01Z", "task_started"),
+        ];
+        // Enough output after the start to span several chunks.
+        let filler = json!({"timestamp": "2026-10-18T10:31:00Z", "type": "response_item",
+            "payload": {"text": "x".repeat(1000)}})
+        .to_string();
+        log.extend(std::iter::repeat_n(filler, 200));
+        fs::write(
+            day.join("rollout-2026-10-18T10-00-00-t1.jsonl"),
+            log.join("\n") + "\n",
+        )
+        .expect("write log");
+
+        let started = 1_792_319_401; // 2026-10-18T10:30:01Z
+        let turn = json!({"type": "agent-turn-complete", "thread-id": "t1", "turn-id": "2"});
+        assert_eq!(
+            codex_turn_duration(sessions.path(), &turn, started + 45).expect("duration"),
+            Some(Duration::from_secs(45))
+        );
+
+        let unknown = json!({"type": "agent-turn-complete", "thread-id": "t2"});
+        assert_eq!(
+            codex_turn_duration(sessions.path(), &unknown, started).expect("duration"),
+            None
+        );
+    }
+
+    #[test]
+    fn track_persists_turn_starts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("turns.json");
+        let prompt = json!({"hook_event_name": "UserPromptSubmit", "session_id": "s1"});
+        let stop = json!({"hook_event_name": "Stop", "session_id": "s1"});
+
+        assert_eq!(
+            track(&path, Agent::Claude, &prompt, 1_000).expect("track"),
+            None
+        );
+        assert_eq!(
+            track(&path, Agent::Claude, &stop, 1_060).expect("track"),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn prunes_stale_sessions() {
+        let mut state = TurnState::default();
+        let prompt = json!({"hook_event_name": "UserPromptSubmit", "session_id": "s1"});
+        let stop = json!({"hook_event_name": "Stop", "session_id": "s1"});
+
+        state.observe(Agent::Claude, &prompt, 0);
+        assert_eq!(state.observe(Agent::Claude, &stop, STALE_AFTER_SECS), None);
+    }
+}
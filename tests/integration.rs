@@ -1,8 +1,9 @@
 use agitiser_notify::agent::Agent;
 use agitiser_notify::event::{normalize, project_name_from_cwd};
-use agitiser_notify::integrations::{claude, codex};
+use agitiser_notify::integrations::{aider, claude, codex, gemini, opencode};
 use agitiser_notify::state::LocalState;
 use serde_json::json;
+use std::process::{Command, Output};
 use std::{fs, io::Write};
 use tempfile::{NamedTempFile, TempDir};
 
@@ -15,11 +16,12 @@ fn claude_setup_remove_round_trip() {
     let path = file.path().to_path_buf();
 
     let exe = std::path::Path::new("/tmp/agitiser-notify");
-    assert!(claude::setup(&path, exe).expect("setup"));
-    assert!(claude::is_configured(&path).expect("is_configured after setup"));
+    let hooks = claude::default_managed_hooks();
+    assert!(claude::setup(&path, exe, &hooks).expect("setup"));
+    assert!(claude::is_configured(&path, &hooks).expect("is_configured after setup"));
 
     assert!(claude::remove(&path).expect("remove"));
-    assert!(!claude::is_configured(&path).expect("is_configured after remove"));
+    assert!(!claude::is_configured(&path, &hooks).expect("is_configured after remove"));
 }
 
 // --- Codex setup/remove round-trip ---
@@ -201,6 +203,19 @@ fn normalize_codex_ignores_unknown_complete_type() {
     assert!(normalize(Agent::Codex, payload).is_none());
 }
 
+#[test]
+fn normalize_opencode_session_idle() {
+    let payload = json!({
+        "type": "session.idle",
+        "session_id": "ses_1",
+        "cwd": "/home/user/Projects/myapp"
+    });
+    let event = normalize(Agent::Opencode, payload).expect("should normalize");
+    assert_eq!(event.agent, Agent::Opencode);
+    assert_eq!(event.event_kind, "task-end");
+    assert_eq!(event.project_name, "myapp");
+}
+
 #[test]
 fn normalize_generic_completed_event() {
     let payload = json!({
@@ -359,6 +374,27 @@ fn temp_home() -> TempDir {
     tempfile::tempdir().expect("temp home dir")
 }
 
+/// Runs the binary with `--config-dir` pointing into `home`.
+fn run_cli(home: &TempDir, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_agitiser-notify"))
+        .arg("--config-dir")
+        .arg(home.path())
+        .args(args)
+        .output()
+        .expect("failed to run agitiser-notify")
+}
+
+/// `PATH` with the test binary's directory in front, so project hooks find it by name.
+fn path_with_binary() -> std::ffi::OsString {
+    let bin_dir = std::path::Path::new(env!("CARGO_BIN_EXE_agitiser-notify"))
+        .parent()
+        .expect("binary directory")
+        .to_path_buf();
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::join_paths(std::iter::once(bin_dir).chain(std::env::split_paths(&path)))
+        .expect("join PATH")
+}
+
 #[test]
 fn config_template_global_round_trip() {
     let bin = env!("CARGO_BIN_EXE_agitiser-notify");
@@ -612,6 +648,102 @@ fn config_subagent_round_trip() {
     );
 }
 
+#[test]
+fn config_claude_hooks_add_remove_round_trip() {
+    let bin = env!("CARGO_BIN_EXE_agitiser-notify");
+    let home = temp_home();
+
+    let add_output = std::process::Command::new(bin)
+        .args([
+            "config",
+            "claude-hooks",
+            "add",
+            "--event",
+            "PostToolUse",
+            "--matcher",
+            "Bash",
+        ])
+        .env("HOME", home.path())
+        .output()
+        .expect("failed to run claude-hooks add");
+    assert!(add_output.status.success());
+
+    let list_output = std::process::Command::new(bin)
+        .args(["config", "claude-hooks", "list"])
+        .env("HOME", home.path())
+        .output()
+        .expect("failed to run claude-hooks list");
+    assert!(list_output.status.success());
+    let listed = String::from_utf8_lossy(&list_output.stdout);
+    assert!(listed.contains("Stop *"));
+    assert!(listed.contains("PermissionRequest ExitPlanMode"));
+    assert!(listed.contains("PostToolUse Bash"));
+
+    let remove_output = std::process::Command::new(bin)
+        .args(["config", "claude-hooks", "remove", "--event", "PostToolUse"])
+        .env("HOME", home.path())
+        .output()
+        .expect("failed to run claude-hooks remove");
+    assert!(remove_output.status.success());
+
+    let list_after_remove = std::process::Command::new(bin)
+        .args(["config", "claude-hooks", "list"])
+        .env("HOME", home.path())
+        .output()
+        .expect("failed to run claude-hooks list after remove");
+    assert!(!String::from_utf8_lossy(&list_after_remove.stdout).contains("PostToolUse"));
+}
+
+#[test]
+fn config_claude_hooks_store_only_changes_from_the_defaults() {
+    let home = temp_home();
+
+    let add = run_cli(
+        &home,
+        &[
+            "config",
+            "claude-hooks",
+            "add",
+            "--event",
+            "PostToolUse",
+            "--matcher",
+            "Bash",
+        ],
+    );
+    assert!(add.status.success());
+    let remove = run_cli(
+        &home,
+        &[
+            "config",
+            "claude-hooks",
+            "remove",
+            "--event",
+            "SubagentStop",
+        ],
+    );
+    assert!(remove.status.success());
+
+    let state =
+        fs::read_to_string(home.path().join("agitiser-notify").join("config.toml")).expect("state");
+    assert!(state.contains("PostToolUse"));
+    assert!(state.contains("SubagentStop"));
+    assert!(!state.contains("UserPromptSubmit"));
+
+    let listed = run_cli(&home, &["config", "claude-hooks", "list"]);
+    let listed = String::from_utf8_lossy(&listed.stdout);
+    assert!(listed.contains("UserPromptSubmit *"));
+    assert!(listed.contains("PostToolUse Bash"));
+    assert!(!listed.contains("SubagentStop"));
+
+    let restore = run_cli(
+        &home,
+        &["config", "claude-hooks", "add", "--event", "SubagentStop"],
+    );
+    assert!(restore.status.success());
+    let listed = run_cli(&home, &["config", "claude-hooks", "list"]);
+    assert!(String::from_utf8_lossy(&listed.stdout).contains("SubagentStop *"));
+}
+
 #[test]
 fn ingest_claude_subagent_skips_when_disabled() {
     let bin = env!("CARGO_BIN_EXE_agitiser-notify");
@@ -640,3 +772,1278 @@ fn ingest_claude_subagent_skips_when_disabled() {
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(stderr.contains("subagent notification disabled"));
 }
+
+#[test]
+fn claude_project_and_local_scope_round_trip() {
+    let bin = env!("CARGO_BIN_EXE_agitiser-notify");
+    let home = temp_home();
+    let project = tempfile::tempdir().expect("temp project dir");
+
+    for (scope, file_name) in [
+        ("project", "settings.json"),
+        ("local", "settings.local.json"),
+    ] {
+        let setup_output = std::process::Command::new(bin)
+            .args([
+                "setup",
+                "--agents",
+                "claude,codex",
+                "--scope",
+                scope,
+                "--dir",
+            ])
+            .arg(project.path())
+            .env("HOME", home.path())
+            .output()
+            .expect("failed to run scoped setup");
+        assert!(setup_output.status.success());
+        let stdout = String::from_utf8_lossy(&setup_output.stdout);
+        assert!(stdout.contains("Codex setup: skipped"));
+
+        let settings_path = project.path().join(".claude").join(file_name);
+        let hooks = claude::default_managed_hooks();
+        assert!(claude::is_configured(&settings_path, &hooks).expect("is_configured"));
+        // Project settings are committed, so they run the command from PATH.
+        let raw = fs::read_to_string(&settings_path).expect("read settings");
+        assert_eq!(raw.contains(bin), scope == "local", "{raw}");
+        assert!(!home.path().join(".claude").join("settings.json").exists());
+        assert!(!home.path().join(".codex").join("config.toml").exists());
+    }
+
+    let doctor_output = std::process::Command::new(bin)
+        .args(["doctor", "--dir"])
+        .arg(project.path())
+        .env("PATH", path_with_binary())
+        .env("HOME", home.path())
+        .output()
+        .expect("failed to run doctor");
+    let stdout = String::from_utf8_lossy(&doctor_output.stdout);
+    assert!(stdout.contains("[info] claude (user): managed hooks not configured"));
+    assert!(stdout.contains("[ok] claude (project)"));
+    assert!(stdout.contains("[ok] claude (local)"));
+    assert!(stdout.contains("several scopes (project, local)"));
+
+    let remove_output = std::process::Command::new(bin)
+        .args(["remove", "--agents", "claude", "--scope", "local", "--dir"])
+        .arg(project.path())
+        .env("HOME", home.path())
+        .output()
+        .expect("failed to run scoped remove");
+    assert!(remove_output.status.success());
+    let hooks = claude::default_managed_hooks();
+    let local_path = project.path().join(".claude").join("settings.local.json");
+    let project_path = project.path().join(".claude").join("settings.json");
+    assert!(!claude::is_configured(&local_path, &hooks).expect("is_configured local"));
+    assert!(claude::is_configured(&project_path, &hooks).expect("is_configured project"));
+}
+
+#[test]
+fn aider_setup_remove_restores_previous_notifications_command() {
+    let home = temp_home();
+    let config_path = home.path().join("aider").join(".aider.conf.yml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    let original = "# personal settings\nmodel: sonnet\nnotifications-command: notify-send aider\n";
+    fs::write(&config_path, original).unwrap();
+
+    let setup_output = run_cli(&home, &["setup", "--agents", "aider"]);
+    assert!(setup_output.status.success());
+    assert!(aider::is_configured(&config_path).expect("is_configured after setup"));
+    let configured = fs::read_to_string(&config_path).expect("read aider config");
+    assert!(configured.starts_with("# personal settings\nmodel: sonnet\n"));
+    assert!(configured.contains("notifications: true"));
+
+    let remove_output = run_cli(&home, &["remove", "--agents", "aider"]);
+    assert!(remove_output.status.success());
+    assert!(!aider::is_configured(&config_path).expect("is_configured after remove"));
+    let restored = fs::read_to_string(&config_path).expect("read aider config");
+    assert_eq!(
+        restored,
+        "# personal settings\nmodel: sonnet\nnotifications-command: \"notify-send aider\"\n"
+    );
+}
+
+#[test]
+fn gemini_user_and_project_scope_round_trip() {
+    let bin = env!("CARGO_BIN_EXE_agitiser-notify");
+    let home = temp_home();
+    let project = tempfile::tempdir().expect("temp project dir");
+    let project_settings = project.path().join(".gemini").join("settings.json");
+    fs::create_dir_all(project_settings.parent().unwrap()).unwrap();
+    fs::write(&project_settings, r#"{"theme":"GitHub"}"#).unwrap();
+
+    for args in [
+        vec!["setup", "--agents", "gemini"],
+        vec!["setup", "--agents", "gemini", "--scope", "project", "--dir"],
+    ] {
+        let output = std::process::Command::new(bin)
+            .args(&args)
+            .args(args.contains(&"--dir").then(|| project.path()))
+            .env("HOME", home.path())
+            .env_remove("GEMINI_CLI_HOME")
+            .output()
+            .expect("failed to run gemini setup");
+        assert!(output.status.success());
+    }
+    let user_settings = home.path().join(".gemini").join("settings.json");
+    assert!(gemini::is_configured(&user_settings).expect("is_configured user"));
+    assert!(gemini::is_configured(&project_settings).expect("is_configured project"));
+    let raw = fs::read_to_string(&project_settings).expect("read project settings");
+    assert!(raw.contains("\"theme\": \"GitHub\""));
+
+    let doctor_output = std::process::Command::new(bin)
+        .args(["doctor", "--dir"])
+        .arg(project.path())
+        .env("PATH", path_with_binary())
+        .env("HOME", home.path())
+        .env_remove("GEMINI_CLI_HOME")
+        .output()
+        .expect("failed to run doctor");
+    let stdout = String::from_utf8_lossy(&doctor_output.stdout);
+    assert!(stdout.contains("[ok] gemini (user)"));
+    assert!(stdout.contains("[ok] gemini (project)"));
+
+    let remove_output = std::process::Command::new(bin)
+        .args([
+            "remove", "--agents", "gemini", "--scope", "project", "--dir",
+        ])
+        .arg(project.path())
+        .env("HOME", home.path())
+        .env_remove("GEMINI_CLI_HOME")
+        .output()
+        .expect("failed to run gemini remove");
+    assert!(remove_output.status.success());
+    assert!(!gemini::is_configured(&project_settings).expect("is_configured project"));
+    assert!(gemini::is_configured(&user_settings).expect("is_configured user"));
+}
+
+#[test]
+fn opencode_setup_remove_round_trip() {
+    let plugin_dir = tempfile::tempdir().expect("temp plugin dir");
+    let exe = std::path::Path::new("/tmp/agitiser-notify");
+
+    assert!(opencode::setup(plugin_dir.path(), exe).expect("setup"));
+    assert!(!opencode::setup(plugin_dir.path(), exe).expect("second setup"));
+    assert!(opencode::is_configured(plugin_dir.path()).expect("is_configured after setup"));
+
+    assert!(opencode::remove(plugin_dir.path()).expect("remove"));
+    assert!(!opencode::remove(plugin_dir.path()).expect("second remove"));
+    assert!(!opencode::is_configured(plugin_dir.path()).expect("is_configured after remove"));
+}
+
+#[test]
+fn opencode_setup_refuses_to_overwrite_unmanaged_plugin() {
+    let plugin_dir = tempfile::tempdir().expect("temp plugin dir");
+    let plugin_path = opencode::plugin_path(plugin_dir.path());
+    fs::write(&plugin_path, "export const Mine = async () => ({});\n").unwrap();
+
+    let exe = std::path::Path::new("/tmp/agitiser-notify");
+    assert!(opencode::setup(plugin_dir.path(), exe).is_err());
+    assert!(!opencode::remove(plugin_dir.path()).expect("remove"));
+    assert!(plugin_path.exists());
+}
+
+#[test]
+fn opencode_user_and_project_scope_round_trip() {
+    let bin = env!("CARGO_BIN_EXE_agitiser-notify");
+    let home = temp_home();
+    let project = tempfile::tempdir().expect("temp project dir");
+
+    let setup_output = run_cli(&home, &["setup", "--agents", "opencode"]);
+    assert!(setup_output.status.success());
+    let user_plugin = home.path().join("opencode").join("plugin");
+    let source = fs::read_to_string(opencode::plugin_path(&user_plugin)).expect("read plugin");
+    assert!(source.contains("\"--config-dir\""));
+
+    for scope in ["project", "local"] {
+        let output = std::process::Command::new(bin)
+            .args(["--config-dir"])
+            .arg(home.path())
+            .args(["setup", "--agents", "opencode", "--scope", scope, "--dir"])
+            .arg(project.path())
+            .output()
+            .expect("failed to run scoped setup");
+        assert!(output.status.success());
+    }
+    let project_plugin = project.path().join(".opencode").join("plugin");
+    assert!(opencode::is_configured(&project_plugin).expect("is_configured project"));
+
+    let doctor_output = std::process::Command::new(bin)
+        .args(["--config-dir"])
+        .arg(home.path())
+        .args(["doctor", "--dir"])
+        .arg(project.path())
+        .env("PATH", path_with_binary())
+        .output()
+        .expect("failed to run doctor");
+    let stdout = String::from_utf8_lossy(&doctor_output.stdout);
+    assert!(stdout.contains("[ok] opencode (user)"));
+    assert!(stdout.contains("[ok] opencode (project)"));
+
+    let remove_output = std::process::Command::new(bin)
+        .args(["--config-dir"])
+        .arg(home.path())
+        .args([
+            "remove", "--agents", "opencode", "--scope", "project", "--dir",
+        ])
+        .arg(project.path())
+        .output()
+        .expect("failed to run scoped remove");
+    assert!(remove_output.status.success());
+    assert!(!opencode::is_configured(&project_plugin).expect("is_configured project"));
+    assert!(opencode::is_configured(&user_plugin).expect("is_configured user"));
+}
+
+#[test]
+fn config_paths_honour_environment_overrides() {
+    let bin = env!("CARGO_BIN_EXE_agitiser-notify");
+    let home = temp_home();
+    let claude_dir = home.path().join("alt-claude");
+    let codex_dir = home.path().join("alt-codex");
+    let xdg_dir = home.path().join("xdg");
+
+    let setup_output = std::process::Command::new(bin)
+        .args(["setup", "--agents", "claude,codex"])
+        .env("HOME", home.path())
+        .env("CLAUDE_CONFIG_DIR", &claude_dir)
+        .env("CODEX_HOME", &codex_dir)
+        .env("XDG_CONFIG_HOME", &xdg_dir)
+        .output()
+        .expect("failed to run setup");
+    assert!(setup_output.status.success());
+    assert!(claude_dir.join("settings.json").exists());
+    assert!(codex_dir.join("config.toml").exists());
+    assert!(!home.path().join(".claude").exists());
+    assert!(!home.path().join(".codex").exists());
+
+    let set_output = std::process::Command::new(bin)
+        .args(["config", "subagent", "set", "--enabled", "false"])
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", &xdg_dir)
+        .output()
+        .expect("failed to run subagent set");
+    assert!(set_output.status.success());
+    assert!(xdg_dir.join("agitiser-notify").join("config.toml").exists());
+}
+
+#[test]
+fn config_dir_flag_isolates_all_config_locations() {
+    let bin = env!("CARGO_BIN_EXE_agitiser-notify");
+    let home = temp_home();
+    let profile = home.path().join("profile");
+
+    let setup_output = std::process::Command::new(bin)
+        .args(["--config-dir"])
+        .arg(&profile)
+        .args(["setup", "--agents", "claude,codex"])
+        .env("HOME", home.path())
+        .env("CLAUDE_CONFIG_DIR", home.path().join("ignored"))
+        .output()
+        .expect("failed to run setup");
+    assert!(setup_output.status.success());
+
+    let settings_path = profile.join("claude").join("settings.json");
+    let settings = fs::read_to_string(&settings_path).expect("read claude settings");
+    assert!(settings.contains("--config-dir"));
+    let codex_config =
+        fs::read_to_string(profile.join("codex").join("config.toml")).expect("read codex config");
+    assert!(codex_config.contains("--config-dir"));
+    assert!(!home.path().join("ignored").exists());
+
+    let set_output = std::process::Command::new(bin)
+        .args([
+            "config",
+            "subagent",
+            "set",
+            "--enabled",
+            "false",
+            "--config-dir",
+        ])
+        .arg(&profile)
+        .env("HOME", home.path())
+        .output()
+        .expect("failed to run subagent set");
+    assert!(set_output.status.success());
+    assert!(profile.join("agitiser-notify").join("config.toml").exists());
+    assert!(!home.path().join(".config").exists());
+
+    let hooks = claude::default_managed_hooks();
+    assert!(claude::is_configured(&settings_path, &hooks).expect("is_configured"));
+}
+
+#[test]
+fn ingest_codex_chains_previous_notify_command() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = env!("CARGO_BIN_EXE_agitiser-notify");
+    let home = temp_home();
+    let profile = home.path().join("profile");
+    let log_path = home.path().join("previous-notify.log");
+    let stub_path = home.path().join("previous-notify.sh");
+    fs::write(
+        &stub_path,
+        format!(
+            "#!/bin/sh\nprintf '%s\\n' \"$@\" >> '{}'\n",
+            log_path.display()
+        ),
+    )
+    .expect("write stub notifier");
+    fs::set_permissions(&stub_path, fs::Permissions::from_mode(0o755)).expect("chmod stub");
+
+    let codex_home = profile.join("codex");
+    fs::create_dir_all(&codex_home).expect("create codex home");
+    fs::write(
+        codex_home.join("config.toml"),
+        format!(r#"notify = ["{}", "--from-codex"]"#, stub_path.display()),
+    )
+    .expect("write codex config");
+
+    let run = |args: &[&str]| {
+        std::process::Command::new(bin)
+            .arg("--config-dir")
+            .arg(&profile)
+            .args(args)
+            .env("HOME", home.path())
+            .output()
+            .expect("failed to run binary")
+    };
+
+    assert!(run(&["setup", "--agents", "codex"]).status.success());
+    assert!(run(&["config", "codex-chain", "set", "--enabled", "true"])
+        .status
+        .success());
+
+    let payload = r#"{"type":"agent-turn-start","cwd":"/tmp/demo"}"#;
+    let output = run(&["ingest", "--agent", "codex", "--verbose", payload]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("chained previous codex notify command"));
+
+    let logged = fs::read_to_string(&log_path).expect("stub notifier should have run");
+    assert_eq!(logged, format!("--from-codex\n{payload}\n"));
+}
+
+#[test]
+fn ingest_skips_turns_shorter_than_min_duration() {
+    let home = temp_home();
+
+    let set_output = run_cli(
+        &home,
+        &[
+            "config",
+            "min-duration",
+            "set",
+            "--agent",
+            "claude",
+            "--value",
+            "2m",
+        ],
+    );
+    assert!(set_output.status.success());
+    let get_output = run_cli(
+        &home,
+        &["config", "min-duration", "get", "--agent", "claude"],
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&get_output.stdout).trim(),
+        "2 minutes"
+    );
+
+    let prompt = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "claude",
+            "--verbose",
+            r#"{"hook_event_name":"UserPromptSubmit","session_id":"s1","cwd":"/tmp/demo"}"#,
+        ],
+    );
+    assert!(prompt.status.success());
+    assert!(String::from_utf8_lossy(&prompt.stderr).contains("not a terminal event"));
+
+    let stop = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "claude",
+            "--verbose",
+            r#"{"hook_event_name":"Stop","session_id":"s1","cwd":"/tmp/demo"}"#,
+        ],
+    );
+    assert!(stop.status.success());
+    assert!(String::from_utf8_lossy(&stop.stderr).contains("below minimum duration of 2 minutes"));
+}
+
+#[test]
+fn ingest_custom_agent_uses_declared_definition() {
+    let home = temp_home();
+    let state_dir = home.path().join("agitiser-notify");
+    fs::create_dir_all(&state_dir).unwrap();
+    fs::write(
+        state_dir.join("config.toml"),
+        r#"
+[custom_agents.builder]
+display_name = "Build Bot"
+event_field = "event.type"
+kinds = { "run.finished" = "task-end" }
+template = "{{agent}} finished in {{project}}"
+"#,
+    )
+    .unwrap();
+
+    let ingest = |args: &[&str]| run_cli(&home, &[&["ingest", "--verbose"], args].concat());
+
+    let missing_name = ingest(&["--agent", "custom", "--payload", "{}"]);
+    assert!(!missing_name.status.success());
+
+    assert!(run_cli(&home, &["mute"]).status.success());
+    let unknown = ingest(&[
+        "--agent",
+        "custom",
+        "--name",
+        "other",
+        "--payload",
+        r#"{"type":"run-finished","cwd":"/srv/api"}"#,
+    ]);
+    assert!(unknown.status.success());
+    let stderr = String::from_utf8_lossy(&unknown.stderr);
+    assert!(stderr.contains("unknown custom agent other"));
+    assert!(stderr.contains("using the default name"));
+    let quiet_unknown = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "custom",
+            "--name",
+            "other",
+            "--payload",
+            r#"{"type":"run-finished","cwd":"/srv/api"}"#,
+        ],
+    );
+    assert!(quiet_unknown.status.success());
+    assert!(quiet_unknown.stderr.is_empty());
+    let history = run_cli(&home, &["history"]);
+    let history = String::from_utf8_lossy(&history.stdout);
+    assert!(
+        history.contains("Agent  run-finished  api  queued"),
+        "{history}"
+    );
+
+    let unmapped = ingest(&[
+        "--agent",
+        "custom",
+        "--name",
+        "builder",
+        "--payload",
+        r#"{"event":{"type":"run.stopped"}}"#,
+    ]);
+    assert!(unmapped.status.success());
+    assert!(String::from_utf8_lossy(&unmapped.stderr).contains("not a terminal event"));
+
+    let doctor_output = run_cli(&home, &["doctor"]);
+    let stdout = String::from_utf8_lossy(&doctor_output.stdout);
+    assert!(stdout.contains("[ok] custom agent builder: 1 event kind(s) mapped from event.type"));
+    assert!(stdout.contains("[warn] custom agent other: ingested but not declared"));
+}
+
+#[test]
+fn config_generic_round_trip_and_precise_terminal_kinds() {
+    let home = temp_home();
+
+    let undone = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "generic",
+            "--verbose",
+            "--payload",
+            r#"{"type":"undone","cwd":"/tmp"}"#,
+        ],
+    );
+    assert!(undone.status.success());
+    assert!(String::from_utf8_lossy(&undone.stderr).contains("not a terminal event"));
+
+    let invalid = run_cli(
+        &home,
+        &["config", "generic", "set", "--terminal-pattern", "("],
+    );
+    assert!(!invalid.status.success());
+
+    let set = run_cli(
+        &home,
+        &[
+            "config",
+            "generic",
+            "set",
+            "--kind-field",
+            "status.phase",
+            "--terminal-kinds",
+            "succeeded,failed",
+        ],
+    );
+    assert!(set.status.success());
+    let get = run_cli(&home, &["config", "generic", "get"]);
+    let stdout = String::from_utf8_lossy(&get.stdout);
+    assert!(stdout.contains("kind_field = status.phase"));
+    assert!(stdout.contains("terminal_kinds = succeeded,failed"));
+    assert!(stdout.contains("cwd_field = <default>"));
+
+    let done = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "generic",
+            "--verbose",
+            "--payload",
+            r#"{"status":{"phase":"done"},"cwd":"/tmp"}"#,
+        ],
+    );
+    assert!(done.status.success());
+    assert!(String::from_utf8_lossy(&done.stderr).contains("not a terminal event"));
+
+    let reset = run_cli(&home, &["config", "generic", "reset"]);
+    assert!(String::from_utf8_lossy(&reset.stdout).contains("generic agent settings reset"));
+}
+
+// --- Per-kind speech settings ---
+
+#[test]
+fn config_speech_round_trip_with_failure_defaults() {
+    let home = temp_home();
+
+    let defaults = run_cli(&home, &["config", "speech", "get", "--kind", "task-failed"]);
+    let stdout = String::from_utf8_lossy(&defaults.stdout);
+    assert!(stdout.contains("priority = important"));
+    assert!(stdout.contains("voice = <default>"));
+    assert!(stdout.contains("dialog-error.oga"));
+
+    let empty = run_cli(&home, &["config", "speech", "set", "--kind", "task-failed"]);
+    assert!(!empty.status.success());
+
+    let set = run_cli(
+        &home,
+        &[
+            "config",
+            "speech",
+            "set",
+            "--kind",
+            "Task-Failed",
+            "--voice",
+            "child_female",
+            "--earcon",
+            "",
+        ],
+    );
+    assert!(
+        String::from_utf8_lossy(&set.stdout).contains("speech settings for task-failed updated")
+    );
+    let get = run_cli(&home, &["config", "speech", "get", "--kind", "task-failed"]);
+    let stdout = String::from_utf8_lossy(&get.stdout);
+    assert!(stdout.contains("priority = important"));
+    assert!(stdout.contains("voice = child_female"));
+    assert!(stdout.contains("earcon = <none>"));
+
+    let reset = run_cli(
+        &home,
+        &["config", "speech", "reset", "--kind", "task-failed"],
+    );
+    assert!(
+        String::from_utf8_lossy(&reset.stdout).contains("speech settings for task-failed reset")
+    );
+    let again = run_cli(
+        &home,
+        &["config", "speech", "reset", "--kind", "task-failed"],
+    );
+    assert!(String::from_utf8_lossy(&again.stdout).contains("already default"));
+}
+
+// --- run -- <command> ---
+
+#[test]
+fn run_propagates_exit_code_and_reports_the_outcome() {
+    let home = temp_home();
+
+    // Keep the test silent: successful runs are shorter than the minimum duration and
+    // failures, which are announced however quick, are held back while muted.
+    assert!(run_cli(&home, &["mute"]).status.success());
+    let set = run_cli(
+        &home,
+        &[
+            "config",
+            "min-duration",
+            "set",
+            "--agent",
+            "generic",
+            "--value",
+            "1h",
+        ],
+    );
+    assert!(set.status.success());
+
+    let failed = run_cli(
+        &home,
+        &[
+            "run",
+            "--verbose",
+            "--",
+            "sh",
+            "-c",
+            "echo streamed; exit 3",
+        ],
+    );
+    assert_eq!(failed.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&failed.stdout), "streamed\n");
+    assert!(String::from_utf8_lossy(&failed.stderr).contains("muted, queued for digest"));
+
+    let succeeded = run_cli(&home, &["run", "--verbose", "--", "true"]);
+    assert_eq!(succeeded.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&succeeded.stderr).contains("below minimum duration"));
+
+    let killed = run_cli(&home, &["run", "--", "sh", "-c", "kill -TERM $$"]);
+    assert_eq!(killed.status.code(), Some(143));
+
+    let json = run_cli(
+        &home,
+        &[
+            "--output",
+            "json",
+            "run",
+            "--",
+            "sh",
+            "-c",
+            "echo streamed; exit 3",
+        ],
+    );
+    assert_eq!(json.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&json.stderr), "streamed\n");
+    let report: serde_json::Value = serde_json::from_slice(&json.stdout).expect("json report");
+    assert_eq!(report["results"][0]["id"], "run");
+    assert_eq!(report["results"][0]["value"]["delivery"], "queued");
+    assert_eq!(
+        report["results"][0]["message"],
+        "sh -c 'echo streamed; exit 3' exited with 3; queued: muted"
+    );
+
+    let missing = run_cli(&home, &["run", "--", "agitiser-notify-no-such-command"]);
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr)
+        .contains("failed to run agitiser-notify-no-such-command"));
+}
+
+// --- Event history ---
+
+#[test]
+fn history_records_filtered_events_and_filters_queries() {
+    let home = temp_home();
+
+    let empty = run_cli(&home, &["history"]);
+    assert!(String::from_utf8_lossy(&empty.stdout).contains("no matching history entries"));
+
+    run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "claude",
+            "--source",
+            "claude-hook",
+            "--payload",
+            r#"{"hook_event_name":"SessionStart","cwd":"/srv/backend"}"#,
+        ],
+    );
+    run_cli(&home, &["config", "subagent", "set", "--enabled", "false"]);
+    run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "claude",
+            "--payload",
+            r#"{"hook_event_name":"SubagentStop","cwd":"/srv/frontend"}"#,
+        ],
+    );
+    run_cli(
+        &home,
+        &["ingest", "--agent", "codex", "--payload", "not json"],
+    );
+
+    let all = run_cli(&home, &["history"]);
+    let stdout = String::from_utf8_lossy(&all.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(lines[0].contains("Claude  -  backend  filtered: not a terminal event (SessionStart)"));
+    assert!(lines[1]
+        .contains("Claude  plan-end  frontend  filtered: claude subagent notifications disabled"));
+    assert!(lines[2].contains("Codex  -  -  filtered: invalid JSON payload"));
+
+    let json = run_cli(
+        &home,
+        &[
+            "--output",
+            "json",
+            "history",
+            "--agent",
+            "claude",
+            "--project",
+            "Frontend",
+            "--since",
+            "1h",
+        ],
+    );
+    let report: serde_json::Value = serde_json::from_slice(&json.stdout).expect("json report");
+    let entries = report["results"].as_array().expect("results");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["id"], "history");
+    assert_eq!(entries[0]["value"]["event_kind"], "plan-end");
+    assert_eq!(entries[0]["value"]["delivery"], "filtered");
+    assert_eq!(entries[0]["value"]["cwd"], "/srv/frontend");
+
+    let newest = run_cli(&home, &["history", "--limit", "1"]);
+    assert!(String::from_utf8_lossy(&newest.stdout).contains("Codex"));
+
+    let invalid = run_cli(&home, &["history", "--since", "soon"]);
+    assert!(!invalid.status.success());
+}
+
+// --- last ---
+
+#[test]
+fn last_prints_the_newest_announcements_oldest_first() {
+    let home = temp_home();
+
+    let empty = run_cli(&home, &["last"]);
+    assert!(String::from_utf8_lossy(&empty.stdout).contains("no announcements yet"));
+
+    let state_dir = home.path().join("agitiser-notify");
+    std::fs::create_dir_all(&state_dir).unwrap();
+    std::fs::write(
+        state_dir.join("recent.json"),
+        r#"{"entries":[
+            {"timestamp":100,"agent":"claude","event_kind":"task-end","message":"Claude finished a task in api"},
+            {"timestamp":200,"agent":"codex","event_kind":"task-failed","message":"Codex failed in backend."},
+            {"timestamp":300,"agent":"gemini","event_kind":"attention","message":"Gemini needs your attention in web."}
+        ]}"#,
+    )
+    .unwrap();
+
+    let last = run_cli(&home, &["last"]);
+    let stdout = String::from_utf8_lossy(&last.stdout);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("attention  Gemini needs your attention in web."));
+
+    let two = run_cli(&home, &["last", "--n", "2"]);
+    let stdout = String::from_utf8_lossy(&two.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("task-failed  Codex failed in backend."));
+    assert!(lines[1].ends_with("Gemini needs your attention in web."));
+}
+
+#[test]
+fn muted_events_are_queued_for_the_digest() {
+    let home = temp_home();
+
+    let invalid = run_cli(&home, &["config", "quiet", "set", "--hours", "22:00"]);
+    assert!(!invalid.status.success());
+    assert!(String::from_utf8_lossy(&invalid.stderr).contains("invalid quiet hours"));
+    let set = run_cli(&home, &["config", "quiet", "set", "--hours", "22:00-07:00"]);
+    assert!(String::from_utf8_lossy(&set.stdout).contains("quiet settings updated"));
+
+    let muted = run_cli(&home, &["mute"]);
+    assert!(String::from_utf8_lossy(&muted.stdout).contains("muted until unmute"));
+    for cwd in ["/tmp/backend", "/tmp/backend", "/tmp/infra"] {
+        let payload = format!(r#"{{"type":"agent-turn-complete","cwd":"{cwd}"}}"#);
+        let ingest = run_cli(
+            &home,
+            &[
+                "ingest",
+                "--agent",
+                "codex",
+                "--verbose",
+                "--payload",
+                &payload,
+            ],
+        );
+        assert!(ingest.status.success());
+        assert!(
+            String::from_utf8_lossy(&ingest.stderr).contains("ingest: muted, queued for digest")
+        );
+    }
+
+    let history = run_cli(&home, &["history"]);
+    assert!(
+        String::from_utf8_lossy(&history.stdout).contains("Codex  task-end  infra  queued: muted")
+    );
+
+    let preview = run_cli(&home, &["digest", "--dry-run"]);
+    assert_eq!(
+        String::from_utf8_lossy(&preview.stdout).trim(),
+        "While you were away: Codex finished 2 tasks in backend, Codex finished a task in infra."
+    );
+    run_cli(
+        &home,
+        &[
+            "config",
+            "template",
+            "set",
+            "--digest",
+            "--value",
+            "{{count}} missed: {{events}}",
+        ],
+    );
+    let templated = run_cli(&home, &["digest", "--dry-run"]);
+    assert!(
+        String::from_utf8_lossy(&templated.stdout).starts_with("3 missed: Codex finished 2 tasks")
+    );
+
+    let unmuted = run_cli(&home, &["unmute"]);
+    assert!(String::from_utf8_lossy(&unmuted.stdout).contains("unmuted; 3 missed announcement(s)"));
+    let quiet = run_cli(&home, &["config", "quiet", "get"]);
+    let stdout = String::from_utf8_lossy(&quiet.stdout);
+    assert!(stdout.contains("hours = 22:00-07:00"));
+    assert!(stdout.contains("muted = no"));
+}
+
+#[test]
+fn stats_summarizes_history_by_group() {
+    let home = temp_home();
+
+    let state_dir = home.path().join("agitiser-notify");
+    std::fs::create_dir_all(&state_dir).unwrap();
+    std::fs::write(
+        state_dir.join("history.jsonl"),
+        [
+            r#"{"timestamp":100,"agent":"claude","event_kind":"task-end","project":"backend","duration_secs":60,"delivery":"announced"}"#,
+            r#"{"timestamp":200,"agent":"claude","event_kind":"task-end","project":"backend","duration_secs":180,"delivery":"announced"}"#,
+            r#"{"timestamp":300,"agent":"codex","event_kind":"task-failed","project":"infra","delivery":"filtered","reason":"muted"}"#,
+        ]
+        .join("\n"),
+    )
+    .unwrap();
+
+    let text = run_cli(&home, &["stats", "--by", "agent"]);
+    let stdout = String::from_utf8_lossy(&text.stdout);
+    assert!(text.status.success(), "{stdout}");
+    assert!(stdout.contains("3 events: 2 announced, 1 filtered, median turn 2 minutes"));
+    assert!(stdout.contains("Claude  2 events: 2 announced, 0 filtered, median turn 2 minutes"));
+    assert!(stdout.contains("busiest projects: backend (2), infra (1)"));
+
+    let json = run_cli(&home, &["--output", "json", "stats", "--by", "kind"]);
+    let report: serde_json::Value = serde_json::from_slice(&json.stdout).expect("json report");
+    let stats = &report["results"][0]["value"];
+    assert_eq!(stats["by"], "kind");
+    assert_eq!(stats["groups"][0]["key"], "task-end");
+    assert_eq!(stats["groups"][0]["median_duration_secs"], 120);
+    assert_eq!(report["results"][1]["message"], "by kind:");
+    assert_eq!(report["results"][1]["status"], "info");
+
+    let recent = run_cli(&home, &["stats", "--since", "1h"]);
+    assert!(String::from_utf8_lossy(&recent.stdout).contains("no matching history entries"));
+}
+
+#[test]
+fn rate_limit_skips_announcements_once_the_bucket_is_empty() {
+    let home = temp_home();
+
+    let invalid = run_cli(&home, &["config", "rate-limit", "set", "--refill", "0"]);
+    assert!(!invalid.status.success());
+    let set = run_cli(
+        &home,
+        &[
+            "config",
+            "rate-limit",
+            "set",
+            "--burst",
+            "1",
+            "--refill",
+            "1h",
+            "--cooldown",
+            "5m",
+        ],
+    );
+    assert!(String::from_utf8_lossy(&set.stdout).contains("rate limit updated"));
+    let get = run_cli(&home, &["config", "rate-limit", "get"]);
+    let stdout = String::from_utf8_lossy(&get.stdout);
+    assert!(stdout.contains("burst = 1"));
+    assert!(stdout.contains("refill = 3600s"));
+    assert!(stdout.contains("cooldown = 300s"));
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    std::fs::write(
+        home.path().join("agitiser-notify").join("throttle.json"),
+        format!(r#"{{"buckets":{{"Codex/backend":{{"tokens":0,"updated":{now}}}}}}}"#),
+    )
+    .unwrap();
+
+    let ingest = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "codex",
+            "--verbose",
+            "--payload",
+            r#"{"type":"agent-turn-complete","cwd":"/tmp/backend"}"#,
+        ],
+    );
+    assert!(ingest.status.success());
+    let stderr = String::from_utf8_lossy(&ingest.stderr);
+    assert!(
+        stderr.contains("ingest: rate limited: Codex used its 1 announcement(s) for backend"),
+        "{stderr}"
+    );
+
+    let history = run_cli(&home, &["history"]);
+    assert!(String::from_utf8_lossy(&history.stdout).contains("backend  filtered: rate limited"));
+
+    let reset = run_cli(&home, &["config", "rate-limit", "reset"]);
+    assert!(String::from_utf8_lossy(&reset.stdout).contains("rate limit reset"));
+}
+
+#[test]
+fn repeated_stop_events_are_deduplicated() {
+    let home = temp_home();
+    let stop = r#"{"hook_event_name":"Stop","session_id":"abc","cwd":"/srv/api"}"#;
+
+    run_cli(&home, &["mute"]);
+    let first = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "claude",
+            "--verbose",
+            "--payload",
+            stop,
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&first.stderr);
+    assert!(stderr.contains("ingest: dedup key Claude/abc/completion is new within 10s"));
+    assert!(stderr.contains("queued for digest"));
+
+    let second = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "claude",
+            "--verbose",
+            "--payload",
+            stop,
+        ],
+    );
+    assert!(second.status.success());
+    assert!(String::from_utf8_lossy(&second.stderr)
+        .contains("ingest: duplicate of Claude/abc/completion seen"));
+    let history = run_cli(&home, &["history"]);
+    assert!(String::from_utf8_lossy(&history.stdout)
+        .contains("task-end  api  filtered: duplicate of Claude/abc/completion"));
+
+    let disabled = run_cli(&home, &["config", "dedup", "set", "--window", "0"]);
+    assert!(String::from_utf8_lossy(&disabled.stdout).contains("dedup window updated"));
+    let third = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "claude",
+            "--verbose",
+            "--payload",
+            stop,
+        ],
+    );
+    assert!(String::from_utf8_lossy(&third.stderr).contains("queued for digest"));
+    let get = run_cli(&home, &["config", "dedup", "get"]);
+    assert!(String::from_utf8_lossy(&get.stdout).contains("window = 0s"));
+
+    run_cli(&home, &["config", "dedup", "reset"]);
+    run_cli(&home, &["config", "subagent", "set", "--enabled", "false"]);
+    let subagent = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "claude",
+            "--payload",
+            r#"{"hook_event_name":"SubagentStop","session_id":"def","cwd":"/srv/api"}"#,
+        ],
+    );
+    assert!(subagent.status.success());
+    let stop = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "claude",
+            "--verbose",
+            "--payload",
+            r#"{"hook_event_name":"Stop","session_id":"def","cwd":"/srv/api"}"#,
+        ],
+    );
+    assert!(String::from_utf8_lossy(&stop.stderr).contains("queued for digest"));
+}
+
+#[test]
+fn config_rules_route_and_drop_events() {
+    let home = temp_home();
+    let infra = r#"{"type":"agent-turn-complete","cwd":"/srv/infra-prod","turn-id":"7"}"#;
+
+    let empty = run_cli(&home, &["config", "rules", "list"]);
+    assert!(String::from_utf8_lossy(&empty.stdout).contains("no rules configured"));
+
+    run_cli(&home, &["config", "subagent", "set", "--enabled", "false"]);
+    let added = run_cli(
+        &home,
+        &[
+            "config",
+            "rules",
+            "add",
+            "--name",
+            "infra-digest",
+            "--project",
+            "infra-*",
+            "--route-to",
+            "digest",
+        ],
+    );
+    assert!(
+        String::from_utf8_lossy(&added.stdout).contains("rule infra-digest added at position 1")
+    );
+    run_cli(
+        &home,
+        &[
+            "config",
+            "rules",
+            "add",
+            "--name",
+            "no-gemini",
+            "--agent",
+            "gemini",
+            "--action",
+            "drop",
+            "--reason",
+            "gemini is muted here",
+            "--position",
+            "1",
+        ],
+    );
+    let duplicate = run_cli(&home, &["config", "rules", "add", "--name", "no-gemini"]);
+    assert!(!duplicate.status.success());
+    let reserved = run_cli(
+        &home,
+        &["config", "rules", "remove", "--name", "claude-subagent"],
+    );
+    assert!(!reserved.status.success());
+
+    let list = run_cli(&home, &["config", "rules", "list"]);
+    let stdout = String::from_utf8_lossy(&list.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(lines[0]
+        .contains("claude-subagent (built-in): agent=claude hook_event_name=SubagentStop -> drop"));
+    assert!(lines[1].starts_with("1. no-gemini: agent=gemini -> drop"));
+    assert!(lines[2].starts_with("2. infra-digest: project=infra-* -> speak, route_to=digest"));
+
+    let test = run_cli(
+        &home,
+        &[
+            "config",
+            "rules",
+            "test",
+            "--agent",
+            "codex",
+            "--payload",
+            infra,
+        ],
+    );
+    assert!(String::from_utf8_lossy(&test.stdout).contains("matched rule infra-digest"));
+    let test = run_cli(
+        &home,
+        &[
+            "config",
+            "rules",
+            "test",
+            "--agent",
+            "gemini",
+            "--payload",
+            r#"{"hook_event_name":"AfterAgent","cwd":"/srv/web"}"#,
+        ],
+    );
+    assert!(String::from_utf8_lossy(&test.stdout)
+        .contains("matched rule no-gemini: drop (gemini is muted here)"));
+
+    let ingest = run_cli(
+        &home,
+        &[
+            "ingest",
+            "--agent",
+            "codex",
+            "--verbose",
+            "--payload",
+            infra,
+        ],
+    );
+    assert!(ingest.status.success());
+    assert!(String::from_utf8_lossy(&ingest.stderr)
+        .contains("ingest: routed to digest by rule infra-digest, queued for digest"));
+    let preview = run_cli(&home, &["digest", "--dry-run"]);
+    assert!(
+        String::from_utf8_lossy(&preview.stdout).contains("Codex finished a task in infra-prod")
+    );
+
+    let removed = run_cli(
+        &home,
+        &["config", "rules", "remove", "--name", "infra-digest"],
+    );
+    assert!(String::from_utf8_lossy(&removed.stdout).contains("rule infra-digest removed"));
+    let test = run_cli(
+        &home,
+        &[
+            "config",
+            "rules",
+            "test",
+            "--agent",
+            "codex",
+            "--payload",
+            infra,
+        ],
+    );
+    assert!(String::from_utf8_lossy(&test.stdout).contains("no rule matched"));
+}
+
+#[test]
+fn output_json_reports_checks_and_settings() {
+    let home = temp_home();
+    let run = |args: &[&str]| run_cli(&home, &[&["--output", "json"], args].concat());
+    let parse = |output: &std::process::Output| {
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("json output")
+    };
+
+    let doctor = parse(&run(&["doctor"]));
+    let checks = doctor["results"].as_array().expect("results");
+    let speech = checks
+        .iter()
+        .find(|check| check["id"] == "speech-dispatcher")
+        .expect("speech-dispatcher check");
+    assert!(matches!(speech["status"].as_str(), Some("ok" | "error")));
+    assert_eq!(doctor["ok"], speech["status"] == "ok");
+    let codex = checks
+        .iter()
+        .find(|check| check["id"] == "codex")
+        .expect("codex check");
+    assert_eq!(codex["status"], "info");
+    assert_eq!(
+        codex["message"],
+        "codex: managed notify command not configured"
+    );
+
+    let unset = parse(&run(&["config", "template", "get", "--agent", "codex"]));
+    assert_eq!(unset["ok"], true);
+    assert_eq!(unset["results"][0]["id"], "config.template.codex");
+    assert_eq!(unset["results"][0]["value"], serde_json::Value::Null);
+
+    let updated = parse(&run(&["config", "dedup", "set", "--window", "30s"]));
+    assert_eq!(updated["results"][0]["message"], "dedup window updated");
+    let window = parse(&run(&["config", "dedup", "get"]));
+    assert_eq!(window["results"][0]["id"], "config.dedup.window_secs");
+    assert_eq!(window["results"][0]["value"], 30);
+
+    let failed = run(&["config", "summary", "set", "--max-words", "0"]);
+    assert_eq!(failed.status.code(), Some(1));
+    let failed = parse(&failed);
+    assert_eq!(failed["ok"], false);
+    assert!(failed["error"]
+        .as_str()
+        .is_some_and(|error| error.contains("at least 1")));
+
+    let text = run_cli(&home, &["config", "dedup", "get"]);
+    assert_eq!(String::from_utf8_lossy(&text.stdout), "window = 30s\n");
+}
+
+#[test]
+fn doctor_fix_repairs_stale_hooks_and_malformed_codex_notify() {
+    let bin = env!("CARGO_BIN_EXE_agitiser-notify");
+    let home = temp_home();
+    let project = tempfile::tempdir().expect("temp project dir");
+    let dir = project.path().to_str().expect("utf-8 project dir");
+    let run = |args: &[&str]| run_cli(&home, &[args, &["--dir", dir]].concat());
+
+    assert!(run(&["setup", "--agents", "claude"]).status.success());
+    let settings_path = home.path().join("claude").join("settings.json");
+    let mut settings: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&settings_path).expect("read settings"))
+            .expect("parse settings");
+    let command = settings["hooks"]["Stop"][0]["hooks"][0]["command"]
+        .as_str()
+        .expect("managed command")
+        .to_string();
+    let stale = command.replace(bin, "/nonexistent/agitiser-notify");
+    for event in ["Stop", "StopFailure", "SubagentStop", "UserPromptSubmit"] {
+        settings["hooks"][event][0]["hooks"][0]["command"] = json!(stale);
+    }
+    settings["hooks"]
+        .as_object_mut()
+        .expect("hooks object")
+        .remove("PermissionRequest");
+    std::fs::write(&settings_path, settings.to_string()).expect("write settings");
+    let codex_path = home.path().join("codex").join("config.toml");
+    std::fs::create_dir_all(codex_path.parent().expect("codex dir")).expect("create codex dir");
+    std::fs::write(&codex_path, "notify = \"notify-send\"\n").expect("write codex config");
+
+    let before = String::from_utf8_lossy(&run(&["doctor"]).stdout).to_string();
+    assert!(before.contains(
+        "[error] claude (user): hook command runs /nonexistent/agitiser-notify, which does not exist"
+    ));
+    assert!(before.contains(
+        "[warn] claude (user): managed hooks missing for PermissionRequest (ExitPlanMode)"
+    ));
+    assert!(before.contains("[warn] codex: notify in"));
+
+    let fixed = String::from_utf8_lossy(&run(&["doctor", "--fix"]).stdout).to_string();
+    assert!(fixed.contains("fixed by `agitiser-notify setup --agents claude --scope user`"));
+    assert!(fixed.contains("fixed by `agitiser-notify setup --agents codex`"));
+
+    let after = String::from_utf8_lossy(&run(&["doctor"]).stdout).to_string();
+    assert!(after.contains("[ok] claude (user): managed hooks configured"));
+    assert!(after.contains("[ok] codex: managed notify command configured"));
+    assert!(!after.contains("[warn]"));
+}
+
+#[test]
+fn relink_rewrites_managed_entries_pointing_at_a_moved_binary() {
+    let bin = env!("CARGO_BIN_EXE_agitiser-notify");
+    let home = temp_home();
+    let settings_path = home.path().join("claude").join("settings.json");
+
+    assert!(run_cli(&home, &["setup", "--agents", "claude,codex"])
+        .status
+        .success());
+    let settings = fs::read_to_string(&settings_path).expect("read settings");
+    fs::write(
+        &settings_path,
+        settings.replace(bin, "/nonexistent/agitiser-notify"),
+    )
+    .expect("write settings");
+    let doctor = String::from_utf8_lossy(&run_cli(&home, &["doctor"]).stdout).to_string();
+    assert!(doctor.contains(
+        "[error] claude (user): hook command runs /nonexistent/agitiser-notify, which does not exist"
+    ));
+
+    let relink = run_cli(&home, &["relink"]);
+    assert!(relink.status.success());
+    let stdout = String::from_utf8_lossy(&relink.stdout);
+    assert!(stdout.contains(&format!(
+        "Claude relink: /nonexistent/agitiser-notify -> {bin} (user scope)"
+    )));
+    assert!(stdout.contains(&format!("Codex relink: already runs {bin} (user scope)")));
+    let settings = fs::read_to_string(&settings_path).expect("read settings");
+    assert!(settings.contains(bin));
+    assert!(!settings.contains("/nonexistent/agitiser-notify"));
+}